@@ -0,0 +1,431 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::fs;
+
+use crate::{
+    app::api::{
+        Api, ApiError,
+        structs::{LatestReleases, Updates},
+    },
+    config,
+};
+
+/// Version listings by account and updater URL, entitlements differ between accounts
+const UPDATES_FILE: &str = "updates.json";
+/// Latest releases by download URL
+const RELEASES_FILE: &str = "latest-releases.json";
+
+/// Last response returned by the API
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Cached<T> {
+    /// Unix timestamp of the last successful request
+    pub fetched: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(alias = "updates")]
+    pub value: T,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Freshness {
+    /// Confirmed by the API just now
+    Fresh,
+    /// From cache, within the freshness window
+    Cached,
+    /// From cache, older than the freshness window (offline or API unavailable)
+    Stale,
+}
+
+/// Version listing or releases together with their origin
+pub struct Listing<T> {
+    pub value: T,
+    pub fetched: u64,
+    pub freshness: Freshness,
+}
+
+type CacheFile<T> = HashMap<String, Cached<T>>;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+fn cache_path(file: &str) -> Option<PathBuf> {
+    config::cache_dir().ok().map(|dir| dir.join(file))
+}
+
+/// Cache is only an optimization, so errors are just logged
+async fn load_cache<T: DeserializeOwned>(path: Option<&Path>) -> CacheFile<T> {
+    let Some(path) = path else {
+        return CacheFile::new();
+    };
+
+    match fs::read_to_string(path).await {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_else(|err| {
+            log::warn!("Unable to parse {}: {}", path.display(), err);
+            CacheFile::new()
+        }),
+        Err(_) => CacheFile::new(),
+    }
+}
+
+async fn save_cache<T: Serialize>(path: Option<&Path>, cache: &CacheFile<T>) {
+    let Some(path) = path else {
+        return;
+    };
+
+    if let Some(parent) = path.parent()
+        && let Err(err) = fs::create_dir_all(parent).await
+    {
+        log::warn!("Unable to create cache folder: {}", err);
+        return;
+    }
+
+    match serde_json::to_string(cache) {
+        Ok(data) => {
+            if let Err(err) = fs::write(path, data).await {
+                log::warn!("Unable to write {}: {}", path.display(), err);
+            }
+        }
+        Err(err) => log::warn!("Unable to serialize {}: {}", path.display(), err),
+    }
+}
+
+/// API can't answer right now, so stale cache is better than nothing
+fn is_unavailable(err: &ApiError) -> bool {
+    match err {
+        ApiError::Network(_) | ApiError::RateLimited(_) => true,
+        ApiError::Server(status, _) => status.is_server_error(),
+        _ => false,
+    }
+}
+
+impl<T> Listing<T> {
+    fn from_cache(cached: Cached<T>, freshness: Freshness) -> Self {
+        Self {
+            value: cached.value,
+            fetched: cached.fetched,
+            freshness,
+        }
+    }
+
+    /// Human readable age, e.g. "5 minutes ago"
+    pub fn age(&self) -> String {
+        let age = now().saturating_sub(self.fetched);
+
+        match age {
+            0..60 => "just now".to_string(),
+            60..3600 => format!("{} minutes ago", age / 60),
+            3600..86400 => format!("{} hours ago", age / 3600),
+            _ => format!("{} days ago", age / 86400),
+        }
+    }
+}
+
+impl Api {
+    fn cache_key(&self) -> String {
+        format!(
+            "{}@{}",
+            self.account.username, self.connection.endpoints.updater
+        )
+    }
+
+    fn is_fresh<T>(&self, cached: &Cached<T>) -> bool {
+        now().saturating_sub(cached.fetched) <= self.connection.cache_max_age
+    }
+
+    /// Version listing from cache when it is fresh enough (or when offline),
+    /// stale cache is also used when the API is unavailable
+    pub async fn get_versions_cached(&self) -> Result<Listing<Updates>, ApiError> {
+        self.versions_cached(cache_path(UPDATES_FILE).as_deref())
+            .await
+    }
+
+    async fn versions_cached(&self, path: Option<&Path>) -> Result<Listing<Updates>, ApiError> {
+        let mut cache = load_cache(path).await;
+        let key = self.cache_key();
+        let cached: Option<Cached<Updates>> = cache.get(&key).cloned();
+
+        if let Some(cached) = &cached
+            && self.is_fresh(cached)
+        {
+            return Ok(Listing::from_cache(cached.clone(), Freshness::Cached));
+        }
+
+        if self.connection.offline {
+            return cached
+                .map(|cached| Listing::from_cache(cached, Freshness::Stale))
+                .ok_or(ApiError::NoCache);
+        }
+
+        let etag = cached.as_ref().and_then(|cached| cached.etag.as_deref());
+
+        let fresh = match (self.get_versions_if_changed(etag).await, cached) {
+            (Ok(Some((value, etag))), _) => Cached {
+                fetched: now(),
+                etag,
+                value,
+            },
+            (Ok(None), Some(cached)) => Cached {
+                fetched: now(),
+                ..cached
+            },
+            //not modified, but we lost our copy in the meantime
+            (Ok(None), None) => Cached {
+                fetched: now(),
+                etag: None,
+                value: self.get_versions().await?,
+            },
+            (Err(err), Some(cached)) if is_unavailable(&err) => {
+                log::warn!("Using stale version listing, API is unavailable: {}", err);
+                return Ok(Listing::from_cache(cached, Freshness::Stale));
+            }
+            (Err(err), _) => return Err(err),
+        };
+
+        cache.insert(key, fresh.clone());
+        save_cache(path, &cache).await;
+
+        Ok(Listing::from_cache(fresh, Freshness::Fresh))
+    }
+
+    /// Latest releases from cache when they are fresh enough (or when offline),
+    /// stale cache is also used when the API is unavailable
+    pub async fn get_latest_releases_cached(&self) -> Result<Listing<LatestReleases>, ApiError> {
+        self.latest_releases_cached(cache_path(RELEASES_FILE).as_deref())
+            .await
+    }
+
+    async fn latest_releases_cached(
+        &self,
+        path: Option<&Path>,
+    ) -> Result<Listing<LatestReleases>, ApiError> {
+        let mut cache = load_cache(path).await;
+        let key = self.connection.endpoints.download.clone();
+        let cached: Option<Cached<LatestReleases>> = cache.get(&key).cloned();
+
+        if let Some(cached) = &cached
+            && self.is_fresh(cached)
+        {
+            return Ok(Listing::from_cache(cached.clone(), Freshness::Cached));
+        }
+
+        if self.connection.offline {
+            return cached
+                .map(|cached| Listing::from_cache(cached, Freshness::Stale))
+                .ok_or(ApiError::NoCache);
+        }
+
+        let fresh = match (self.get_latest_releases().await, cached) {
+            (Ok(value), _) => Cached {
+                fetched: now(),
+                etag: None,
+                value,
+            },
+            (Err(err), Some(cached)) if is_unavailable(&err) => {
+                log::warn!("Using stale latest releases, API is unavailable: {}", err);
+                return Ok(Listing::from_cache(cached, Freshness::Stale));
+            }
+            (Err(err), _) => return Err(err),
+        };
+
+        cache.insert(key, fresh.clone());
+        save_cache(path, &cache).await;
+
+        Ok(Listing::from_cache(fresh, Freshness::Fresh))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, http::StatusCode};
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::{
+        config::{Account, Config},
+        functions::create_connection,
+        server::fake::FakeServer,
+        structs::EndpointArgs,
+    };
+
+    const USERS: &str = r#"{"users": {"player": {"password": "secret", "token": "token"}}}"#;
+
+    async fn api_at(url: &str, offline: bool) -> Api {
+        let endpoints = EndpointArgs {
+            updater_url: Some(url.to_string()),
+            download_url: Some(url.to_string()),
+            auth_url: Some(url.to_string()),
+            mods_url: Some(url.to_string()),
+            offline,
+        };
+        let connection = create_connection(&Config::default(), &endpoints)
+            .await
+            .unwrap();
+
+        Api::new(
+            Account::new("player".to_string(), "token".to_string()),
+            connection,
+        )
+    }
+
+    /// Server answering every request with 503
+    async fn unavailable_server() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = Router::new().fallback(|| async { StatusCode::SERVICE_UNAVAILABLE });
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        format!("http://{}", address)
+    }
+
+    fn fixtures() -> TempDir {
+        let fixtures = TempDir::new("fixtures").unwrap();
+        std::fs::write(fixtures.path().join("fake.json"), USERS).unwrap();
+        let tree = fixtures.path().join("core-linux_headless64").join("1.0.0");
+        std::fs::create_dir_all(&tree).unwrap();
+        std::fs::write(tree.join("file.txt"), "1.0.0").unwrap();
+        fixtures
+    }
+
+    async fn write_entry<T: Serialize>(path: &Path, key: &str, fetched: u64, value: T) {
+        let mut cache = CacheFile::new();
+        cache.insert(
+            key.to_string(),
+            Cached {
+                fetched,
+                etag: None,
+                value,
+            },
+        );
+        save_cache(Some(path), &cache).await;
+    }
+
+    #[tokio::test]
+    async fn fresh_cache_is_used_without_request() {
+        let dir = TempDir::new("cache").unwrap();
+        let path = dir.path().join(UPDATES_FILE);
+        //nothing listens there, so any request would fail
+        let api = api_at("http://127.0.0.1:1", false).await;
+        write_entry(&path, &api.cache_key(), now(), Updates::new()).await;
+
+        let listing = api.versions_cached(Some(&path)).await.unwrap();
+
+        assert_eq!(listing.freshness, Freshness::Cached);
+        assert!(listing.value.is_empty());
+    }
+
+    #[tokio::test]
+    async fn expired_cache_is_refreshed() {
+        let fixtures = fixtures();
+        let server = FakeServer::start_local(fixtures.path()).await;
+        let api = server
+            .api(Account::new("player".to_string(), "token".to_string()))
+            .await;
+        let dir = TempDir::new("cache").unwrap();
+        let path = dir.path().join(UPDATES_FILE);
+        write_entry(&path, &api.cache_key(), 0, Updates::new()).await;
+
+        let listing = api.versions_cached(Some(&path)).await.unwrap();
+
+        assert_eq!(listing.freshness, Freshness::Fresh);
+        assert!(!listing.value.is_empty());
+
+        let cache: CacheFile<Updates> = load_cache(Some(&path)).await;
+        let cached = &cache[&api.cache_key()];
+        assert!(cached.fetched > 0);
+        assert_eq!(cached.value.len(), listing.value.len());
+    }
+
+    #[tokio::test]
+    async fn offline_uses_stale_cache() {
+        let dir = TempDir::new("cache").unwrap();
+        let path = dir.path().join(UPDATES_FILE);
+        let api = api_at("http://127.0.0.1:1", true).await;
+
+        assert!(matches!(
+            api.versions_cached(Some(&path)).await,
+            Err(ApiError::NoCache)
+        ));
+
+        write_entry(&path, &api.cache_key(), 0, Updates::new()).await;
+        let listing = api.versions_cached(Some(&path)).await.unwrap();
+
+        assert_eq!(listing.freshness, Freshness::Stale);
+    }
+
+    #[tokio::test]
+    async fn server_error_falls_back_to_stale_cache() {
+        let dir = TempDir::new("cache").unwrap();
+        let path = dir.path().join(UPDATES_FILE);
+        let api = api_at(&unavailable_server().await, false).await;
+
+        assert!(matches!(
+            api.versions_cached(Some(&path)).await,
+            Err(ApiError::Server(StatusCode::SERVICE_UNAVAILABLE, _))
+        ));
+
+        write_entry(&path, &api.cache_key(), 0, Updates::new()).await;
+        let listing = api.versions_cached(Some(&path)).await.unwrap();
+
+        assert_eq!(listing.freshness, Freshness::Stale);
+    }
+
+    #[tokio::test]
+    async fn invalid_credentials_are_not_hidden_by_cache() {
+        let fixtures = fixtures();
+        let server = FakeServer::start_local(fixtures.path()).await;
+        let api = server
+            .api(Account::new("player".to_string(), "wrong".to_string()))
+            .await;
+        let dir = TempDir::new("cache").unwrap();
+        let path = dir.path().join(UPDATES_FILE);
+        write_entry(&path, &api.cache_key(), 0, Updates::new()).await;
+
+        assert!(matches!(
+            api.versions_cached(Some(&path)).await,
+            Err(ApiError::InvalidCredentials(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn latest_releases_are_cached_for_offline_use() {
+        let fixtures = fixtures();
+        let server = FakeServer::start_local(fixtures.path()).await;
+        let dir = TempDir::new("cache").unwrap();
+        let path = dir.path().join(RELEASES_FILE);
+
+        let mut endpoints = server.endpoints();
+        let api = Api::anonymous(
+            create_connection(&Config::default(), &endpoints)
+                .await
+                .unwrap(),
+        );
+        let listing = api.latest_releases_cached(Some(&path)).await.unwrap();
+        assert_eq!(listing.freshness, Freshness::Fresh);
+        assert_eq!(listing.value.stable.headless.as_deref(), Some("1.0.0"));
+
+        //cached entry is older than the freshness window
+        let mut cache: CacheFile<LatestReleases> = load_cache(Some(&path)).await;
+        cache.values_mut().for_each(|cached| cached.fetched = 0);
+        save_cache(Some(&path), &cache).await;
+
+        endpoints.offline = true;
+        let offline = Api::anonymous(
+            create_connection(&Config::default(), &endpoints)
+                .await
+                .unwrap(),
+        );
+        let listing = offline.latest_releases_cached(Some(&path)).await.unwrap();
+
+        assert_eq!(listing.freshness, Freshness::Stale);
+        assert_eq!(listing.value.stable.headless.as_deref(), Some("1.0.0"));
+    }
+}
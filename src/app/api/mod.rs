@@ -1,66 +1,391 @@
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 
-use crate::{app::api::structs::Updates, config::Config};
+use crate::{
+    app::api::structs::{Arch, LatestReleases, Updates, VersionDiff},
+    config::{Account, Config, ConfigError, Endpoints},
+    structs::EndpointArgs,
+};
+pub mod cache;
+pub mod portal;
 pub mod structs;
 
+pub const API_VERSION: &str = "2";
+/// Version of auth.factorio.com/api-login, which returns JSON object
+pub const AUTH_API_VERSION: &str = "4";
+
+/// Client of the Factorio APIs, shared by TUI and CLI
+#[derive(Clone)]
 pub struct Api {
-    pub config: Config,
+    pub account: Account,
+    connection: Connection,
 }
 
-impl Config {
-    fn to_query(&self) -> String {
-        format!("username={}&token={}", self.username, self.token)
-    }
+/// Endpoints and configured HTTP client, from which clients for accounts are created
+#[derive(Clone)]
+pub struct Connection {
+    endpoints: Endpoints,
+    client: reqwest::Client,
+    /// Seconds, for which cached version listing is used without asking the API
+    cache_max_age: u64,
+    /// No requests are made, only cached data is used
+    offline: bool,
 }
 
-const BASE_URL: &str = "https://factorio.com";
+impl Connection {
+    /// Uses endpoints and HTTP settings from config, endpoints are overridden by CLI/environment
+    pub async fn new(config: &Config, overrides: &EndpointArgs) -> Result<Self, ConfigError> {
+        Ok(Self {
+            endpoints: config.endpoints.clone().with_overrides(overrides),
+            client: config.http.client().await?,
+            cache_max_age: config.cache.max_age,
+            offline: overrides.offline,
+        })
+    }
+}
 
+/// Error body returned by factorio.com
 #[derive(Serialize, Deserialize)]
 pub struct ErrorResponse {
     message: String,
-    statuc: u32,
+    #[serde(default)]
+    status: u16,
+    /// Error code, only returned by auth endpoint
+    #[serde(default)]
+    error: Option<String>,
 }
 
-type Response<T> = Result<T, ErrorResponse>;
+#[derive(Serialize, Deserialize)]
+pub struct LoginResponse {
+    username: String,
+    token: String,
+}
 
+#[derive(Debug)]
 pub enum ApiError {
-    Reqwest,
+    InvalidCredentials(String),
+    /// Password login needs code, which was sent to user's email
+    EmailAuthenticationRequired(String),
+    /// Account doesn't own requested package
+    NotEntitled(String),
+    NotFound(String),
+    RateLimited(String),
+    Server(StatusCode, String),
+    Network(reqwest::Error),
     Decode,
+    /// Request would be needed in offline mode
+    Offline,
+    /// Offline mode without cached version listing
+    NoCache,
+}
+
+impl ApiError {
+    async fn from_response(res: reqwest::Response) -> Self {
+        let status = res.status();
+        let body = res.text().await.unwrap_or_default();
+
+        let (message, code) = match serde_json::from_str::<ErrorResponse>(&body) {
+            Ok(error) => (error.message, error.error),
+            Err(_) if !body.trim().is_empty() => (body.trim().to_string(), None),
+            Err(_) => (
+                status
+                    .canonical_reason()
+                    .unwrap_or("Unknown error")
+                    .to_string(),
+                None,
+            ),
+        };
+
+        match code.as_deref() {
+            Some("email-authentication-required") => {
+                return ApiError::EmailAuthenticationRequired(message);
+            }
+            Some("login-failed") => return ApiError::InvalidCredentials(message),
+            _ => {}
+        }
+
+        match status {
+            StatusCode::UNAUTHORIZED => ApiError::InvalidCredentials(message),
+            StatusCode::FORBIDDEN => ApiError::NotEntitled(message),
+            StatusCode::NOT_FOUND => ApiError::NotFound(message),
+            StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimited(message),
+            status => ApiError::Server(status, message),
+        }
+    }
+
+    /// What can user do about this error
+    pub fn hint(&self) -> &'static str {
+        match self {
+            ApiError::InvalidCredentials(_) => {
+                "Check your username and token (or password), the token can be found on your factorio.com profile page."
+            }
+            ApiError::EmailAuthenticationRequired(_) => {
+                "Enter the authentication code factorio.com sent to your email."
+            }
+            ApiError::NotEntitled(_) => {
+                "This account doesn't own the requested package, log in with an account which does (e.g. one with Space Age)."
+            }
+            ApiError::NotFound(_) => {
+                "The requested version or package doesn't exist, check the version or the configured API URL."
+            }
+            ApiError::RateLimited(_) => "Too many requests were made, wait a while and try again.",
+            ApiError::Server(_, _) => "Factorio servers are having problems, try again later.",
+            ApiError::Network(_) => {
+                "Check your internet connection, proxy settings and the configured API URL."
+            }
+            ApiError::Decode => {
+                "The server returned unexpected data, check the configured API URL."
+            }
+            ApiError::Offline => {
+                "Only cached version listing and packages can be used offline, run without --offline to download the rest."
+            }
+            ApiError::NoCache => {
+                "Run once without --offline, so the version listing (or latest releases) gets cached."
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::InvalidCredentials(message) => write!(f, "Invalid credentials: {}", message),
+            ApiError::EmailAuthenticationRequired(message) => {
+                write!(f, "Email authentication required: {}", message)
+            }
+            ApiError::NotEntitled(message) => write!(f, "Not entitled: {}", message),
+            ApiError::NotFound(message) => write!(f, "Not found: {}", message),
+            ApiError::RateLimited(message) => write!(f, "Rate limited: {}", message),
+            ApiError::Server(status, message) => {
+                write!(f, "Server error ({}): {}", status, message)
+            }
+            ApiError::Network(err) => write!(f, "Unable to reach Factorio API: {}", err),
+            ApiError::Decode => write!(f, "Unable to decode response from Factorio API"),
+            ApiError::Offline => write!(f, "Factorio API can't be reached in offline mode"),
+            ApiError::NoCache => write!(f, "No cached versions are available"),
+        }
+    }
 }
 
+impl std::error::Error for ApiError {}
+
 impl Api {
-    pub fn new(config: Config) -> Self {
-        Api { config }
+    pub fn new(account: Account, connection: Connection) -> Self {
+        Api {
+            account,
+            connection,
+        }
     }
 
-    pub async fn check_credentials(&self) -> Result<bool, ApiError> {
-        let url = format!(
-            "{}/get-available-versions?{}",
-            BASE_URL,
-            self.config.to_query()
-        );
+    pub fn connection(&self) -> Connection {
+        self.connection.clone()
+    }
 
-        let res = match reqwest::get(url).await {
-            Ok(res) => res,
-            Err(_) => return Err(ApiError::Reqwest),
-        };
+    pub fn is_offline(&self) -> bool {
+        self.connection.offline
+    }
 
-        Ok(res.status() == StatusCode::OK)
+    /// Client without credentials, only for public data and headless downloads
+    pub fn anonymous(connection: Connection) -> Self {
+        Self::new(Account::new(String::new(), String::new()), connection)
     }
 
-    pub async fn get_versions(&self) -> Result<Response<Updates>, ApiError> {
-        let url = format!(
-            "{}/get-available-versions?{}",
-            BASE_URL,
-            self.config.to_query()
-        );
+    pub fn is_anonymous(&self) -> bool {
+        self.account.token.is_empty()
+    }
 
-        let res = match reqwest::get(url).await {
-            Ok(res) => res,
-            Err(_) => return Err(ApiError::Reqwest),
-        };
+    fn auth_query(&self) -> Vec<(&str, &str)> {
+        if self.is_anonymous() {
+            return vec![("apiVersion", API_VERSION)];
+        }
+
+        vec![
+            ("username", self.account.username.as_str()),
+            ("token", self.account.token.as_str()),
+            ("apiVersion", API_VERSION),
+        ]
+    }
+
+    /// Sends authenticated request, non-success responses are turned into [`ApiError`]
+    async fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<reqwest::Response, ApiError> {
+        if self.connection.offline {
+            return Err(ApiError::Offline);
+        }
+
+        let res = self
+            .connection
+            .client
+            .get(url)
+            .query(&self.auth_query())
+            .query(query)
+            .send()
+            .await
+            .map_err(|err| ApiError::Network(err.without_url()))?;
+
+        if res.status().is_success() {
+            Ok(res)
+        } else {
+            Err(ApiError::from_response(res).await)
+        }
+    }
+
+    /// Obtains token for `account.username` by logging in with password,
+    /// only the resulting token is kept in account
+    pub async fn login(
+        &mut self,
+        password: &str,
+        email_code: Option<&str>,
+    ) -> Result<(), ApiError> {
+        let mut form = vec![
+            ("username", self.account.username.as_str()),
+            ("password", password),
+            ("api_version", AUTH_API_VERSION),
+            ("require_game_ownership", "true"),
+        ];
+
+        if let Some(code) = email_code {
+            form.push(("email_authentication_code", code));
+        }
+
+        if self.connection.offline {
+            return Err(ApiError::Offline);
+        }
+
+        let res = self
+            .connection
+            .client
+            .post(format!("{}/api-login", self.connection.endpoints.auth))
+            .form(&form)
+            .send()
+            .await
+            .map_err(|err| ApiError::Network(err.without_url()))?;
+
+        if !res.status().is_success() {
+            return Err(ApiError::from_response(res).await);
+        }
+
+        let login: LoginResponse = res.json().await.map_err(|_| ApiError::Decode)?;
+
+        self.account.username = login.username;
+        self.account.token = login.token;
+
+        Ok(())
+    }
+
+    pub async fn check_credentials(&self) -> Result<(), ApiError> {
+        self.get_versions().await.map(|_| ())
+    }
+
+    pub async fn get_versions(&self) -> Result<Updates, ApiError> {
+        let res = self
+            .get(
+                &format!(
+                    "{}/get-available-versions",
+                    self.connection.endpoints.updater
+                ),
+                &[],
+            )
+            .await?;
+
+        res.json().await.map_err(|_| ApiError::Decode)
+    }
+
+    /// Versions with ETag of the response, `None` when they didn't change since `etag`
+    pub async fn get_versions_if_changed(
+        &self,
+        etag: Option<&str>,
+    ) -> Result<Option<(Updates, Option<String>)>, ApiError> {
+        if self.connection.offline {
+            return Err(ApiError::Offline);
+        }
+
+        let mut request = self
+            .connection
+            .client
+            .get(format!(
+                "{}/get-available-versions",
+                self.connection.endpoints.updater
+            ))
+            .query(&self.auth_query());
+
+        if let Some(etag) = etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+
+        let res = request
+            .send()
+            .await
+            .map_err(|err| ApiError::Network(err.without_url()))?;
+
+        if res.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+
+        if !res.status().is_success() {
+            return Err(ApiError::from_response(res).await);
+        }
+
+        let etag = res
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+
+        let updates = res.json().await.map_err(|_| ApiError::Decode)?;
+
+        Ok(Some((updates, etag)))
+    }
+
+    pub async fn get_patch_download_link(
+        &self,
+        arch: &Arch,
+        diff: &VersionDiff,
+    ) -> Result<String, ApiError> {
+        let package = arch.to_string();
+
+        let mut links = self
+            .get(
+                &format!("{}/get-download-link", self.connection.endpoints.updater),
+                &[
+                    ("package", package.as_str()),
+                    ("from", diff.from.as_str()),
+                    ("to", diff.to.as_str()),
+                ],
+            )
+            .await?
+            .json::<Vec<String>>()
+            .await
+            .map_err(|_| ApiError::Decode)?;
+
+        match (links.pop(), links.is_empty()) {
+            (Some(link), true) => Ok(link),
+            _ => Err(ApiError::Decode),
+        }
+    }
+
+    /// Latest stable and experimental versions, doesn't need credentials
+    pub async fn get_latest_releases(&self) -> Result<LatestReleases, ApiError> {
+        let res = self
+            .get(
+                &format!("{}/api/latest-releases", self.connection.endpoints.download),
+                &[],
+            )
+            .await?;
 
         res.json().await.map_err(|_| ApiError::Decode)
     }
+
+    /// Link of full game package, which redirects to the actual file
+    pub fn get_full_download_link(&self, arch: &Arch, version: &str) -> String {
+        let (build, distro) = arch.download_build();
+        format!(
+            "{}/get-download/{}/{}/{}",
+            self.connection.endpoints.download, version, build, distro
+        )
+    }
+
+    /// Starts download of link returned by one of the `get_*_download_link` methods
+    pub async fn download(&self, url: &str) -> Result<reqwest::Response, ApiError> {
+        self.get(url, &[]).await
+    }
 }
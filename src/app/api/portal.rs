@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+use crate::app::api::{Api, ApiError};
+
+/// Mod as returned by the mod portal's `api/mods/{name}/full`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PortalMod {
+    pub name: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub releases: Vec<Release>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Release {
+    /// Path on the portal, credentials are added when downloading
+    pub download_url: String,
+    pub file_name: String,
+    pub info_json: ReleaseInfo,
+    #[serde(default)]
+    pub released_at: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+}
+
+/// Part of release's info.json, which is exposed by the portal
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReleaseInfo {
+    pub factorio_version: String,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl Api {
+    pub async fn get_mod(&self, name: &str) -> Result<PortalMod, ApiError> {
+        let res = self
+            .get(
+                &format!("{}/api/mods/{}/full", self.connection.endpoints.mods, name),
+                &[],
+            )
+            .await?;
+
+        res.json().await.map_err(|_| ApiError::Decode)
+    }
+
+    /// Link of release, which can be passed to `download`
+    pub fn get_mod_download_link(&self, release: &Release) -> String {
+        format!("{}{}", self.connection.endpoints.mods, release.download_url)
+    }
+}
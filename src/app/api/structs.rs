@@ -1,4 +1,4 @@
-use clap::{Parser, ValueEnum, command};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt::Display};
 
@@ -35,6 +35,10 @@ pub enum Platform {
     //Win32,
     #[serde(rename = "win64")]
     Win64,
+    /// Linux server without graphics, contains Space Age since 2.0
+    #[serde(rename = "headless")]
+    #[value(name = "headless")]
+    Headless,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ValueEnum, PartialEq, Eq, Hash)]
@@ -67,6 +71,88 @@ pub enum Arch {
     Other,
 }
 
+impl Display for Arch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arch::CoreLinux64 => write!(f, "core-linux64"),
+            Arch::CoreLinuxHeadless64 => write!(f, "core-linux_headless64"),
+            Arch::CoreMac => write!(f, "core-mac"),
+            Arch::CoreWin64 => write!(f, "core-win64"),
+            Arch::CoreExpansionLinux64 => write!(f, "core_expansion-linux64"),
+            Arch::CoreExpansionMac => write!(f, "core_expansion-mac"),
+            Arch::CoreExpansionWin64 => write!(f, "core_expansion-win64"),
+            Arch::Other => write!(f, "other"),
+        }
+    }
+}
+
+const DOWNLOAD_BUILDS: &[(Arch, &str, &str)] = &[
+    (Arch::CoreLinux64, "alpha", "linux64"),
+    (Arch::CoreLinuxHeadless64, "headless", "linux64"),
+    (Arch::CoreExpansionLinux64, "expansion", "linux64"),
+    (Arch::CoreMac, "alpha", "osx"),
+    (Arch::CoreExpansionMac, "expansion", "osx"),
+    (Arch::CoreWin64, "alpha", "win64-manual"),
+    (Arch::CoreExpansionWin64, "expansion", "win64-manual"),
+];
+
+impl Arch {
+    /// Build and distro used by get-download for this package
+    pub fn download_build(&self) -> (&'static str, &'static str) {
+        DOWNLOAD_BUILDS
+            .iter()
+            .find(|(arch, _, _)| arch == self)
+            .map(|(_, build, distro)| (*build, *distro))
+            .unwrap()
+    }
+
+    pub fn from_download_build(build: &str, distro: &str) -> Option<Arch> {
+        DOWNLOAD_BUILDS
+            .iter()
+            .find(|(_, b, d)| *b == build && *d == distro)
+            .map(|(arch, _, _)| arch.clone())
+    }
+
+    /// Packages, that can be installed
+    pub fn all() -> impl Iterator<Item = Arch> {
+        DOWNLOAD_BUILDS.iter().map(|(arch, _, _)| arch.clone())
+    }
+
+    /// Headless server can be downloaded without credentials
+    pub fn is_public(&self) -> bool {
+        *self == Arch::CoreLinuxHeadless64
+    }
+}
+
+/// Versions of one release channel by build, as returned by the public latest-releases
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Releases {
+    pub alpha: Option<String>,
+    pub demo: Option<String>,
+    pub expansion: Option<String>,
+    pub headless: Option<String>,
+}
+
+impl Releases {
+    pub fn get(&self, arch: &Arch) -> Option<&str> {
+        let (_, build, _) = DOWNLOAD_BUILDS.iter().find(|(known, _, _)| known == arch)?;
+
+        let version = match *build {
+            "headless" => &self.headless,
+            "expansion" => &self.expansion,
+            _ => &self.alpha,
+        };
+
+        version.as_deref()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LatestReleases {
+    pub stable: Releases,
+    pub experimental: Releases,
+}
+
 impl From<(Version, Platform)> for Arch {
     fn from((version, platform): (Version, Platform)) -> Self {
         match (version, platform) {
@@ -80,22 +166,23 @@ impl From<(Version, Platform)> for Arch {
             (Version::SpaceAge, Platform::Linux64) => Arch::CoreExpansionLinux64,
             (Version::SpaceAge, Platform::Mac) => Arch::CoreExpansionMac,
             (Version::SpaceAge, Platform::Win64) => Arch::CoreExpansionWin64,
+            (_, Platform::Headless) => Arch::CoreLinuxHeadless64,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VersionDiff {
     pub from: String,
     pub to: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Stable {
     pub stable: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum Item {
     VersionDiff(VersionDiff),
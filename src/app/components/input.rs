@@ -32,10 +32,12 @@ impl Input {
             unselected_style: Style::default(),
             input_type,
             title: None,
+            value: String::new(),
             selected: false,
         }
     }
 
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> InputBuilder {
         Self::create_input(InputType::Text)
     }
@@ -49,10 +51,7 @@ impl Input {
     }
 
     pub fn set_error(&mut self, error: Option<&str>) {
-        self.error = match error {
-            Some(err) => Some(err.to_string()),
-            None => None,
-        };
+        self.error = error.map(|err| err.to_string());
     }
 
     pub fn render(&mut self) -> Paragraph<'_> {
@@ -92,6 +91,7 @@ impl Input {
 
 pub struct InputBuilder {
     title: Option<String>,
+    value: String,
     selected_style: Style,
     unselected_style: Style,
     input_type: InputType,
@@ -104,6 +104,12 @@ impl InputBuilder {
         self
     }
 
+    /// Initial value
+    pub fn value<T: Into<String>>(mut self, value: T) -> Self {
+        self.value = value.into();
+        self
+    }
+
     pub fn selected_style(mut self, style: Style) -> Self {
         self.selected_style = style;
         self
@@ -121,7 +127,7 @@ impl InputBuilder {
 
     pub fn build(self) -> Input {
         Input {
-            native_input: NativeInput::default(),
+            native_input: NativeInput::new(self.value),
             error: None,
             selected_style: self.selected_style,
             unselected_style: self.unselected_style,
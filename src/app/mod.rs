@@ -4,27 +4,35 @@ use crate::{
     app::{
         api::Api,
         components::popup::Popup,
-        screens::{Screen, login::Login, main::Main},
+        components::popup::PopupBuilder,
+        screens::{Screen, login::Login, main::Main, unlock::Unlock},
     },
     config::Config,
+    functions::create_connection,
+    structs::EndpointArgs,
 };
 use crossterm::event::{Event, EventStream, KeyCode, KeyEvent};
 use futures_util::StreamExt;
-use ratatui::{DefaultTerminal, Frame, layout::Rect};
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::Rect,
+    style::Stylize,
+    text::{Line, Text},
+};
 
-mod api;
+pub(crate) mod api;
 mod components;
 mod screens;
 
 pub struct App<'a> {
     exited: bool,
-    screen: Box<dyn Screen>,
+    screen: Box<dyn Screen + Send>,
     popup: Option<Popup<'a>>,
     event_rx: tokio::sync::mpsc::Receiver<Event>,
 }
 
 impl App<'_> {
-    pub async fn new() -> Self {
+    pub async fn new(overrides: EndpointArgs, profile: Option<String>) -> anyhow::Result<Self> {
         let config = Config::load().await.unwrap();
 
         let (tx, rx) = tokio::sync::mpsc::channel(1024);
@@ -32,22 +40,49 @@ impl App<'_> {
         tokio::spawn(async move {
             let mut stream = EventStream::default();
             while let Some(event) = stream.next().await {
-                match event {
-                    Ok(event) => tx.send(event).await.unwrap(),
-                    _ => {}
+                if let Ok(event) = event {
+                    tx.send(event).await.unwrap();
                 }
             }
         });
 
-        Self {
+        let config = config.unwrap_or_default();
+        let connection = create_connection(&config, &overrides).await?;
+        let profile = profile.unwrap_or_else(|| config.active_profile.clone());
+
+        let (screen, popup): (Box<dyn Screen + Send>, _) = match config.account(Some(&profile)) {
+            Some(account) if account.is_locked() => (
+                Box::new(Unlock::new(profile, account.clone(), connection)),
+                None,
+            ),
+            Some(account) => (
+                Box::new(Main::new(profile, Api::new(account.clone(), connection)).await),
+                None,
+            ),
+            None => {
+                let login = Login::new(connection, profile).await;
+                let popup = login.import_popup();
+                (Box::new(login), popup)
+            }
+        };
+
+        let popup = match (popup, Config::permission_warning().await) {
+            (None, Some(warning)) => Some(
+                PopupBuilder::default()
+                    .title(Line::from(" Warning ").bold().yellow().centered())
+                    .content(Text::from(warning))
+                    .build()
+                    .unwrap(),
+            ),
+            (popup, _) => popup,
+        };
+
+        Ok(Self {
             exited: false,
-            screen: match config {
-                Some(config) => Box::new(Main::new(Api::new(config))),
-                None => Box::new(Login::default()),
-            },
-            popup: None,
+            screen,
+            popup,
             event_rx: rx,
-        }
+        })
     }
 
     pub async fn main_loop(mut self, term: &mut DefaultTerminal) -> anyhow::Result<()> {
@@ -107,13 +142,14 @@ impl App<'_> {
     }
 
     async fn handle_event(&mut self, event: Event) {
-        match &event {
-            crossterm::event::Event::Key(key_event) => self.handle_key(key_event).await,
-            _ => {}
-        };
+        if let crossterm::event::Event::Key(key_event) = &event {
+            self.handle_key(key_event).await;
+        }
     }
 
     async fn handle_key(&mut self, ev: &KeyEvent) {
+        //q is typed into focused text input instead
+        let typing = self.popup.is_none() && self.screen.is_typing();
         let is_exit = matches!(
             ev.code,
             KeyCode::Char('c')
@@ -121,17 +157,15 @@ impl App<'_> {
                     .modifiers
                     .contains(crossterm::event::KeyModifiers::CONTROL)
 
-        ) || matches!(ev.code, KeyCode::Char('q') | KeyCode::Esc);
+        ) || ev.code == KeyCode::Esc
+            || (ev.code == KeyCode::Char('q') && !typing);
 
         if is_exit {
             self.handle_exit();
             return;
         }
 
-        let popup_result = self
-            .popup
-            .as_mut()
-            .map_or(None, |popup| popup.handle_key(ev));
+        let popup_result = self.popup.as_mut().and_then(|popup| popup.handle_key(ev));
 
         let screen_result = match popup_result {
             Some(popup_result) => self.screen.on_popup(popup_result).await,
@@ -140,7 +174,14 @@ impl App<'_> {
 
         if let Some(screen_ev) = screen_result {
             match screen_ev {
-                screens::ScreenEvent::Logged(config) => self.screen = Box::new(Main::new(config)),
+                screens::ScreenEvent::Logged { profile, api } => {
+                    self.screen = Box::new(Main::new(profile, api).await);
+                    self.popup = None;
+                }
+                screens::ScreenEvent::Switch(screen) => {
+                    self.screen = screen;
+                    self.popup = None;
+                }
                 screens::ScreenEvent::OpenPopup(popup) => self.popup = Some(popup),
                 screens::ScreenEvent::ClosePopup => self.popup = None,
             }
@@ -0,0 +1,204 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, BorderType, Paragraph, Tabs, Wrap},
+};
+use semver::Version;
+
+use crate::{
+    app::{
+        api::{Api, structs::Arch},
+        components::popup::PopupResult,
+        screens::{Screen, ScreenEvent},
+    },
+    steps::changelog::load_changelog,
+};
+
+/// Installation with a newer version available
+pub struct Target {
+    pub arch: Arch,
+    pub folder: PathBuf,
+    pub from: String,
+    pub to: String,
+}
+
+/// Changes between installed and target versions
+pub struct Changelog {
+    profile: String,
+    api: Api,
+    targets: Vec<Target>,
+    selected: usize,
+    lines: Vec<Line<'static>>,
+    scroll: u16,
+}
+
+impl Changelog {
+    pub async fn new(profile: String, api: Api, targets: Vec<Target>) -> Self {
+        let mut changelog = Self {
+            profile,
+            api,
+            targets,
+            selected: 0,
+            lines: Vec::new(),
+            scroll: 0,
+        };
+        changelog.load().await;
+        changelog
+    }
+
+    async fn load(&mut self) {
+        self.scroll = 0;
+
+        let Some(target) = self.targets.get(self.selected) else {
+            self.lines = vec![
+                Line::from("No installations found in the current folder").yellow(),
+                Line::from("Changelog is shown for installations with a newer version available")
+                    .italic(),
+            ];
+            return;
+        };
+
+        let (changelog, source) =
+            match load_changelog(&target.arch, &target.folder, Some(&target.to)).await {
+                Ok(changelog) => changelog,
+                Err(err) => {
+                    self.lines = vec![Line::from(format!("{:#}", err)).red()];
+                    return;
+                }
+            };
+
+        let from = Version::parse(&target.from).ok();
+        let to = Version::parse(&target.to).ok();
+
+        let mut lines = vec![
+            Line::from(format!("{} -> {}", target.from, target.to)).bold(),
+            Line::from(format!("Changelog from {}", source.display())).dark_gray(),
+            Line::default(),
+        ];
+
+        let entries = changelog.between(from.as_ref(), to.as_ref());
+        if entries.is_empty() {
+            lines.push(
+                Line::from(
+                    "No changes in changelog yet, the new version has to be downloaded first",
+                )
+                .yellow(),
+            );
+        }
+
+        for entry in entries {
+            lines.push(
+                Line::from(match &entry.date {
+                    Some(date) => format!("Version {} ({})", entry.version, date),
+                    None => format!("Version {}", entry.version),
+                })
+                .bold()
+                .blue(),
+            );
+
+            for section in &entry.sections {
+                lines.push(Line::from(format!("  {}:", section.name)).yellow());
+                lines.extend(
+                    section
+                        .entries
+                        .iter()
+                        .map(|text| Line::from(format!("    - {}", text))),
+                );
+            }
+
+            lines.push(Line::default());
+        }
+
+        self.lines = lines;
+    }
+}
+
+#[async_trait]
+impl Screen for Changelog {
+    fn render(&mut self, frame: &mut ratatui::Frame) {
+        let card = Block::bordered()
+            .border_type(BorderType::Rounded)
+            .title(Line::from(" Changelog ").bold().blue().centered());
+
+        let inside = card.inner(frame.area());
+        frame.render_widget(card, frame.area());
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Min(1),
+                Constraint::Length(1),
+            ])
+            .split(inside);
+
+        frame.render_widget(
+            Tabs::new(
+                self.targets
+                    .iter()
+                    .map(|target| target.arch.to_string())
+                    .collect::<Vec<_>>(),
+            )
+            .select(self.selected)
+            .highlight_style(Style::new().bold().underlined()),
+            layout[0],
+        );
+
+        frame.render_widget(
+            Paragraph::new(Text::from(self.lines.clone()))
+                .wrap(Wrap { trim: false })
+                .scroll((self.scroll, 0)),
+            layout[1],
+        );
+
+        frame.render_widget(
+            Paragraph::new("Up/Down: scroll, Left/Right: installation, Backspace: back")
+                .dark_gray()
+                .centered(),
+            layout[2],
+        );
+    }
+
+    async fn on_key(&mut self, key: &KeyEvent) -> Option<ScreenEvent> {
+        match key.code {
+            KeyCode::Down => {
+                self.scroll = self.scroll.saturating_add(1);
+                None
+            }
+            KeyCode::Up => {
+                self.scroll = self.scroll.saturating_sub(1);
+                None
+            }
+            KeyCode::PageDown => {
+                self.scroll = self.scroll.saturating_add(10);
+                None
+            }
+            KeyCode::PageUp => {
+                self.scroll = self.scroll.saturating_sub(10);
+                None
+            }
+            KeyCode::Left | KeyCode::Right if !self.targets.is_empty() => {
+                self.selected = match key.code {
+                    KeyCode::Left => (self.selected + self.targets.len() - 1) % self.targets.len(),
+                    _ => (self.selected + 1) % self.targets.len(),
+                };
+                self.load().await;
+                None
+            }
+            KeyCode::Backspace => Some(ScreenEvent::Logged {
+                profile: self.profile.clone(),
+                api: self.api.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    async fn on_popup(&mut self, _: PopupResult) -> Option<ScreenEvent> {
+        Some(ScreenEvent::ClosePopup)
+    }
+}
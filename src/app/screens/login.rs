@@ -1,61 +1,75 @@
+use std::path::PathBuf;
+
 use async_trait::async_trait;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     layout::{Constraint, Direction, Flex, Layout, Rect},
     style::{Color, Style, Stylize},
-    text::Line,
-    widgets::{Block, BorderType, Paragraph, Wrap},
+    text::{Line, Text},
+    widgets::{Block, BorderType, Paragraph, Tabs, Wrap},
 };
 
 use crate::{
     app::{
-        api::Api,
-        components::{input::Input, popup::PopupResult},
-        screens::{ConstaintDirection, ConstrainExtend, Screen, ScreenEvent},
+        api::{Api, ApiError, Connection},
+        components::{
+            input::Input,
+            popup::{Popup, PopupBuilder, PopupResult, PopupType},
+        },
+        screens::{ConstaintDirection, ConstrainExtend, Screen, ScreenEvent, unlock::Unlock},
     },
-    config::Config,
+    config::{Account, Config, player_data::PlayerData},
 };
 
-#[derive(Default, PartialEq)]
-enum Selected {
+#[derive(Default, PartialEq, Clone, Copy)]
+enum Mode {
     #[default]
-    Username,
     Token,
-    Button,
+    Password,
 }
 
-impl Selected {
-    fn next(&self) -> Self {
-        match self {
-            Selected::Username => Selected::Token,
-            Selected::Token => Selected::Button,
-            Selected::Button => Selected::Username,
-        }
-    }
-
-    fn prev(&self) -> Self {
-        match self {
-            Selected::Username => Selected::Button,
-            Selected::Token => Selected::Username,
-            Selected::Button => Selected::Token,
-        }
-    }
+#[derive(Default, PartialEq, Clone, Copy)]
+enum Selected {
+    Profile,
+    Mode,
+    #[default]
+    Username,
+    Secret,
+    EmailCode,
+    Button,
+    /// Continue without account, only public releases are available
+    Anonymous,
 }
 
 pub struct Login {
     selected: Selected,
+    mode: Mode,
+    /// Name of profile, under which the account is stored
+    profile: Input,
     username: Input,
     token: Input,
+    password: Input,
+    email_code: Input,
+    /// factorio.com asked for code sent to email
+    email_code_required: bool,
+    /// Credentials found in player-data.json, which are offered for import
+    import: Option<(PathBuf, Account)>,
+    connection: Connection,
 }
 
-impl Default for Login {
-    fn default() -> Self {
-        Self {
-            selected: Default::default(),
-            username: Input::new().selected().title("Username").build(),
-            token: Input::password().title("Token").build(),
-        }
-    }
+fn error_popup(message: String, hint: &str) -> ScreenEvent {
+    ScreenEvent::OpenPopup(
+        PopupBuilder::default()
+            .title(Line::from(" Login failed ").bold().red().centered())
+            .content(Text::from(vec![
+                Line::from(message),
+                Line::default(),
+                Line::from(hint.to_string()).italic(),
+            ]))
+            .border_style(Style::new().fg(Color::Red))
+            .build()
+            .unwrap(),
+    )
 }
 
 fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
@@ -67,63 +81,242 @@ fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
 }
 
 impl Login {
+    pub async fn new(connection: Connection, profile: String) -> Self {
+        let import = match PlayerData::find(None).await {
+            Ok(found) => found.and_then(|(path, data)| Some((path, data.into_account()?))),
+            Err(err) => {
+                log::warn!("Unable to read player-data.json: {:?}", err);
+                None
+            }
+        };
+
+        Self {
+            selected: Default::default(),
+            mode: Default::default(),
+            profile: Input::new().title("Profile").value(profile).build(),
+            username: Input::new().selected().title("Username").build(),
+            token: Input::password().title("Token").build(),
+            password: Input::password().title("Password").build(),
+            email_code: Input::new().title("Email authentication code").build(),
+            email_code_required: false,
+            import,
+            connection,
+        }
+    }
+
+    /// Popup offering import of credentials from player-data.json
+    pub fn import_popup(&self) -> Option<Popup<'static>> {
+        let (path, account) = self.import.as_ref()?;
+
+        Some(
+            PopupBuilder::default()
+                .title(Line::from(" Import credentials ").bold().blue().centered())
+                .content(Text::from(format!(
+                    "Found credentials of {} in {}. Do you want to import them?",
+                    account.username,
+                    path.display()
+                )))
+                .popup_type(PopupType::YesNo)
+                .build()
+                .unwrap(),
+        )
+    }
+
+    async fn import_credentials(&mut self, account: Account) -> Option<ScreenEvent> {
+        if self.profile.value().is_empty() {
+            self.profile.set_error(Some("Please enter profile name"));
+            self.select(Selected::Profile);
+            return Some(ScreenEvent::ClosePopup);
+        }
+
+        let api = Api::new(account, self.connection.clone());
+
+        match api.check_credentials().await {
+            Ok(_) => self.logged(api).await,
+            Err(err) => Some(error_popup(err.to_string(), err.hint())),
+        }
+    }
+
+    /// Stores account under selected profile and makes the profile active
+    async fn logged(&self, api: Api) -> Option<ScreenEvent> {
+        let profile = self.profile.value().to_string();
+
+        let mut config = match Config::load().await {
+            Ok(config) => config.unwrap_or_default(),
+            Err(err) => {
+                return Some(error_popup(
+                    format!("Unable to load config: {:?}", err),
+                    "Fix or remove the config file and try again.",
+                ));
+            }
+        };
+
+        //new token of encrypted profile is encrypted with its passphrase
+        if let Some(stored) = config.profiles.get(&profile)
+            && config.is_encrypted(&profile)
+        {
+            return Some(ScreenEvent::Switch(Box::new(Unlock::login(
+                profile,
+                stored.clone(),
+                api.account,
+                self.connection.clone(),
+            ))));
+        }
+
+        config.set_account(profile.clone(), api.account.clone());
+
+        match config.save().await {
+            Ok(_) => Some(ScreenEvent::Logged { profile, api }),
+            Err(err) => Some(error_popup(
+                format!("Unable to save config: {:?}", err),
+                "Check that your config directory is writable.",
+            )),
+        }
+    }
+
+    /// Selectable items in order, in which they are rendered
+    fn items(&self) -> Vec<Selected> {
+        let mut items = vec![
+            Selected::Profile,
+            Selected::Mode,
+            Selected::Username,
+            Selected::Secret,
+        ];
+
+        if self.mode == Mode::Password && self.email_code_required {
+            items.push(Selected::EmailCode);
+        }
+
+        items.push(Selected::Button);
+        items.push(Selected::Anonymous);
+        items
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        let items = self.items();
+        let current = items
+            .iter()
+            .position(|item| *item == self.selected)
+            .unwrap_or(0) as isize;
+
+        let next = (current + offset).rem_euclid(items.len() as isize) as usize;
+        self.select(items[next]);
+    }
+
     fn select(&mut self, selected: Selected) {
         self.selected = selected;
 
+        self.profile
+            .set_selected(self.selected == Selected::Profile);
         self.username
             .set_selected(self.selected == Selected::Username);
-        self.token.set_selected(self.selected == Selected::Token);
+        self.token.set_selected(self.selected == Selected::Secret);
+        self.password
+            .set_selected(self.selected == Selected::Secret);
+        self.email_code
+            .set_selected(self.selected == Selected::EmailCode);
+    }
+
+    fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            Mode::Token => Mode::Password,
+            Mode::Password => Mode::Token,
+        };
+    }
+
+    fn secret(&mut self) -> &mut Input {
+        match self.mode {
+            Mode::Token => &mut self.token,
+            Mode::Password => &mut self.password,
+        }
     }
 
     async fn submit(&mut self) -> Option<ScreenEvent> {
         let mut errors = false;
 
-        if self.username.value().len() == 0 {
+        if self.profile.value().is_empty() {
+            self.profile.set_error(Some("Please enter profile name"));
+            errors = true;
+        } else {
+            self.profile.set_error(None);
+        }
+
+        if self.username.value().is_empty() {
             self.username.set_error(Some("Please enter username"));
             errors = true;
         } else {
             self.username.set_error(None);
         }
 
-        if self.token.value().len() == 0 {
-            self.token.set_error(Some("Please enter token"));
+        let missing = match self.mode {
+            Mode::Token => "Please enter token",
+            Mode::Password => "Please enter password",
+        };
+
+        if self.secret().value().is_empty() {
+            self.secret().set_error(Some(missing));
             errors = true;
         } else {
-            self.token.set_error(None);
+            self.secret().set_error(None);
         }
 
         if errors {
             return None;
         }
 
-        let config = Config {
-            username: self.username.value().to_string(),
-            token: self.token.value().to_string(),
+        let result = match self.mode {
+            Mode::Token => self.login_token().await,
+            Mode::Password => self.login_password().await,
         };
 
-        config.save().await.unwrap();
-
-        let api = Api::new(config);
-
-        match api.check_credentials().await {
-            Ok(check) => {
-                if check {
-                    Some(ScreenEvent::Logged(api))
-                } else {
-                    let err = Some("Invalid combination of username/token");
-                    self.username.set_error(err.clone());
-                    self.token.set_error(err);
-                    None
-                }
+        match result {
+            Ok(api) => self.logged(api).await,
+            Err(ApiError::InvalidCredentials(_)) => {
+                let err = Some(match self.mode {
+                    Mode::Token => "Invalid combination of username/token",
+                    Mode::Password => "Invalid combination of username/password",
+                });
+                self.username.set_error(err);
+                self.secret().set_error(err);
+                None
             }
-            Err(_) => {
-                let err = Some("Unable to check username/token validity");
-                self.username.set_error(err.clone());
-                self.token.set_error(err);
+            Err(ApiError::EmailAuthenticationRequired(_)) => {
+                self.email_code
+                    .set_error(Some("Enter code which was sent to your email"));
+                self.email_code_required = true;
+                self.select(Selected::EmailCode);
                 None
             }
+            Err(err) => Some(error_popup(err.to_string(), err.hint())),
         }
     }
+
+    async fn login_token(&self) -> Result<Api, ApiError> {
+        let account = Account::new(
+            self.username.value().to_string(),
+            self.token.value().to_string(),
+        );
+
+        let api = Api::new(account, self.connection.clone());
+        api.check_credentials().await?;
+
+        Ok(api)
+    }
+
+    /// Exchanges password for token, password itself is never stored
+    async fn login_password(&self) -> Result<Api, ApiError> {
+        let account = Account::new(self.username.value().to_string(), String::new());
+
+        let email_code = match self.email_code.value() {
+            "" => None,
+            code => Some(code),
+        };
+
+        let mut api = Api::new(account, self.connection.clone());
+        api.login(self.password.value(), email_code).await?;
+
+        Ok(api)
+    }
 }
 
 #[async_trait]
@@ -135,10 +328,27 @@ impl Screen for Login {
             .border_type(BorderType::Rounded)
             .title(title);
 
+        let mut constraints = vec![
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Length(3),
+        ];
+
+        if self.mode == Mode::Password && self.email_code_required {
+            constraints.push(Constraint::Length(3));
+        }
+
+        constraints.push(Constraint::Length(3));
+        constraints.push(Constraint::Length(3));
+
+        let height = constraints.len() as u16 * 3 - 2;
+
         let centered = center(
             frame.area(),
             Constraint::Percentage(25).max(&frame.area(), 40, ConstaintDirection::Horizontal),
-            Constraint::Percentage(23).max(&frame.area(), 14, ConstaintDirection::Vertical),
+            Constraint::Length(height),
         );
 
         let inside = card.inner(centered);
@@ -146,22 +356,42 @@ impl Screen for Login {
 
         let main_layout = Layout::default()
             .direction(Direction::Vertical)
-            .constraints(vec![
-                Constraint::Fill(1),
-                Constraint::Ratio(1, 4),
-                Constraint::Ratio(1, 4),
-                Constraint::Fill(1),
-            ])
+            .constraints(constraints)
             .split(inside);
 
         frame.render_widget(
-            Paragraph::new("with username and token from factorio.com")
-                .wrap(Wrap { trim: false })
-                .centered(),
+            Paragraph::new(match self.mode {
+                Mode::Token => "with username and token from factorio.com",
+                Mode::Password => "with factorio.com username and password",
+            })
+            .wrap(Wrap { trim: false })
+            .centered(),
             main_layout[0],
         );
-        frame.render_widget(self.username.render(), main_layout[1]);
-        frame.render_widget(self.token.render(), main_layout[2]);
+        frame.render_widget(self.profile.render(), main_layout[1]);
+        frame.render_widget(
+            Tabs::new(vec!["Token", "Password"])
+                .select(match self.mode {
+                    Mode::Token => 0,
+                    Mode::Password => 1,
+                })
+                .style(if self.selected == Selected::Mode {
+                    Style::new().fg(Color::Yellow)
+                } else {
+                    Style::new()
+                })
+                .highlight_style(Style::new().bold().underlined()),
+            Layout::horizontal([Constraint::Length(20)])
+                .flex(Flex::Center)
+                .split(main_layout[2])[0],
+        );
+        frame.render_widget(self.username.render(), main_layout[3]);
+        frame.render_widget(self.secret().render(), main_layout[4]);
+
+        if self.mode == Mode::Password && self.email_code_required {
+            frame.render_widget(self.email_code.render(), main_layout[5]);
+        }
+
         frame.render_widget(
             Paragraph::new("Login")
                 .block(Block::bordered())
@@ -171,30 +401,57 @@ impl Screen for Login {
                     Style::new()
                 })
                 .centered(),
-            main_layout[3],
+            main_layout[main_layout.len() - 2],
+        );
+        frame.render_widget(
+            Paragraph::new("Continue without account")
+                .block(Block::bordered())
+                .style(if self.selected == Selected::Anonymous {
+                    Style::new().fg(Color::Yellow)
+                } else {
+                    Style::new().dark_gray()
+                })
+                .centered(),
+            main_layout[main_layout.len() - 1],
         );
     }
 
     async fn on_key(&mut self, key: &KeyEvent) -> Option<ScreenEvent> {
         match key.code {
             KeyCode::Tab | KeyCode::Down => {
-                self.select(self.selected.next());
+                self.move_selection(1);
                 None
             }
             KeyCode::Up => {
-                self.select(self.selected.prev());
+                self.move_selection(-1);
                 None
             }
+            KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')
+                if self.selected == Selected::Mode =>
+            {
+                self.toggle_mode();
+                None
+            }
+            KeyCode::Enter if self.selected == Selected::Anonymous => Some(ScreenEvent::Logged {
+                profile: String::new(),
+                api: Api::anonymous(self.connection.clone()),
+            }),
             KeyCode::Enter => self.submit().await,
             _ => {
                 match self.selected {
+                    Selected::Profile => {
+                        self.profile.handle_key(key);
+                    }
                     Selected::Username => {
                         self.username.handle_key(key);
                     }
-                    Selected::Token => {
-                        self.token.handle_key(key);
+                    Selected::Secret => {
+                        self.secret().handle_key(key);
+                    }
+                    Selected::EmailCode => {
+                        self.email_code.handle_key(key);
                     }
-                    Selected::Button => { /* EMPTY */ }
+                    Selected::Mode | Selected::Button | Selected::Anonymous => { /* EMPTY */ }
                 };
 
                 None
@@ -202,7 +459,17 @@ impl Screen for Login {
         }
     }
 
-    async fn on_popup(&mut self, _: PopupResult) -> Option<ScreenEvent> {
-        None
+    fn is_typing(&self) -> bool {
+        matches!(
+            self.selected,
+            Selected::Profile | Selected::Username | Selected::Secret | Selected::EmailCode
+        )
+    }
+
+    async fn on_popup(&mut self, result: PopupResult) -> Option<ScreenEvent> {
+        match (self.import.take(), result) {
+            (Some((_, account)), PopupResult::Yes) => self.import_credentials(account).await,
+            _ => Some(ScreenEvent::ClosePopup),
+        }
     }
 }
@@ -1,37 +1,209 @@
 use async_trait::async_trait;
-use crossterm::event::KeyEvent;
+use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     style::Stylize,
-    text::Line,
+    text::{Line, Text},
     widgets::{Block, BorderType, Paragraph},
 };
 
-use crate::app::{
-    api::Api,
-    components::popup::PopupResult,
-    screens::{Screen, ScreenEvent},
+use crate::{
+    app::{
+        api::{
+            Api, ApiError,
+            cache::{Freshness, Listing},
+            structs::{Arch, LatestReleases, Updates},
+        },
+        components::popup::PopupResult,
+        screens::{
+            Screen, ScreenEvent,
+            changelog::{Changelog, Target},
+            login::Login,
+            mod_profiles::ModProfiles,
+            profiles::Profiles,
+        },
+    },
+    config::DEFAULT_PROFILE,
+    functions::load_config,
+    steps::items::{arch_install_folder, find_stable},
 };
 
+enum Versions {
+    /// Version listing of account, possibly from cache
+    Listing(Result<Listing<Updates>, ApiError>),
+    /// Public releases, when continuing without account, possibly from cache
+    Public(Result<Listing<LatestReleases>, ApiError>),
+}
+
 pub struct Main {
     username: String,
+    profile: String,
+    versions: Versions,
     api: Api,
     count: usize,
 }
 
 impl Main {
-    pub fn new(api: Api) -> Self {
+    pub async fn new(profile: String, api: Api) -> Self {
+        let versions = if api.is_anonymous() {
+            Versions::Public(api.get_latest_releases_cached().await)
+        } else {
+            Versions::Listing(api.get_versions_cached().await)
+        };
+
         Self {
-            username: api.config.username.clone(),
+            username: api.account.username.clone(),
+            profile,
+            versions,
             api,
             count: 0,
         }
     }
+
+    fn error_lines(err: &ApiError) -> Vec<Line<'static>> {
+        vec![
+            Line::from(format!("Unable to get versions: {}", err)).red(),
+            Line::from(err.hint()).italic(),
+        ]
+    }
+
+    fn releases_lines(releases: &LatestReleases) -> Vec<Line<'static>> {
+        let channel = |name: &str, version: &Option<String>| {
+            Line::from(format!(
+                "{} headless: {}",
+                name,
+                version.as_deref().unwrap_or("-")
+            ))
+        };
+
+        vec![
+            Line::from("Latest public releases").green(),
+            channel("Stable", &releases.stable.headless),
+            channel("Experimental", &releases.experimental.headless),
+            Line::from(format!(
+                "Stable game: {}",
+                releases.stable.alpha.as_deref().unwrap_or("-")
+            )),
+            Line::from(format!(
+                "Stable Space Age: {}",
+                releases.stable.expansion.as_deref().unwrap_or("-")
+            )),
+        ]
+    }
+
+    /// Installations in default folders, which have a newer version available
+    async fn changelog_targets(&self) -> Vec<Target> {
+        let latest: Vec<(Arch, String)> = match &self.versions {
+            Versions::Listing(Ok(listing)) => listing
+                .value
+                .iter()
+                .filter_map(|(arch, items)| Some((arch.clone(), find_stable(items)?.to_string())))
+                .collect(),
+            Versions::Public(Ok(releases)) => releases
+                .value
+                .stable
+                .get(&Arch::CoreLinuxHeadless64)
+                .map(|version| (Arch::CoreLinuxHeadless64, version.to_string()))
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let mut targets = Vec::new();
+
+        for (arch, to) in latest {
+            if arch == Arch::Other {
+                continue;
+            }
+
+            let folder = arch_install_folder(&arch, None);
+            if let Ok(Some(config)) = load_config(&folder).await
+                && config.current_version != to
+            {
+                targets.push(Target {
+                    arch,
+                    folder,
+                    from: config.current_version,
+                    to,
+                });
+            }
+        }
+
+        targets.sort_by_key(|target| target.arch.to_string());
+        targets
+    }
+
+    fn status_line<T>(listing: &Listing<T>) -> Line<'static> {
+        match listing.freshness {
+            Freshness::Fresh => Line::from("Versions are up to date").green(),
+            Freshness::Cached => {
+                Line::from(format!("Versions cached {}", listing.age())).dark_gray()
+            }
+            Freshness::Stale => Line::from(format!(
+                "Stale versions from {}, newer ones may exist",
+                listing.age()
+            ))
+            .yellow(),
+        }
+    }
+
+    fn listing_lines(&self) -> Vec<Line<'static>> {
+        let listing = match &self.versions {
+            Versions::Listing(Ok(listing)) => listing,
+            Versions::Listing(Err(err)) | Versions::Public(Err(err)) => {
+                return Self::error_lines(err);
+            }
+            Versions::Public(Ok(releases)) => {
+                let mut lines = Self::releases_lines(&releases.value);
+                if releases.freshness != Freshness::Fresh {
+                    lines.insert(1, Self::status_line(releases));
+                }
+                return lines;
+            }
+        };
+
+        let status = Self::status_line(listing);
+
+        let mut stable = listing
+            .value
+            .iter()
+            .filter_map(|(arch, items)| Some(format!("{}: {}", arch, find_stable(items)?)))
+            .collect::<Vec<_>>();
+        stable.sort();
+
+        let mut lines = vec![status];
+        lines.extend(stable.into_iter().map(Line::from));
+        lines
+    }
 }
 
 #[async_trait]
 impl Screen for Main {
     fn render(&mut self, frame: &mut ratatui::Frame) {
-        let text = format!("Welcome {}", self.username);
+        let mut lines = if self.api.is_anonymous() {
+            vec![
+                Line::from("Not logged in"),
+                Line::from("Only the headless server can be updated without account").italic(),
+                Line::default(),
+            ]
+        } else {
+            vec![
+                Line::from(format!("Welcome {}", self.username)),
+                Line::from(format!("Profile: {}", self.profile)).italic(),
+                Line::default(),
+            ]
+        };
+        lines.extend(self.listing_lines());
+        lines.push(Line::default());
+        lines.push(Line::from("Press c to see changelog of available updates").dark_gray());
+        lines.push(Line::from("Press m to manage mod profiles").dark_gray());
+
+        if self.api.is_anonymous() {
+            lines.push(Line::from("Press l to log in").dark_gray());
+        } else {
+            lines.push(Line::from("Press p to switch profiles").dark_gray());
+        }
+
+        let text = Text::from(lines);
         frame.render_widget(
             Paragraph::new(text)
                 .block(
@@ -44,11 +216,30 @@ impl Screen for Main {
         );
     }
 
-    async fn on_key(&mut self, _: &KeyEvent) -> Option<ScreenEvent> {
-        None
+    async fn on_key(&mut self, key: &KeyEvent) -> Option<ScreenEvent> {
+        match key.code {
+            KeyCode::Char('c') => Some(ScreenEvent::Switch(Box::new(
+                Changelog::new(
+                    self.profile.clone(),
+                    self.api.clone(),
+                    self.changelog_targets().await,
+                )
+                .await,
+            ))),
+            KeyCode::Char('m') => Some(ScreenEvent::Switch(Box::new(
+                ModProfiles::new(self.profile.clone(), self.api.clone()).await,
+            ))),
+            KeyCode::Char('l') if self.api.is_anonymous() => Some(ScreenEvent::Switch(Box::new(
+                Login::new(self.api.connection(), DEFAULT_PROFILE.to_string()).await,
+            ))),
+            KeyCode::Char('p') if !self.api.is_anonymous() => Some(ScreenEvent::Switch(Box::new(
+                Profiles::new(self.api.connection()).await,
+            ))),
+            _ => None,
+        }
     }
 
     async fn on_popup(&mut self, _: PopupResult) -> Option<ScreenEvent> {
-        None
+        Some(ScreenEvent::ClosePopup)
     }
 }
@@ -1,7 +1,11 @@
 #![allow(dead_code)]
 
+pub(crate) mod changelog;
 pub(crate) mod login;
 pub(crate) mod main;
+pub(crate) mod mod_profiles;
+pub(crate) mod profiles;
+pub(crate) mod unlock;
 
 use async_trait::async_trait;
 use crossterm::event::KeyEvent;
@@ -20,10 +24,20 @@ pub trait Screen {
     fn render(&mut self, frame: &mut Frame);
     async fn on_key(&mut self, key: &KeyEvent) -> Option<ScreenEvent>;
     async fn on_popup(&mut self, result: PopupResult) -> Option<ScreenEvent>;
+
+    /// Text input is focused, so typed characters aren't shortcuts
+    fn is_typing(&self) -> bool {
+        false
+    }
 }
 
 pub enum ScreenEvent {
-    Logged(Api),
+    Logged {
+        profile: String,
+        api: Api,
+    },
+    /// Replaces current screen
+    Switch(Box<dyn Screen + Send>),
     OpenPopup(Popup<'static>),
     ClosePopup,
 }
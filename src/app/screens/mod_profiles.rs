@@ -0,0 +1,340 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, BorderType, List, ListState, Paragraph, Tabs},
+};
+
+use crate::{
+    app::{
+        api::{Api, structs::Arch},
+        components::{
+            input::Input,
+            popup::{PopupBuilder, PopupResult, PopupType},
+        },
+        screens::{Screen, ScreenEvent},
+    },
+    functions::load_config,
+    mods::profiles,
+    steps::items::arch_install_folder,
+};
+
+/// Saved mod profiles of installations in default folders
+pub struct ModProfiles {
+    profile: String,
+    api: Api,
+    installations: Vec<(Arch, PathBuf)>,
+    selected: usize,
+    names: Vec<String>,
+    active: Option<String>,
+    state: ListState,
+    /// Name of profile being saved
+    naming: Option<Input>,
+    /// Profile waiting for confirmation of removal
+    removing: Option<String>,
+    message: Line<'static>,
+}
+
+impl ModProfiles {
+    pub async fn new(profile: String, api: Api) -> Self {
+        let mut installations = Vec::new();
+        for arch in Arch::all() {
+            let folder = arch_install_folder(&arch, None);
+            if let Ok(Some(_)) = load_config(&folder).await {
+                installations.push((arch, folder));
+            }
+        }
+
+        let mut screen = Self {
+            profile,
+            api,
+            installations,
+            selected: 0,
+            names: Vec::new(),
+            active: None,
+            state: ListState::default(),
+            naming: None,
+            removing: None,
+            message: Line::default(),
+        };
+        screen.load().await;
+        screen
+    }
+
+    fn profiles(&self) -> anyhow::Result<Option<(profiles::ModProfiles, PathBuf)>> {
+        let Some((_, folder)) = self.installations.get(self.selected) else {
+            return Ok(None);
+        };
+
+        Ok(Some((
+            profiles::ModProfiles::new(folder)?,
+            folder.join("mods"),
+        )))
+    }
+
+    async fn load(&mut self) {
+        let loaded = async {
+            let Some((profiles, _)) = self.profiles()? else {
+                return Ok((Vec::new(), None));
+            };
+
+            anyhow::Ok((profiles.list().await?, profiles.active().await?))
+        }
+        .await;
+
+        match loaded {
+            Ok((names, active)) => {
+                self.names = names;
+                self.active = active;
+            }
+            Err(err) => {
+                self.names = Vec::new();
+                self.active = None;
+                self.message = Line::from(format!("{:#}", err)).red();
+            }
+        }
+
+        let selected = self.state.selected().unwrap_or(0);
+        self.state
+            .select((!self.names.is_empty()).then(|| selected.min(self.names.len() - 1)));
+    }
+
+    fn selected_name(&self) -> Option<String> {
+        self.names.get(self.state.selected()?).cloned()
+    }
+
+    async fn activate(&mut self) {
+        let Some(name) = self.selected_name() else {
+            return;
+        };
+
+        let result = async {
+            let (profiles, mods_folder) = self.profiles()?.unwrap();
+            profiles.activate(&name, &mods_folder).await
+        }
+        .await;
+
+        self.message = match result {
+            Ok(profile) => Line::from(format!(
+                "Activated profile {} with {} mods",
+                name,
+                profile.files.len()
+            ))
+            .green(),
+            Err(err) => Line::from(format!("{:#}", err)).red(),
+        };
+        self.load().await;
+    }
+
+    async fn save(&mut self, name: String) {
+        let result = async {
+            let (profiles, mods_folder) = self.profiles()?.unwrap();
+            profiles.save(&name, &mods_folder).await
+        }
+        .await;
+
+        self.message = match result {
+            Ok(profile) => Line::from(format!(
+                "Saved profile {} with {} mods",
+                name,
+                profile.files.len()
+            ))
+            .green(),
+            Err(err) => Line::from(format!("{:#}", err)).red(),
+        };
+        self.load().await;
+
+        if let Some(index) = self.names.iter().position(|saved| *saved == name) {
+            self.state.select(Some(index));
+        }
+    }
+
+    fn confirm_removal(&mut self) -> Option<ScreenEvent> {
+        let name = self.selected_name()?;
+
+        let popup = PopupBuilder::default()
+            .title(
+                Line::from(" Remove mod profile ")
+                    .bold()
+                    .yellow()
+                    .centered(),
+            )
+            .content(Text::from(format!(
+                "Do you want to remove mod profile {}? Its mods stay in the shared store.",
+                name
+            )))
+            .popup_type(PopupType::YesNo)
+            .build()
+            .unwrap();
+
+        self.removing = Some(name);
+        Some(ScreenEvent::OpenPopup(popup))
+    }
+}
+
+#[async_trait]
+impl Screen for ModProfiles {
+    fn render(&mut self, frame: &mut ratatui::Frame) {
+        let card = Block::bordered()
+            .border_type(BorderType::Rounded)
+            .title(Line::from(" Mod profiles ").bold().blue().centered());
+
+        let inside = card.inner(frame.area());
+        frame.render_widget(card, frame.area());
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Min(1),
+                Constraint::Length(if self.naming.is_some() { 3 } else { 0 }),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .split(inside);
+
+        frame.render_widget(
+            Tabs::new(
+                self.installations
+                    .iter()
+                    .map(|(arch, _)| arch.to_string())
+                    .collect::<Vec<_>>(),
+            )
+            .select(self.selected)
+            .highlight_style(Style::new().bold().underlined()),
+            layout[0],
+        );
+
+        if self.installations.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No installations found in the current folder").yellow(),
+                layout[1],
+            );
+        } else if self.names.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No mod profiles, press s to save the current mods as one")
+                    .dark_gray(),
+                layout[1],
+            );
+        } else {
+            let items = self.names.iter().map(|name| {
+                if self.active.as_deref() == Some(name.as_str()) {
+                    Line::from(format!("{} (active)", name)).bold()
+                } else {
+                    Line::from(name.clone())
+                }
+            });
+
+            frame.render_stateful_widget(
+                List::new(items)
+                    .highlight_style(Style::new().fg(Color::Yellow))
+                    .highlight_symbol("> "),
+                layout[1],
+                &mut self.state,
+            );
+        }
+
+        if let Some(input) = &mut self.naming {
+            frame.render_widget(input.render(), layout[2]);
+        }
+
+        frame.render_widget(Paragraph::new(self.message.clone()), layout[3]);
+
+        frame.render_widget(
+            Paragraph::new(if self.naming.is_some() {
+                "Enter: save (empty name to cancel)"
+            } else {
+                "Enter: activate, s: save current mods, u: update selected, d: remove, Left/Right: installation, Backspace: back"
+            })
+            .dark_gray()
+            .centered(),
+            layout[4],
+        );
+    }
+
+    async fn on_key(&mut self, key: &KeyEvent) -> Option<ScreenEvent> {
+        if let Some(input) = &mut self.naming {
+            if key.code == KeyCode::Enter {
+                let name = input.value().trim().to_string();
+                self.naming = None;
+
+                if !name.is_empty() {
+                    self.save(name).await;
+                }
+            } else {
+                input.handle_key(key);
+            }
+
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Down | KeyCode::Tab => {
+                self.state.select_next();
+                None
+            }
+            KeyCode::Up => {
+                self.state.select_previous();
+                None
+            }
+            KeyCode::Left | KeyCode::Right if !self.installations.is_empty() => {
+                self.selected = match key.code {
+                    KeyCode::Left => {
+                        (self.selected + self.installations.len() - 1) % self.installations.len()
+                    }
+                    _ => (self.selected + 1) % self.installations.len(),
+                };
+                self.message = Line::default();
+                self.state.select(None);
+                self.load().await;
+                None
+            }
+            KeyCode::Enter => {
+                self.activate().await;
+                None
+            }
+            KeyCode::Char('s') if !self.installations.is_empty() => {
+                self.naming = Some(Input::new().selected().title("Profile name").build());
+                None
+            }
+            KeyCode::Char('u') => {
+                if let Some(name) = self.selected_name() {
+                    self.save(name).await;
+                }
+                None
+            }
+            KeyCode::Char('d') => self.confirm_removal(),
+            KeyCode::Backspace => Some(ScreenEvent::Logged {
+                profile: self.profile.clone(),
+                api: self.api.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    async fn on_popup(&mut self, result: PopupResult) -> Option<ScreenEvent> {
+        if let (Some(name), PopupResult::Yes) = (self.removing.take(), result) {
+            let result = async {
+                let (profiles, _) = self.profiles()?.unwrap();
+                profiles.remove(&name).await
+            }
+            .await;
+
+            self.message = match result {
+                Ok(()) => Line::from(format!("Removed profile {}", name)).green(),
+                Err(err) => Line::from(format!("{:#}", err)).red(),
+            };
+            self.load().await;
+        }
+
+        Some(ScreenEvent::ClosePopup)
+    }
+
+    fn is_typing(&self) -> bool {
+        self.naming.is_some()
+    }
+}
@@ -0,0 +1,207 @@
+use async_trait::async_trait;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Direction, Flex, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, BorderType, List, ListState, Paragraph},
+};
+
+use crate::{
+    app::{
+        api::{Api, Connection},
+        components::popup::{PopupBuilder, PopupResult, PopupType},
+        screens::{
+            ConstaintDirection, ConstrainExtend, Screen, ScreenEvent, login::Login, unlock::Unlock,
+        },
+    },
+    config::Config,
+};
+
+/// Lists stored profiles and switches between them
+pub struct Profiles {
+    config: Config,
+    state: ListState,
+    /// Profile waiting for confirmation of removal
+    removing: Option<String>,
+    connection: Connection,
+}
+
+fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
+    let [area] = Layout::horizontal([horizontal])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([vertical]).flex(Flex::Center).areas(area);
+    area
+}
+
+fn error_popup(message: String) -> ScreenEvent {
+    ScreenEvent::OpenPopup(
+        PopupBuilder::default()
+            .title(Line::from(" Error ").bold().red().centered())
+            .content(Text::from(message))
+            .border_style(Style::new().fg(Color::Red))
+            .build()
+            .unwrap(),
+    )
+}
+
+impl Profiles {
+    pub async fn new(connection: Connection) -> Self {
+        let config = match Config::load().await {
+            Ok(config) => config.unwrap_or_default(),
+            Err(err) => {
+                log::warn!("Unable to load config: {:?}", err);
+                Config::default()
+            }
+        };
+
+        let active = config
+            .profiles
+            .keys()
+            .position(|name| *name == config.active_profile);
+
+        Self {
+            config,
+            state: ListState::default().with_selected(active.or(Some(0))),
+            removing: None,
+            connection,
+        }
+    }
+
+    fn selected(&self) -> Option<String> {
+        self.config
+            .profiles
+            .keys()
+            .nth(self.state.selected()?)
+            .cloned()
+    }
+
+    async fn activate(&mut self) -> Option<ScreenEvent> {
+        let profile = self.selected()?;
+        let account = self.config.profiles.get(&profile)?.clone();
+
+        self.config.active_profile = profile.clone();
+        if let Err(err) = self.config.save().await {
+            return Some(error_popup(format!("Unable to save config: {:?}", err)));
+        }
+
+        if account.is_locked() {
+            return Some(ScreenEvent::Switch(Box::new(Unlock::new(
+                profile,
+                account,
+                self.connection.clone(),
+            ))));
+        }
+
+        Some(ScreenEvent::Logged {
+            profile,
+            api: Api::new(account, self.connection.clone()),
+        })
+    }
+
+    fn confirm_removal(&mut self) -> Option<ScreenEvent> {
+        let profile = self.selected()?;
+
+        if profile == self.config.active_profile {
+            return Some(error_popup(
+                "Active profile can't be removed, switch to another one first".to_string(),
+            ));
+        }
+
+        let popup = PopupBuilder::default()
+            .title(Line::from(" Remove profile ").bold().yellow().centered())
+            .content(Text::from(format!(
+                "Do you want to remove profile {} with its credentials?",
+                profile
+            )))
+            .popup_type(PopupType::YesNo)
+            .build()
+            .unwrap();
+
+        self.removing = Some(profile);
+        Some(ScreenEvent::OpenPopup(popup))
+    }
+}
+
+#[async_trait]
+impl Screen for Profiles {
+    fn render(&mut self, frame: &mut ratatui::Frame) {
+        let title = Line::from("Profiles").bold().blue().centered();
+
+        let card = Block::bordered()
+            .border_type(BorderType::Rounded)
+            .title(title);
+
+        let centered = center(
+            frame.area(),
+            Constraint::Percentage(25).max(&frame.area(), 40, ConstaintDirection::Horizontal),
+            Constraint::Length(self.config.profiles.len() as u16 + 5),
+        );
+
+        let inside = card.inner(centered);
+        frame.render_widget(card, centered);
+
+        let main_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Min(1), Constraint::Length(2)])
+            .split(inside);
+
+        let items = self.config.profiles.iter().map(|(name, account)| {
+            let line = Line::from(format!("{} - {}", name, account.username));
+            if *name == self.config.active_profile {
+                line.bold()
+            } else {
+                line
+            }
+        });
+
+        frame.render_stateful_widget(
+            List::new(items)
+                .highlight_style(Style::new().fg(Color::Yellow))
+                .highlight_symbol("> "),
+            main_layout[0],
+            &mut self.state,
+        );
+
+        frame.render_widget(
+            Paragraph::new("Enter: switch, a: add, d: remove")
+                .dark_gray()
+                .centered(),
+            main_layout[1],
+        );
+    }
+
+    async fn on_key(&mut self, key: &KeyEvent) -> Option<ScreenEvent> {
+        match key.code {
+            KeyCode::Down | KeyCode::Tab => {
+                self.state.select_next();
+                None
+            }
+            KeyCode::Up => {
+                self.state.select_previous();
+                None
+            }
+            KeyCode::Enter => self.activate().await,
+            KeyCode::Char('a') => Some(ScreenEvent::Switch(Box::new(
+                Login::new(self.connection.clone(), String::new()).await,
+            ))),
+            KeyCode::Char('d') => self.confirm_removal(),
+            _ => None,
+        }
+    }
+
+    async fn on_popup(&mut self, result: PopupResult) -> Option<ScreenEvent> {
+        if let (Some(profile), PopupResult::Yes) = (self.removing.take(), result) {
+            self.config.remove_profile(&profile);
+
+            if let Err(err) = self.config.save().await {
+                return Some(error_popup(format!("Unable to save config: {:?}", err)));
+            }
+
+            self.state.select(Some(0));
+        }
+
+        Some(ScreenEvent::ClosePopup)
+    }
+}
@@ -0,0 +1,164 @@
+use async_trait::async_trait;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Direction, Flex, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, BorderType, Paragraph, Wrap},
+};
+
+use crate::{
+    app::{
+        api::{Api, Connection},
+        components::{input::Input, popup::PopupResult},
+        screens::{ConstaintDirection, ConstrainExtend, Screen, ScreenEvent},
+    },
+    config::{Account, Config, ConfigError},
+};
+
+/// Asks for passphrase of encrypted token
+pub struct Unlock {
+    profile: String,
+    account: Account,
+    /// Account of new login, which is stored encrypted with the passphrase
+    login: Option<Account>,
+    passphrase: Input,
+    connection: Connection,
+}
+
+fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
+    let [area] = Layout::horizontal([horizontal])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([vertical]).flex(Flex::Center).areas(area);
+    area
+}
+
+impl Unlock {
+    pub fn new(profile: String, account: Account, connection: Connection) -> Self {
+        Self {
+            profile,
+            account,
+            login: None,
+            passphrase: Input::password().selected().title("Passphrase").build(),
+            connection,
+        }
+    }
+
+    /// Asks for passphrase of the profile's stored token, before new login replaces it
+    pub fn login(profile: String, stored: Account, login: Account, connection: Connection) -> Self {
+        Self {
+            login: Some(login),
+            ..Self::new(profile, stored, connection)
+        }
+    }
+
+    /// Encrypts new login with the passphrase and stores it
+    async fn store_login(&self, login: Account) -> Result<Account, String> {
+        let mut config = Config::load()
+            .await
+            .map_err(|err| format!("Unable to load config: {:?}", err))?
+            .unwrap_or_default();
+
+        config
+            .set_login(self.profile.clone(), login, Some(self.passphrase.value()))
+            .map_err(|err| format!("Unable to encrypt token: {:?}", err))?;
+        config
+            .save()
+            .await
+            .map_err(|err| format!("Unable to save config: {:?}", err))?;
+
+        Ok(config.profiles[&self.profile].clone())
+    }
+
+    async fn submit(&mut self) -> Option<ScreenEvent> {
+        match self.account.unlock(self.passphrase.value()) {
+            Ok(_) => {
+                let account = match self.login.clone() {
+                    Some(login) => match self.store_login(login).await {
+                        Ok(account) => account,
+                        Err(err) => {
+                            self.passphrase.set_error(Some(&err));
+                            return None;
+                        }
+                    },
+                    None => self.account.clone(),
+                };
+
+                Some(ScreenEvent::Logged {
+                    profile: self.profile.clone(),
+                    api: Api::new(account, self.connection.clone()),
+                })
+            }
+            Err(ConfigError::WrongPassphrase) => {
+                self.passphrase.set_error(Some("Wrong passphrase"));
+                None
+            }
+            Err(_) => {
+                self.passphrase
+                    .set_error(Some("Stored token is corrupted, please log in again"));
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Screen for Unlock {
+    fn render(&mut self, frame: &mut ratatui::Frame) {
+        let title = Line::from("Unlock credentials").bold().blue().centered();
+
+        let card = Block::bordered()
+            .border_type(BorderType::Rounded)
+            .title(title);
+
+        let centered = center(
+            frame.area(),
+            Constraint::Percentage(25).max(&frame.area(), 40, ConstaintDirection::Horizontal),
+            Constraint::Length(7),
+        );
+
+        let inside = card.inner(centered);
+        frame.render_widget(card, centered);
+
+        let main_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(2), Constraint::Length(3)])
+            .split(inside);
+
+        frame.render_widget(
+            Paragraph::new(match &self.login {
+                Some(login) => format!(
+                    "Profile {} is encrypted, enter passphrase to store token of {}",
+                    self.profile, login.username
+                ),
+                None => format!(
+                    "Token of {} (profile {}) is encrypted, enter passphrase",
+                    self.account.username, self.profile
+                ),
+            })
+            .wrap(Wrap { trim: false })
+            .centered(),
+            main_layout[0],
+        );
+        frame.render_widget(self.passphrase.render(), main_layout[1]);
+    }
+
+    async fn on_key(&mut self, key: &KeyEvent) -> Option<ScreenEvent> {
+        match key.code {
+            KeyCode::Enter => self.submit().await,
+            _ => {
+                self.passphrase.handle_key(key);
+                None
+            }
+        }
+    }
+
+    async fn on_popup(&mut self, _: PopupResult) -> Option<ScreenEvent> {
+        Some(ScreenEvent::ClosePopup)
+    }
+
+    fn is_typing(&self) -> bool {
+        true
+    }
+}
@@ -1,18 +1,53 @@
-use factorio_updater::app::App;
+use std::path::Path;
+
+use clap::Parser;
+use factorio_updater::{
+    app::App,
+    logging,
+    server::{fake::dev_server, serve},
+    steps::{
+        changelog::handle_changelog, check::handle_check, credentials::handle_credentials,
+        handle_mirror, handle_update, headless::handle_headless, login::handle_login,
+        mods::handle_mods, print_error, profiles::handle_profiles, webhooks::handle_webhooks,
+    },
+    structs::{Cli, Command},
+};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    //let args = Args::parse();
-    //handle_update(args).await?;
+    let cli = Cli::parse();
+
+    logging::log_to_file(Path::new("debug.log"), log::LevelFilter::Debug)?;
+
+    let result = match cli.command {
+        Some(Command::Update(args)) => handle_update(args, cli.endpoints, cli.profile).await,
+        Some(Command::Mirror(args)) => handle_mirror(args, cli.endpoints, cli.profile).await,
+        Some(Command::Changelog(args)) => handle_changelog(args).await,
+        Some(Command::Check(args)) => handle_check(args, cli.endpoints).await,
+        Some(Command::Serve(args)) => serve(args).await,
+        Some(Command::Login(args)) => handle_login(args, cli.endpoints, cli.profile).await,
+        Some(Command::Credentials(args)) => handle_credentials(args, cli.profile).await,
+        Some(Command::Profiles(args)) => handle_profiles(args).await,
+        Some(Command::Mods(args)) => handle_mods(args, cli.endpoints, cli.profile).await,
+        Some(Command::Headless(args)) => handle_headless(args).await,
+        Some(Command::Webhooks(args)) => handle_webhooks(args, cli.endpoints).await,
+        Some(Command::DevServer(args)) => dev_server(args).await,
+        None => {
+            let app = App::new(cli.endpoints, cli.profile).await?;
 
-    simple_logging::log_to_file("debug.log", log::LevelFilter::Debug)?;
+            let mut term = ratatui::init();
+            app.main_loop(&mut term).await?;
 
-    let mut term = ratatui::init();
+            ratatui::restore();
 
-    let app = App::new().await;
-    app.main_loop(&mut term).await?;
+            Ok(())
+        }
+    };
 
-    ratatui::restore();
+    if let Err(err) = result {
+        print_error(&err);
+        std::process::exit(1);
+    }
 
     Ok(())
 }
@@ -0,0 +1,204 @@
+use semver::Version;
+
+/// Path of changelog inside of installation (and packages, under their top level folder)
+pub const CHANGELOG_PATH: &str = "data/changelog.txt";
+
+/// Parsed `data/changelog.txt`, newest version first like in the file
+#[derive(Debug, Default)]
+pub struct Changelog {
+    pub versions: Vec<VersionEntry>,
+}
+
+#[derive(Debug)]
+pub struct VersionEntry {
+    pub version: String,
+    pub date: Option<String>,
+    pub sections: Vec<Section>,
+}
+
+/// Group of entries, e.g. Bugfixes, Features or Changes
+#[derive(Debug)]
+pub struct Section {
+    pub name: String,
+    pub entries: Vec<String>,
+}
+
+impl Changelog {
+    /// Lenient parser, lines which don't fit the format are skipped
+    pub fn parse(data: &str) -> Self {
+        let mut versions: Vec<VersionEntry> = Vec::new();
+
+        for line in data.lines() {
+            let trimmed = line.trim();
+
+            if trimmed.is_empty() || trimmed.chars().all(|char| char == '-') {
+                continue;
+            }
+
+            if let Some(version) = trimmed.strip_prefix("Version:") {
+                versions.push(VersionEntry {
+                    version: version.trim().to_string(),
+                    date: None,
+                    sections: Vec::new(),
+                });
+                continue;
+            }
+
+            let Some(entry) = versions.last_mut() else {
+                continue;
+            };
+
+            if let Some(date) = trimmed.strip_prefix("Date:") {
+                entry.date = Some(date.trim().to_string());
+                continue;
+            }
+
+            let indent = line.len() - line.trim_start().len();
+
+            if let Some(text) = trimmed.strip_prefix("- ") {
+                match entry.sections.last_mut() {
+                    Some(section) => section.entries.push(text.to_string()),
+                    None => entry.sections.push(Section {
+                        name: "Changes".to_string(),
+                        entries: vec![text.to_string()],
+                    }),
+                }
+            } else if indent <= 2 && trimmed.ends_with(':') {
+                entry.sections.push(Section {
+                    name: trimmed.trim_end_matches(':').to_string(),
+                    entries: Vec::new(),
+                });
+            } else if let Some(last) = entry
+                .sections
+                .last_mut()
+                .and_then(|section| section.entries.last_mut())
+            {
+                //continuation of multi-line entry
+                last.push(' ');
+                last.push_str(trimmed);
+            }
+        }
+
+        Self { versions }
+    }
+
+    /// Entries newer than `from` up to and including `to`, newest first
+    pub fn between(&self, from: Option<&Version>, to: Option<&Version>) -> Vec<&VersionEntry> {
+        self.versions
+            .iter()
+            .filter(|entry| {
+                let Ok(version) = Version::parse(&entry.version) else {
+                    return false;
+                };
+
+                from.is_none_or(|from| version > *from) && to.is_none_or(|to| version <= *to)
+            })
+            .collect()
+    }
+
+    /// Newest version in changelog
+    pub fn latest(&self) -> Option<&str> {
+        self.versions.first().map(|entry| entry.version.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGELOG: &str = "\
+---------------------------------------------------------------------------------------------------
+Version: 2.0.1
+Date: 22.10.2024
+  Bugfixes:
+    - Fixed crash when opening the map.
+      Second line of the entry.
+  Changes:
+    - Faster loading.
+---------------------------------------------------------------------------------------------------
+Version: 2.0.0
+Date: 21.10.2024
+  Features:
+    - Space Age.
+---------------------------------------------------------------------------------------------------
+Version: 1.1.110
+Date: 05.06.2024
+  Bugfixes:
+    - Fixed inserters.
+---------------------------------------------------------------------------------------------------
+Version: 1.1.109
+  - Entry without a section.
+";
+
+    fn versions(entries: &[&VersionEntry]) -> Vec<String> {
+        entries.iter().map(|entry| entry.version.clone()).collect()
+    }
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn parses_versions_sections_and_entries() {
+        let changelog = Changelog::parse(CHANGELOG);
+
+        assert_eq!(changelog.latest(), Some("2.0.1"));
+        assert_eq!(changelog.versions.len(), 4);
+
+        let newest = &changelog.versions[0];
+        assert_eq!(newest.date.as_deref(), Some("22.10.2024"));
+        assert_eq!(newest.sections.len(), 2);
+        assert_eq!(newest.sections[0].name, "Bugfixes");
+        assert_eq!(
+            newest.sections[0].entries,
+            ["Fixed crash when opening the map. Second line of the entry."]
+        );
+        assert_eq!(newest.sections[1].entries, ["Faster loading."]);
+
+        let oldest = &changelog.versions[3];
+        assert_eq!(oldest.date, None);
+        assert_eq!(oldest.sections[0].name, "Changes");
+        assert_eq!(oldest.sections[0].entries, ["Entry without a section."]);
+    }
+
+    #[test]
+    fn skips_lines_outside_of_versions() {
+        let changelog = Changelog::parse("garbage\n  - entry\nVersion: 1.0.0\n  Bugfixes:\n");
+
+        assert_eq!(changelog.versions.len(), 1);
+        assert!(changelog.versions[0].sections[0].entries.is_empty());
+    }
+
+    #[test]
+    fn range_crosses_major_versions() {
+        let changelog = Changelog::parse(CHANGELOG);
+
+        assert_eq!(
+            versions(&changelog.between(Some(&version("1.1.109")), Some(&version("2.0.0")))),
+            ["2.0.0", "1.1.110"]
+        );
+        assert_eq!(
+            versions(&changelog.between(Some(&version("1.1.110")), Some(&version("2.0.1")))),
+            ["2.0.1", "2.0.0"]
+        );
+    }
+
+    #[test]
+    fn open_ranges() {
+        let changelog = Changelog::parse(CHANGELOG);
+
+        assert_eq!(
+            versions(&changelog.between(Some(&version("2.0.0")), None)),
+            ["2.0.1"]
+        );
+        assert_eq!(
+            versions(&changelog.between(None, Some(&version("1.1.110")))),
+            ["1.1.110", "1.1.109"]
+        );
+        assert!(
+            changelog
+                .between(Some(&version("2.0.1")), Some(&version("2.0.1")))
+                .is_empty()
+        );
+    }
+}
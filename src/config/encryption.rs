@@ -0,0 +1,115 @@
+use argon2::Argon2;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, KeyInit, Nonce,
+    aead::{Aead, AeadCore, OsRng, rand_core::RngCore},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigError;
+
+/// Token encrypted with key derived from user's passphrase (Argon2 + ChaCha20-Poly1305),
+/// all fields are base64 encoded
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncryptedToken {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, ConfigError> {
+    let mut key = Key::default();
+
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|_| ConfigError::Encryption)?;
+
+    Ok(key)
+}
+
+impl EncryptedToken {
+    pub fn encrypt(token: &str, passphrase: &str) -> Result<Self, ConfigError> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+
+        let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let ciphertext = cipher
+            .encrypt(&nonce, token.as_bytes())
+            .map_err(|_| ConfigError::Encryption)?;
+
+        Ok(Self {
+            salt: BASE64_STANDARD.encode(salt),
+            nonce: BASE64_STANDARD.encode(nonce),
+            ciphertext: BASE64_STANDARD.encode(ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<String, ConfigError> {
+        let decode = |data: &str| {
+            BASE64_STANDARD
+                .decode(data)
+                .map_err(|_| ConfigError::Encryption)
+        };
+
+        let salt = decode(&self.salt)?;
+        let nonce = decode(&self.nonce)?;
+        let ciphertext = decode(&self.ciphertext)?;
+
+        if nonce.len() != 12 {
+            return Err(ConfigError::Encryption);
+        }
+
+        let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+        let token = cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| ConfigError::WrongPassphrase)?;
+
+        String::from_utf8(token).map_err(|_| ConfigError::Encryption)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypts_with_same_passphrase() {
+        let encrypted = EncryptedToken::encrypt("abc123", "correct horse").unwrap();
+
+        assert_ne!(encrypted.ciphertext, BASE64_STANDARD.encode("abc123"));
+        assert_eq!(encrypted.decrypt("correct horse").unwrap(), "abc123");
+    }
+
+    #[test]
+    fn rejects_wrong_passphrase() {
+        let encrypted = EncryptedToken::encrypt("abc123", "correct horse").unwrap();
+
+        assert!(matches!(
+            encrypted.decrypt("battery staple"),
+            Err(ConfigError::WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn salt_and_nonce_differ_between_encryptions() {
+        let first = EncryptedToken::encrypt("abc123", "correct horse").unwrap();
+        let second = EncryptedToken::encrypt("abc123", "correct horse").unwrap();
+
+        assert_ne!(first.salt, second.salt);
+        assert_ne!(first.nonce, second.nonce);
+        assert_ne!(first.ciphertext, second.ciphertext);
+    }
+
+    #[test]
+    fn rejects_tampered_token() {
+        let mut encrypted = EncryptedToken::encrypt("abc123", "correct horse").unwrap();
+        encrypted.nonce = BASE64_STANDARD.encode([0u8; 4]);
+
+        assert!(matches!(
+            encrypted.decrypt("correct horse"),
+            Err(ConfigError::Encryption)
+        ));
+    }
+}
@@ -0,0 +1,104 @@
+use std::{path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::config::ConfigError;
+
+/// Settings of the HTTP client used for API requests and downloads
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Proxy for all requests (http://, https://, socks5:// or socks5h://),
+    /// system proxy from environment is used when missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_password: Option<String>,
+    /// PEM files with additional trusted root certificates
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ca_certificates: Vec<PathBuf>,
+    /// Seconds to wait for connection
+    pub connect_timeout: u64,
+    /// Seconds to wait for each read, so stalled downloads fail instead of hanging
+    pub read_timeout: u64,
+    /// Defaults to factorio-updater/<version>
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            proxy_username: None,
+            proxy_password: None,
+            ca_certificates: Vec::new(),
+            connect_timeout: 30,
+            read_timeout: 60,
+            user_agent: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for HttpConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpConfig")
+            .field("proxy", &self.proxy)
+            .field("proxy_username", &self.proxy_username)
+            .field(
+                "proxy_password",
+                &self.proxy_password.as_ref().map(|_| "<redacted>"),
+            )
+            .field("ca_certificates", &self.ca_certificates)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("read_timeout", &self.read_timeout)
+            .field("user_agent", &self.user_agent)
+            .finish()
+    }
+}
+
+impl HttpConfig {
+    pub async fn client(&self) -> Result<reqwest::Client, ConfigError> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_timeout))
+            .read_timeout(Duration::from_secs(self.read_timeout))
+            .user_agent(
+                self.user_agent
+                    .clone()
+                    .unwrap_or_else(|| format!("factorio-updater/{}", env!("CARGO_PKG_VERSION"))),
+            );
+
+        if let Some(proxy) = &self.proxy {
+            let mut proxy = reqwest::Proxy::all(proxy).map_err(ConfigError::Http)?;
+
+            if let Some(username) = &self.proxy_username {
+                proxy = proxy.basic_auth(username, self.proxy_password.as_deref().unwrap_or(""));
+            }
+
+            builder = builder.proxy(proxy);
+        }
+
+        for path in &self.ca_certificates {
+            let data = match fs::read(path).await {
+                Ok(data) => data,
+                Err(_) => return Err(ConfigError::Read(path.clone())),
+            };
+
+            let certificates = reqwest::Certificate::from_pem_bundle(&data)
+                .map_err(|_| ConfigError::Certificate(path.clone()))?;
+
+            if certificates.is_empty() {
+                return Err(ConfigError::Certificate(path.clone()));
+            }
+
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        builder.build().map_err(ConfigError::Http)
+    }
+}
@@ -1,12 +1,150 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
-use tokio::fs;
+use tokio::{fs, io::AsyncWriteExt};
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Config {
+use crate::{
+    config::{encryption::EncryptedToken, http::HttpConfig},
+    hooks::Hooks,
+    structs::EndpointArgs,
+    webhooks::Webhook,
+};
+
+pub mod encryption;
+pub mod http;
+pub mod player_data;
+
+/// Name of profile, which holds credentials from configs without profiles
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// factorio.com account stored in a profile
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Account {
     pub username: String,
+    /// Empty while account is locked, never saved when `encrypted_token` is set
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_token: Option<EncryptedToken>,
+}
+
+impl std::fmt::Debug for Account {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Account")
+            .field("username", &self.username)
+            .field("token", &"<redacted>")
+            .field("encrypted", &self.encrypted_token.is_some())
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Config {
+    /// Accounts by profile name
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Account>,
+    /// Profile used when neither installation nor CLI selects one
+    #[serde(default = "default_profile")]
+    pub active_profile: String,
+    #[serde(default)]
+    pub endpoints: Endpoints,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// Notified about available updates and update results
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<Webhook>,
+    /// Commands run around the stages of updates of all installations
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+}
+
+fn default_profile() -> String {
+    DEFAULT_PROFILE.to_string()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            profiles: BTreeMap::new(),
+            active_profile: default_profile(),
+            endpoints: Endpoints::default(),
+            http: HttpConfig::default(),
+            cache: CacheConfig::default(),
+            webhooks: Vec::new(),
+            hooks: Hooks::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Seconds, for which cached version listing is used without asking the API
+    pub max_age: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { max_age: 3600 }
+    }
+}
+
+/// Base URLs of the Factorio APIs, can point to a mirror (see `serve` command) or a proxy
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Endpoints {
+    /// get-available-versions and get-download-link
+    pub updater: String,
+    /// get-download of full game packages
+    pub download: String,
+    /// api-login for obtaining token with password
+    pub auth: String,
+    /// Mod portal (mod listings and downloads)
+    pub mods: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            updater: "https://updater.factorio.com".to_string(),
+            download: "https://factorio.com".to_string(),
+            auth: "https://auth.factorio.com".to_string(),
+            mods: "https://mods.factorio.com".to_string(),
+        }
+    }
+}
+
+impl Endpoints {
+    /// Applies URLs passed from CLI or environment
+    pub fn with_overrides(mut self, overrides: &EndpointArgs) -> Self {
+        if let Some(updater) = &overrides.updater_url {
+            self.updater = updater.clone();
+        }
+
+        if let Some(download) = &overrides.download_url {
+            self.download = download.clone();
+        }
+
+        if let Some(auth) = &overrides.auth_url {
+            self.auth = auth.clone();
+        }
+
+        if let Some(mods) = &overrides.mods_url {
+            self.mods = mods.clone();
+        }
+
+        self.updater = self.updater.trim_end_matches('/').to_string();
+        self.download = self.download.trim_end_matches('/').to_string();
+        self.auth = self.auth.trim_end_matches('/').to_string();
+        self.mods = self.mods.trim_end_matches('/').to_string();
+
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -16,22 +154,182 @@ pub enum ConfigError {
     Parse(serde_json::Error),
     CreateDirectory,
     Write(PathBuf),
+    Encryption,
+    WrongPassphrase,
+    /// File doesn't contain any PEM certificate
+    Certificate(PathBuf),
+    /// Invalid proxy or HTTP client settings
+    Http(reqwest::Error),
 }
 
-static FOLDER_NAME: &'static str = "factorio-updater";
+static FOLDER_NAME: &str = "factorio-updater";
 
-impl Config {
+/// Folder for cached version listings and downloaded packages
+pub fn cache_dir() -> Result<PathBuf, ConfigError> {
+    match dirs::cache_dir() {
+        Some(dir) => Ok(dir.join(FOLDER_NAME)),
+        None => Err(ConfigError::NoConfigDir),
+    }
+}
+
+/// Folder for data, that has to survive clearing of the cache (e.g. mods of mod profiles)
+pub fn data_dir() -> Result<PathBuf, ConfigError> {
+    match dirs::data_dir() {
+        Some(dir) => Ok(dir.join(FOLDER_NAME)),
+        None => Err(ConfigError::NoConfigDir),
+    }
+}
+
+#[cfg(unix)]
+async fn has_loose_permissions(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    match fs::metadata(path).await {
+        Ok(metadata) => metadata.permissions().mode() & 0o077 != 0,
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(unix))]
+async fn has_loose_permissions(_: &Path) -> bool {
+    false
+}
+
+/// Writes file readable only by its owner
+pub(crate) async fn write_private(path: &Path, data: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(path).await?;
+
+    //mode is only applied to new files, so fix permissions of existing one
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .await?;
+    }
+
+    file.write_all(data.as_bytes()).await?;
+    file.flush().await
+}
+
+impl Account {
     pub fn new(username: String, token: String) -> Self {
-        Self { username, token }
+        Self {
+            username,
+            token,
+            encrypted_token: None,
+        }
     }
 
-    pub async fn load() -> Result<Option<Self>, ConfigError> {
-        let config = match dirs::config_dir() {
-            Some(dir) => dir,
-            None => return Err(ConfigError::NoConfigDir),
-        };
+    /// Token is encrypted and needs to be unlocked with passphrase
+    pub fn is_locked(&self) -> bool {
+        self.encrypted_token.is_some() && self.token.is_empty()
+    }
+
+    pub fn unlock(&mut self, passphrase: &str) -> Result<(), ConfigError> {
+        if let Some(encrypted) = &self.encrypted_token {
+            self.token = encrypted.decrypt(passphrase)?;
+        }
 
-        let config = config.join(Path::new(FOLDER_NAME)).join("config.json");
+        Ok(())
+    }
+
+    /// Token will be saved encrypted with passphrase
+    pub fn encrypt(&mut self, passphrase: &str) -> Result<(), ConfigError> {
+        self.encrypted_token = Some(EncryptedToken::encrypt(&self.token, passphrase)?);
+        Ok(())
+    }
+
+    /// Token will be saved as plain text again
+    pub fn remove_encryption(&mut self) {
+        self.encrypted_token = None;
+    }
+}
+
+impl Config {
+    fn path() -> Result<PathBuf, ConfigError> {
+        match dirs::config_dir() {
+            Some(dir) => Ok(dir.join(Path::new(FOLDER_NAME)).join("config.json")),
+            None => Err(ConfigError::NoConfigDir),
+        }
+    }
+
+    /// Warning for config file, which can be read by other users
+    pub async fn permission_warning() -> Option<String> {
+        let path = Self::path().ok()?;
+
+        if has_loose_permissions(&path).await {
+            Some(format!(
+                "{} can be read by other users, it will be restricted to you on next save (or run chmod 600 on it).",
+                path.display()
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Account of profile, or of the active profile
+    pub fn account(&self, profile: Option<&str>) -> Option<&Account> {
+        self.profiles.get(profile.unwrap_or(&self.active_profile))
+    }
+
+    /// Stores account under profile name and makes the profile active
+    pub fn set_account(&mut self, profile: String, account: Account) {
+        self.profiles.insert(profile.clone(), account);
+        self.active_profile = profile;
+    }
+
+    /// Removes profile, the first remaining profile (or the default one) becomes active instead
+    /// of the removed one
+    pub fn remove_profile(&mut self, name: &str) -> Option<Account> {
+        let account = self.profiles.remove(name)?;
+
+        if self.active_profile == name {
+            self.active_profile = self
+                .profiles
+                .keys()
+                .next()
+                .cloned()
+                .unwrap_or_else(default_profile);
+        }
+
+        Some(account)
+    }
+
+    /// Token of profile is stored encrypted, so new logins need its passphrase
+    pub fn is_encrypted(&self, profile: &str) -> bool {
+        self.profiles
+            .get(profile)
+            .is_some_and(|account| account.encrypted_token.is_some())
+    }
+
+    /// Stores account of a new login like `set_account`, but encrypted profile stays encrypted,
+    /// `passphrase` has to unlock its current token
+    pub fn set_login(
+        &mut self,
+        profile: String,
+        mut account: Account,
+        passphrase: Option<&str>,
+    ) -> Result<(), ConfigError> {
+        if let Some(stored) = self.profiles.get(&profile)
+            && stored.encrypted_token.is_some()
+        {
+            let passphrase = passphrase.ok_or(ConfigError::WrongPassphrase)?;
+            stored.clone().unlock(passphrase)?;
+            account.encrypt(passphrase)?;
+        }
+
+        self.set_account(profile, account);
+        Ok(())
+    }
+
+    pub async fn load() -> Result<Option<Self>, ConfigError> {
+        let config = Self::path()?;
 
         if !match fs::try_exists(&config).await {
             Ok(exists) => exists,
@@ -45,37 +343,163 @@ impl Config {
             Err(_) => return Err(ConfigError::Read(config)),
         };
 
-        let config: Config = match serde_json::from_str(&data) {
+        if has_loose_permissions(&config).await {
+            log::warn!("{} can be read by other users", config.display());
+        }
+
+        let mut config: Config = match serde_json::from_str(&data) {
             Ok(config) => config,
             Err(err) => return Err(ConfigError::Parse(err)),
         };
 
+        //configs from before profiles have the account at top level
+        if config.profiles.is_empty()
+            && let Ok(account) = serde_json::from_str::<Account>(&data)
+        {
+            config.profiles.insert(DEFAULT_PROFILE.to_string(), account);
+        }
+
         Ok(Some(config))
     }
 
+    /// Config as it is saved, tokens of encrypted profiles are left out
+    fn to_json(&self) -> Result<String, ConfigError> {
+        let mut stored = self.clone();
+        for account in stored.profiles.values_mut() {
+            if account.encrypted_token.is_some() {
+                account.token.clear();
+            }
+        }
+
+        serde_json::to_string_pretty(&stored).map_err(ConfigError::Parse)
+    }
+
     pub async fn save(&self) -> Result<(), ConfigError> {
-        let config_dir = match dirs::config_dir() {
-            Some(dir) => dir,
-            None => return Err(ConfigError::NoConfigDir),
-        };
+        let config_path = Self::path()?;
+        let config_dir = config_path.parent().ok_or(ConfigError::NoConfigDir)?;
 
-        let config_dir = config_dir.join(Path::new(FOLDER_NAME));
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
 
-        if let Err(_) = fs::create_dir_all(&config_dir).await {
+        #[cfg(unix)]
+        builder.mode(0o700);
+
+        if builder.create(config_dir).await.is_err() {
             return Err(ConfigError::CreateDirectory);
         }
 
-        let config_path = config_dir.join("config.json");
+        let data = self.to_json()?;
 
-        let data = match serde_json::to_string_pretty(self) {
-            Ok(data) => data,
-            Err(err) => return Err(ConfigError::Parse(err)),
-        };
-
-        if let Err(_) = fs::write(&config_path, data).await {
+        if write_private(&config_path, &data).await.is_err() {
             return Err(ConfigError::Write(config_path));
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypted_config() -> Config {
+        let mut account = Account::new("player".to_string(), "old-token".to_string());
+        account.encrypt("passphrase").unwrap();
+        account.token.clear();
+
+        let mut config = Config::default();
+        config.set_account("main".to_string(), account);
+        config
+    }
+
+    #[test]
+    fn login_keeps_profile_encrypted() {
+        let mut config = encrypted_config();
+        let login = Account::new("player".to_string(), "new-token".to_string());
+
+        config
+            .set_login("main".to_string(), login, Some("passphrase"))
+            .unwrap();
+
+        let account = config.account(Some("main")).unwrap();
+        assert_eq!(account.token, "new-token");
+        assert_eq!(
+            account
+                .encrypted_token
+                .as_ref()
+                .unwrap()
+                .decrypt("passphrase")
+                .unwrap(),
+            "new-token"
+        );
+
+        let saved = config.to_json().unwrap();
+        assert!(saved.contains("encrypted_token"));
+        assert!(!saved.contains("new-token"));
+    }
+
+    #[test]
+    fn login_of_encrypted_profile_needs_its_passphrase() {
+        let mut config = encrypted_config();
+        let login = Account::new("player".to_string(), "new-token".to_string());
+
+        assert!(matches!(
+            config.set_login("main".to_string(), login.clone(), Some("wrong")),
+            Err(ConfigError::WrongPassphrase)
+        ));
+        assert!(matches!(
+            config.set_login("main".to_string(), login, None),
+            Err(ConfigError::WrongPassphrase)
+        ));
+
+        let account = config.account(Some("main")).unwrap();
+        assert!(account.is_locked());
+        assert_eq!(
+            account
+                .encrypted_token
+                .as_ref()
+                .unwrap()
+                .decrypt("passphrase")
+                .unwrap(),
+            "old-token"
+        );
+    }
+
+    #[test]
+    fn removing_active_profile_activates_remaining_one() {
+        let mut config = Config::default();
+        for name in ["alt", "main", "work"] {
+            config.set_account(
+                name.to_string(),
+                Account::new(name.to_string(), "token".to_string()),
+            );
+        }
+
+        //inactive profile doesn't change the active one
+        assert!(config.remove_profile("alt").is_some());
+        assert_eq!(config.active_profile, "work");
+
+        assert!(config.remove_profile("work").is_some());
+        assert_eq!(config.active_profile, "main");
+
+        assert!(config.remove_profile("main").is_some());
+        assert_eq!(config.active_profile, DEFAULT_PROFILE);
+        assert!(config.remove_profile("main").is_none());
+    }
+
+    #[test]
+    fn login_of_plain_profile_stays_plain() {
+        let mut config = Config::default();
+
+        config
+            .set_login(
+                DEFAULT_PROFILE.to_string(),
+                Account::new("player".to_string(), "token".to_string()),
+                None,
+            )
+            .unwrap();
+
+        assert!(!config.is_encrypted(DEFAULT_PROFILE));
+        assert!(config.to_json().unwrap().contains("\"token\": \"token\""));
+    }
+}
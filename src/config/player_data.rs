@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use tokio::fs;
+
+use crate::config::{Account, ConfigError};
+
+/// Credentials stored by the game in player-data.json
+#[derive(Deserialize, Debug)]
+pub struct PlayerData {
+    #[serde(rename = "service-username")]
+    pub username: Option<String>,
+    #[serde(rename = "service-token")]
+    pub token: Option<String>,
+}
+
+/// Standard write-data locations of Factorio
+pub fn default_locations() -> Vec<PathBuf> {
+    let mut locations = Vec::new();
+
+    if let Some(home) = dirs::home_dir() {
+        locations.push(home.join(".factorio"));
+    }
+
+    if let Some(config) = dirs::config_dir() {
+        //%APPDATA% on Windows
+        locations.push(config.join("Factorio"));
+    }
+
+    if let Some(data) = dirs::data_dir() {
+        //~/Library/Application Support on macOS
+        locations.push(data.join("factorio"));
+    }
+
+    locations
+        .into_iter()
+        .map(|location| location.join("player-data.json"))
+        .collect()
+}
+
+impl PlayerData {
+    pub async fn load(path: &Path) -> Result<Self, ConfigError> {
+        let data = match fs::read_to_string(path).await {
+            Ok(data) => data,
+            Err(_) => return Err(ConfigError::Read(path.to_path_buf())),
+        };
+
+        serde_json::from_str(&data).map_err(ConfigError::Parse)
+    }
+
+    /// Finds first player-data.json with credentials, either on `path` or in default locations
+    pub async fn find(path: Option<&Path>) -> Result<Option<(PathBuf, Self)>, ConfigError> {
+        let locations = match path {
+            Some(path) => vec![path.to_path_buf()],
+            None => default_locations(),
+        };
+
+        for location in locations {
+            if !fs::try_exists(&location).await.unwrap_or(false) {
+                continue;
+            }
+
+            let data = Self::load(&location).await?;
+
+            if data.username.is_some() && data.token.is_some() {
+                return Ok(Some((location, data)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn into_account(self) -> Option<Account> {
+        Some(Account::new(self.username?, self.token?))
+    }
+}
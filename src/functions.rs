@@ -1,19 +1,73 @@
 use std::path::Path;
 
 use anyhow::Context;
-use serde_json::json;
 use tokio::fs;
 
-use crate::structs::{Args, Config};
+use crate::{
+    app::api::{Api, Connection},
+    config,
+    steps::credentials::unlocked_account,
+    structs::{Config, Credentials, EndpointArgs},
+};
 
-pub const API_VERSION: &str = "2";
+/// API client for CLI credentials or stored profile, endpoints are taken from config file and overridden by CLI/environment
+pub async fn create_api(
+    credentials: &Credentials,
+    overrides: &EndpointArgs,
+    profile: Option<&str>,
+) -> anyhow::Result<Api> {
+    if let Some(warning) = config::Config::permission_warning().await {
+        eprintln!("{}", console::style(warning).yellow());
+    }
+
+    let config = config::Config::load()
+        .await
+        .map_err(|err| anyhow::anyhow!("Failed to load config: {:?}", err))?
+        .unwrap_or_default();
+
+    let account = match (&credentials.username, &credentials.token) {
+        (Some(username), Some(token)) => config::Account::new(username.clone(), token.clone()),
+        _ => unlocked_account(&config, profile)
+            .context("Pass --username and --token, or log in with the login command")?,
+    };
+
+    Ok(Api::new(
+        account,
+        create_connection(&config, overrides).await?,
+    ))
+}
+
+/// Like `create_api`, but without any credentials or stored account falls back to
+/// anonymous access (public releases only)
+pub async fn create_api_or_anonymous(
+    credentials: &Credentials,
+    overrides: &EndpointArgs,
+    profile: Option<&str>,
+) -> anyhow::Result<Api> {
+    let config = config::Config::load()
+        .await
+        .map_err(|err| anyhow::anyhow!("Failed to load config: {:?}", err))?
+        .unwrap_or_default();
+
+    if credentials.username.is_none()
+        && credentials.token.is_none()
+        && profile.is_none()
+        && config.account(None).is_none()
+    {
+        return Ok(Api::anonymous(create_connection(&config, overrides).await?));
+    }
 
-pub fn get_base_query_params(args: &Args) -> serde_json::Value {
-    json!({
-        "username": args.username,
-        "token": args.token,
-        "version": API_VERSION,
-    })
+    create_api(credentials, overrides, profile).await
+}
+
+/// Endpoints and HTTP client configured in config
+pub async fn create_connection(
+    config: &config::Config,
+    overrides: &EndpointArgs,
+) -> anyhow::Result<Connection> {
+    Connection::new(config, overrides)
+        .await
+        .map_err(|err| anyhow::anyhow!("Invalid HTTP settings in config: {:?}", err))
 }
 
 pub async fn load_config(base_folder: &Path) -> anyhow::Result<Option<Config>> {
@@ -32,3 +86,13 @@ pub async fn load_config(base_folder: &Path) -> anyhow::Result<Option<Config>> {
         Ok(None)
     }
 }
+
+pub async fn save_config(base_folder: &Path, config: &Config) -> anyhow::Result<()> {
+    let config_data =
+        serde_json::to_string_pretty(config).context("Failed to serialize config file")?;
+
+    //lifecycle settings contain RCON password
+    config::write_private(&base_folder.join("config.json"), &config_data)
+        .await
+        .context("Failed to write config file")
+}
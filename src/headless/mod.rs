@@ -0,0 +1,482 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use tokio::{
+    fs,
+    process::{Child, Command},
+};
+
+use crate::structs::ServerSettings;
+
+pub mod rcon;
+pub mod snapshots;
+
+/// Created by running game in its write data folder, removed on exit
+pub const LOCK_FILE: &str = ".lock";
+/// Log of the running game, the previous one is renamed on start
+pub const LOG_FILE: &str = "factorio-current.log";
+
+/// Logged when the server hosts the game
+const STARTED_MARKER: &str = "to(InGame)";
+/// Logged when the server exits
+const EXITED_MARKER: &str = "Goodbye";
+/// Interval of checking lock and log files
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Headless server of an installation, write data are in the installation folder
+pub struct Server<'a> {
+    folder: &'a Path,
+    settings: &'a ServerSettings,
+}
+
+/// Command run by the system shell in the installation folder
+pub(crate) fn shell(command: &str, folder: &Path) -> Command {
+    #[cfg(unix)]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        //own process group, so Ctrl+C in the updater doesn't stop the server
+        shell.process_group(0);
+        shell
+    };
+
+    #[cfg(not(unix))]
+    let mut shell = {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    };
+
+    shell
+        .current_dir(folder)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    shell
+}
+
+async fn exists(path: &Path) -> anyhow::Result<bool> {
+    fs::try_exists(path)
+        .await
+        .with_context(|| format!("Failed to check if {} exists", path.display()))
+}
+
+#[cfg(unix)]
+async fn signal(pid: u32, force: bool) -> anyhow::Result<bool> {
+    let status = Command::new("kill")
+        .arg(if force { "-KILL" } else { "-TERM" })
+        .arg(pid.to_string())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .context("Unable to run kill")?;
+
+    Ok(status.success())
+}
+
+#[cfg(not(unix))]
+async fn signal(pid: u32, force: bool) -> anyhow::Result<bool> {
+    let mut taskkill = Command::new("taskkill");
+    taskkill.arg("/PID").arg(pid.to_string());
+    if force {
+        taskkill.arg("/F");
+    }
+
+    let status = taskkill
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .context("Unable to run taskkill")?;
+
+    Ok(status.success())
+}
+
+#[cfg(unix)]
+async fn is_alive(pid: u32) -> bool {
+    Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(unix))]
+async fn is_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .arg("/FI")
+        .arg(format!("PID eq {}", pid))
+        .output()
+        .await
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+}
+
+impl<'a> Server<'a> {
+    pub fn new(folder: &'a Path, settings: &'a ServerSettings) -> Self {
+        Self { folder, settings }
+    }
+
+    fn pid_path(&self) -> Option<PathBuf> {
+        self.settings
+            .pid_file
+            .as_ref()
+            .map(|pid_file| self.folder.join(pid_file))
+    }
+
+    async fn pid(&self) -> anyhow::Result<Option<u32>> {
+        let Some(path) = self.pid_path() else {
+            return Ok(None);
+        };
+        if !exists(&path).await? {
+            return Ok(None);
+        }
+
+        let pid = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        Ok(Some(pid.trim().parse().with_context(|| {
+            format!("{} doesn't contain PID", path.display())
+        })?))
+    }
+
+    /// Game keeps `.lock` in its write data folder while it runs
+    pub async fn is_running(&self) -> anyhow::Result<bool> {
+        exists(&self.folder.join(LOCK_FILE)).await
+    }
+
+    /// Waits until `.lock` disappears, returns false on timeout
+    async fn wait_for_exit(&self, timeout: Duration) -> anyhow::Result<bool> {
+        let start = tokio::time::Instant::now();
+
+        while self.is_running().await? {
+            if start.elapsed() >= timeout {
+                return Ok(false);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        Ok(true)
+    }
+
+    /// Stops the server with the stop command, or by signalling PID from PID file.
+    /// Server, which doesn't exit in graceful timeout, is killed when its PID is known.
+    pub async fn stop(&self) -> anyhow::Result<()> {
+        let pid = self.pid().await?;
+
+        match (&self.settings.stop_command, pid) {
+            (Some(command), _) => {
+                let status = shell(command, self.folder)
+                    .status()
+                    .await
+                    .context("Unable to run stop command")?;
+                if !status.success() {
+                    anyhow::bail!("Stop command failed with {}", status);
+                }
+            }
+            (None, Some(pid)) => {
+                if !signal(pid, false).await? {
+                    log::warn!("Unable to signal server process {}", pid);
+                }
+            }
+            (None, None) => anyhow::bail!(
+                "Unable to stop the server, set stop command or PID file (headless configure)"
+            ),
+        }
+
+        if self
+            .wait_for_exit(Duration::from_secs(self.settings.graceful_timeout))
+            .await?
+        {
+            return self.remove_pid_file().await;
+        }
+
+        let Some(pid) = pid else {
+            anyhow::bail!(
+                "Server didn't stop in {} seconds",
+                self.settings.graceful_timeout
+            );
+        };
+
+        log::warn!("Server didn't stop in time, killing process {}", pid);
+        signal(pid, true).await?;
+
+        let start = tokio::time::Instant::now();
+        while is_alive(pid).await {
+            if start.elapsed() >= Duration::from_secs(10) {
+                anyhow::bail!("Unable to kill server process {}", pid);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        //killed game can't remove its lock
+        let lock = self.folder.join(LOCK_FILE);
+        if exists(&lock).await? {
+            fs::remove_file(&lock)
+                .await
+                .context("Unable to remove lock of killed server")?;
+        }
+
+        self.remove_pid_file().await
+    }
+
+    async fn remove_pid_file(&self) -> anyhow::Result<()> {
+        if let Some(path) = self.pid_path()
+            && exists(&path).await?
+        {
+            fs::remove_file(&path)
+                .await
+                .with_context(|| format!("Unable to remove {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the start command and waits until the log reports hosted game
+    pub async fn start(&self) -> anyhow::Result<()> {
+        let command = self
+            .settings
+            .start_command
+            .as_deref()
+            .context("Unable to start the server, set start command (headless configure)")?;
+
+        if self.is_running().await? {
+            anyhow::bail!(
+                "Server is already running, {} exists",
+                self.folder.join(LOCK_FILE).display()
+            );
+        }
+        //left by a crashed server
+        self.remove_pid_file().await?;
+
+        //file times come from a coarse clock, so log of this run is told apart by a changed one
+        let previous_log = self.log_modified().await;
+        //PID file is left to the start command, PID of the shell running it isn't the game's
+        let mut child = shell(command, self.folder)
+            .spawn()
+            .context("Unable to run start command")?;
+
+        self.wait_for_start(&mut child, previous_log).await
+    }
+
+    async fn wait_for_start(
+        &self,
+        child: &mut Child,
+        previous_log: Option<SystemTime>,
+    ) -> anyhow::Result<()> {
+        let log_path = self.folder.join(LOG_FILE);
+        let timeout = Duration::from_secs(self.settings.startup_timeout);
+        let start = tokio::time::Instant::now();
+        let mut exited = false;
+
+        loop {
+            //commands of services exit right away, only failures matter
+            if !exited
+                && let Some(status) = child.try_wait().context("Unable to check server process")?
+            {
+                if !status.success() {
+                    anyhow::bail!("Start command failed with {}", status);
+                }
+                exited = true;
+            }
+
+            if let Some(log) = self.current_log(&log_path, previous_log).await? {
+                if log.contains(STARTED_MARKER) {
+                    return Ok(());
+                }
+                if log.contains(EXITED_MARKER) {
+                    anyhow::bail!(
+                        "Server exited during start, see {}:\n{}",
+                        log_path.display(),
+                        last_lines(&log, 5)
+                    );
+                }
+            }
+
+            if start.elapsed() >= timeout {
+                anyhow::bail!(
+                    "Server didn't host the game in {} seconds, see {}",
+                    self.settings.startup_timeout,
+                    log_path.display()
+                );
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn log_modified(&self) -> Option<SystemTime> {
+        let metadata = fs::metadata(self.folder.join(LOG_FILE)).await.ok()?;
+        metadata.modified().ok()
+    }
+
+    /// Log modified since `previous` time, unchanged log is from the previous run
+    async fn current_log(
+        &self,
+        path: &Path,
+        previous: Option<SystemTime>,
+    ) -> anyhow::Result<Option<String>> {
+        let Ok(metadata) = fs::metadata(path).await else {
+            return Ok(None);
+        };
+        if previous.is_some() && metadata.modified().ok() == previous {
+            return Ok(None);
+        }
+
+        let data = fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        Ok(Some(String::from_utf8_lossy(&data).to_string()))
+    }
+}
+
+fn last_lines(text: &str, count: usize) -> String {
+    let lines = text.lines().collect::<Vec<_>>();
+    lines[lines.len().saturating_sub(count)..].join("\n")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    /// Game, which writes its PID, lock and log like the real one, `$1` is its fate after start.
+    /// Crashing game exits successfully, so only its log tells about the failure
+    const FAKE_GAME: &str = r#"
+echo $$ > server.pid
+touch .lock
+echo "0.000 Loading map" > factorio-current.log
+case "$1" in
+    crash) echo "0.500 Goodbye" >> factorio-current.log; rm .lock; exit 0 ;;
+    hang) trap '' TERM ;;
+    *) trap 'echo "9.000 Goodbye" >> factorio-current.log; rm .lock; exit 0' TERM ;;
+esac
+sleep 0.5
+[ "$1" = "stuck" ] || echo "1.000 changing state from(CreatingGame) to(InGame)" >> factorio-current.log
+while true; do sleep 0.1; done
+"#;
+
+    fn server_folder() -> TempDir {
+        let folder = TempDir::new("server").unwrap();
+        std::fs::write(folder.path().join("game.sh"), FAKE_GAME).unwrap();
+        folder
+    }
+
+    fn settings(fate: &str) -> ServerSettings {
+        ServerSettings {
+            start_command: Some(format!("exec sh game.sh {}", fate)),
+            pid_file: Some(PathBuf::from("server.pid")),
+            graceful_timeout: 2,
+            startup_timeout: 5,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn starts_and_stops_with_pid_file() {
+        let folder = server_folder();
+        let settings = settings("run");
+        let server = Server::new(folder.path(), &settings);
+
+        server.start().await.unwrap();
+        assert!(server.is_running().await.unwrap());
+        let pid = server.pid().await.unwrap().unwrap();
+        assert!(is_alive(pid).await);
+
+        server.stop().await.unwrap();
+        assert!(!server.is_running().await.unwrap());
+        assert!(!folder.path().join("server.pid").exists());
+        assert!(
+            std::fs::read_to_string(folder.path().join(LOG_FILE))
+                .unwrap()
+                .contains(EXITED_MARKER)
+        );
+    }
+
+    #[tokio::test]
+    async fn kills_server_ignoring_stop() {
+        let folder = server_folder();
+        let settings = settings("hang");
+        let server = Server::new(folder.path(), &settings);
+
+        server.start().await.unwrap();
+        let pid = server.pid().await.unwrap().unwrap();
+
+        server.stop().await.unwrap();
+        assert!(!is_alive(pid).await);
+        assert!(!server.is_running().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn reports_server_exiting_during_start() {
+        let folder = server_folder();
+        let settings = settings("crash");
+        let server = Server::new(folder.path(), &settings);
+
+        let err = server.start().await.unwrap_err();
+
+        assert!(err.to_string().contains("exited"), "{:#}", err);
+        assert!(!server.is_running().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn times_out_without_hosted_game() {
+        let folder = server_folder();
+        let mut settings = settings("stuck");
+        settings.startup_timeout = 1;
+        let server = Server::new(folder.path(), &settings);
+
+        let err = server.start().await.unwrap_err();
+        assert!(
+            err.to_string().contains("didn't host the game"),
+            "{:#}",
+            err
+        );
+
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn refuses_start_while_locked() {
+        let folder = server_folder();
+        std::fs::write(folder.path().join(LOCK_FILE), "").unwrap();
+        let settings = settings("run");
+        let server = Server::new(folder.path(), &settings);
+
+        let err = server.start().await.unwrap_err();
+
+        assert!(err.to_string().contains("already running"), "{:#}", err);
+        assert!(!folder.path().join("server.pid").exists());
+    }
+
+    #[tokio::test]
+    async fn ignores_log_of_previous_run() {
+        let folder = server_folder();
+        let log = folder.path().join(LOG_FILE);
+        std::fs::write(&log, "1.000 changing state from(CreatingGame) to(InGame)").unwrap();
+        let old = SystemTime::now() - Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(&log)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        let settings = settings("run");
+        let server = Server::new(folder.path(), &settings);
+        let previous = server.log_modified().await;
+        assert_eq!(previous, Some(old));
+        assert!(server.current_log(&log, previous).await.unwrap().is_none());
+
+        std::fs::write(&log, "0.000 Loading map").unwrap();
+        assert!(server.current_log(&log, previous).await.unwrap().is_some());
+    }
+}
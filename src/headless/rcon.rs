@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::timeout,
+};
+
+/// Packet types of the Source RCON protocol
+const AUTH: i32 = 3;
+const AUTH_RESPONSE: i32 = 2;
+const EXEC_COMMAND: i32 = 2;
+const RESPONSE_VALUE: i32 = 0;
+
+/// Biggest packet the server sends, bigger responses are split
+const MAX_PACKET: i32 = 4096 + 10;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Packet {
+    id: i32,
+    kind: i32,
+    body: String,
+}
+
+/// Client of the game's RCON interface (--rcon-port and --rcon-password of the server)
+pub struct Rcon {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+impl Rcon {
+    /// Connects and authenticates with the password
+    pub async fn connect(address: &str, password: &str) -> anyhow::Result<Self> {
+        let stream = timeout(TIMEOUT, TcpStream::connect(address))
+            .await
+            .with_context(|| format!("Connecting to RCON at {} timed out", address))?
+            .with_context(|| format!("Unable to connect to RCON at {}", address))?;
+
+        let mut rcon = Self { stream, next_id: 1 };
+        let id = rcon.send(AUTH, password).await?;
+
+        loop {
+            let packet = rcon.receive().await?;
+            //some servers send empty response before the auth response
+            if packet.kind != AUTH_RESPONSE {
+                continue;
+            }
+            if packet.id == -1 {
+                anyhow::bail!("RCON password was rejected by {}", address);
+            }
+            if packet.id == id {
+                return Ok(rcon);
+            }
+        }
+    }
+
+    /// Runs a command (or sends a chat message without leading slash), returns its output
+    pub async fn command(&mut self, command: &str) -> anyhow::Result<String> {
+        let id = self.send(EXEC_COMMAND, command).await?;
+
+        loop {
+            let packet = self.receive().await?;
+            if packet.kind == RESPONSE_VALUE && packet.id == id {
+                return Ok(packet.body);
+            }
+        }
+    }
+
+    async fn send(&mut self, kind: i32, body: &str) -> anyhow::Result<i32> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut packet = Vec::with_capacity(body.len() + 14);
+        packet.extend((body.len() as i32 + 10).to_le_bytes());
+        packet.extend(id.to_le_bytes());
+        packet.extend(kind.to_le_bytes());
+        packet.extend(body.as_bytes());
+        packet.extend([0, 0]);
+
+        timeout(TIMEOUT, self.stream.write_all(&packet))
+            .await
+            .context("Sending RCON packet timed out")?
+            .context("Unable to send RCON packet")?;
+
+        Ok(id)
+    }
+
+    async fn receive(&mut self) -> anyhow::Result<Packet> {
+        timeout(TIMEOUT, self.read_packet())
+            .await
+            .context("Server didn't respond over RCON")?
+    }
+
+    async fn read_packet(&mut self) -> anyhow::Result<Packet> {
+        let size = self
+            .stream
+            .read_i32_le()
+            .await
+            .context("RCON connection was closed")?;
+        if !(10..=MAX_PACKET).contains(&size) {
+            anyhow::bail!("Invalid RCON packet size {}", size);
+        }
+
+        let mut data = vec![0; size as usize];
+        self.stream
+            .read_exact(&mut data)
+            .await
+            .context("RCON connection was closed")?;
+
+        let id = i32::from_le_bytes(data[0..4].try_into().unwrap());
+        let kind = i32::from_le_bytes(data[4..8].try_into().unwrap());
+        //body is followed by two null bytes
+        let body = String::from_utf8_lossy(&data[8..data.len() - 2]).to_string();
+
+        Ok(Packet { id, kind, body })
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use tokio::fs;
+
+use crate::{headless::rcon::Rcon, mods::settings::MOD_SETTINGS_FILE};
+
+/// Saves of the server, in its write data folder
+pub const SAVES_FOLDER: &str = "saves";
+/// Snapshots of saves taken before updates, in the installation folder
+pub const BACKUPS_FOLDER: &str = "save-backups";
+
+/// Snapshot folder, named `<unix time>-<game version>`, with `-<n>` appended for further
+/// snapshots taken in the same second
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub time: u64,
+    pub version: String,
+    /// File name of the save
+    pub save: Option<String>,
+}
+
+/// Newest save of the installation with its modification time
+async fn newest_save(folder: &Path) -> anyhow::Result<Option<(PathBuf, SystemTime)>> {
+    let saves = folder.join(SAVES_FOLDER);
+    if !fs::try_exists(&saves)
+        .await
+        .context("Failed to check if saves folder exists")?
+    {
+        return Ok(None);
+    }
+
+    let mut newest: Option<(PathBuf, SystemTime)> = None;
+    let mut entries = fs::read_dir(&saves)
+        .await
+        .with_context(|| format!("Failed to read {}", saves.display()))?;
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .with_context(|| format!("Failed to read {}", saves.display()))?
+    {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "zip") {
+            continue;
+        }
+
+        let modified = entry
+            .metadata()
+            .await
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        if newest.as_ref().is_none_or(|(_, time)| modified > *time) {
+            newest = Some((path, modified));
+        }
+    }
+
+    Ok(newest)
+}
+
+/// Saves the game over RCON and waits until a save is written
+pub async fn save_over_rcon(
+    folder: &Path,
+    rcon: &mut Rcon,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let before = newest_save(folder).await?.map(|(_, time)| time);
+    rcon.command("/server-save").await?;
+
+    //the game writes the save in background
+    let start = tokio::time::Instant::now();
+    loop {
+        if let Some((_, time)) = newest_save(folder).await?
+            && before.is_none_or(|before| time > before)
+        {
+            //file is renamed into place when complete, give it a moment anyway
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            return Ok(());
+        }
+
+        if start.elapsed() >= timeout {
+            anyhow::bail!("Game wasn't saved in {} seconds", timeout.as_secs());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// Snapshots of the installation, the oldest first
+pub async fn list(folder: &Path) -> anyhow::Result<Vec<Snapshot>> {
+    let backups = folder.join(BACKUPS_FOLDER);
+    if !fs::try_exists(&backups)
+        .await
+        .context("Failed to check if backups folder exists")?
+    {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    let mut entries = fs::read_dir(&backups)
+        .await
+        .with_context(|| format!("Failed to read {}", backups.display()))?;
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .with_context(|| format!("Failed to read {}", backups.display()))?
+    {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some((time, version)) = name.split_once('-') else {
+            continue;
+        };
+        let Ok(time) = time.parse::<u64>() else {
+            continue;
+        };
+        let (version, counter) = match version.split_once('-') {
+            Some((version, counter)) => match counter.parse::<u32>() {
+                Ok(counter) => (version, counter),
+                Err(_) => continue,
+            },
+            None => (version, 1),
+        };
+
+        let mut save = None;
+        let mut files = fs::read_dir(entry.path())
+            .await
+            .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        while let Some(file) = files
+            .next_entry()
+            .await
+            .with_context(|| format!("Failed to read {}", entry.path().display()))?
+        {
+            let file_name = file.file_name().to_string_lossy().to_string();
+            if file_name.ends_with(".zip") {
+                save = Some(file_name);
+            }
+        }
+
+        snapshots.push((
+            counter,
+            Snapshot {
+                path: entry.path(),
+                time,
+                version: version.to_string(),
+                save,
+            },
+        ));
+    }
+
+    snapshots.sort_by_key(|(counter, snapshot)| (snapshot.time, *counter));
+    Ok(snapshots
+        .into_iter()
+        .map(|(_, snapshot)| snapshot)
+        .collect())
+}
+
+/// Copies the newest save (with mod settings) into a snapshot tagged with the game version,
+/// only `keep` newest snapshots are kept. Nothing is done, when there are no saves.
+pub async fn take(folder: &Path, version: &str, keep: usize) -> anyhow::Result<Option<Snapshot>> {
+    let Some((save, _)) = newest_save(folder).await? else {
+        return Ok(None);
+    };
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+    let backups = folder.join(BACKUPS_FOLDER);
+    fs::create_dir_all(&backups)
+        .await
+        .with_context(|| format!("Unable to create {}", backups.display()))?;
+
+    //existing snapshot is never written into, the next free name is taken instead
+    let mut counter = 1;
+    let path = loop {
+        let name = match counter {
+            1 => format!("{}-{}", time, version),
+            _ => format!("{}-{}-{}", time, version, counter),
+        };
+        let path = backups.join(name);
+        match fs::create_dir(&path).await {
+            Ok(()) => break path,
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => counter += 1,
+            Err(err) => {
+                return Err(err).with_context(|| format!("Unable to create {}", path.display()));
+            }
+        }
+    };
+
+    let save_name = save
+        .file_name()
+        .context("Save has no file name")?
+        .to_string_lossy()
+        .to_string();
+    fs::copy(&save, path.join(&save_name))
+        .await
+        .with_context(|| format!("Unable to copy {}", save.display()))?;
+
+    let settings = folder.join("mods").join(MOD_SETTINGS_FILE);
+    if fs::try_exists(&settings)
+        .await
+        .context("Failed to check if mod settings exist")?
+    {
+        fs::copy(&settings, path.join(MOD_SETTINGS_FILE))
+            .await
+            .context("Unable to copy mod settings")?;
+    }
+
+    let snapshots = list(folder).await?;
+    for old in snapshots.iter().take(snapshots.len().saturating_sub(keep)) {
+        fs::remove_dir_all(&old.path)
+            .await
+            .with_context(|| format!("Unable to remove {}", old.path.display()))?;
+    }
+
+    Ok(Some(Snapshot {
+        path,
+        time,
+        version: version.to_string(),
+        save: Some(save_name),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn snapshots_in_same_second_dont_collide() {
+        let folder = tempdir::TempDir::new("snapshots").unwrap();
+        std::fs::create_dir(folder.path().join(SAVES_FOLDER)).unwrap();
+        let save = folder.path().join(SAVES_FOLDER).join("world.zip");
+
+        let mut taken = Vec::new();
+        for content in ["first", "second", "third"] {
+            std::fs::write(&save, content).unwrap();
+            taken.push(take(folder.path(), "2.0.1", 2).await.unwrap().unwrap());
+        }
+
+        //names only differ when taken in the same second
+        let snapshots = list(folder.path()).await.unwrap();
+        assert_eq!(snapshots.len(), 2);
+        for (snapshot, taken) in snapshots.iter().zip(&taken[1..]) {
+            assert_eq!(snapshot.path, taken.path);
+            assert_eq!(snapshot.version, "2.0.1");
+            assert_eq!(snapshot.save.as_deref(), Some("world.zip"));
+        }
+        assert_eq!(
+            std::fs::read_to_string(snapshots[1].path.join("world.zip")).unwrap(),
+            "third"
+        );
+    }
+}
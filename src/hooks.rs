@@ -0,0 +1,190 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use anyhow::Context;
+use console::style;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, headless::shell};
+
+/// Commands run by the system shell around the stages of an update
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Hooks {
+    /// Failing pre-hooks abort the update
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_download: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_download: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_apply: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_apply: Option<String>,
+    /// Restart hooks only run, when a running headless server is restarted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_restart: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_restart: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Stage {
+    Download,
+    Apply,
+    Restart,
+}
+
+impl Stage {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Download => "download",
+            Self::Apply => "apply",
+            Self::Restart => "restart",
+        }
+    }
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        [
+            &self.pre_download,
+            &self.post_download,
+            &self.pre_apply,
+            &self.post_apply,
+            &self.pre_restart,
+            &self.post_restart,
+        ]
+        .iter()
+        .all(|hook| hook.is_none())
+    }
+
+    fn command(&self, stage: Stage, pre: bool) -> Option<&str> {
+        match (stage, pre) {
+            (Stage::Download, true) => self.pre_download.as_deref(),
+            (Stage::Download, false) => self.post_download.as_deref(),
+            (Stage::Apply, true) => self.pre_apply.as_deref(),
+            (Stage::Apply, false) => self.post_apply.as_deref(),
+            (Stage::Restart, true) => self.pre_restart.as_deref(),
+            (Stage::Restart, false) => self.post_restart.as_deref(),
+        }
+    }
+}
+
+/// Global hooks from config file and hooks of the installation, global ones run first
+pub struct HookRunner {
+    hooks: Vec<Hooks>,
+    folder: PathBuf,
+    env: Vec<(&'static str, String)>,
+}
+
+impl HookRunner {
+    pub async fn load(
+        folder: &Path,
+        installation: Option<&Hooks>,
+        arch: String,
+        from: Option<String>,
+        to: String,
+    ) -> Self {
+        let global = match Config::load().await {
+            Ok(config) => config.unwrap_or_default().hooks,
+            Err(err) => {
+                log::warn!("Global hooks skipped, unable to load config: {:?}", err);
+                Hooks::default()
+            }
+        };
+
+        Self {
+            hooks: [Some(global), installation.cloned()]
+                .into_iter()
+                .flatten()
+                .filter(|hooks| !hooks.is_empty())
+                .collect(),
+            folder: folder.to_path_buf(),
+            env: vec![
+                (
+                    "FACTORIO_UPDATER_INSTALLATION",
+                    folder.display().to_string(),
+                ),
+                ("FACTORIO_UPDATER_ARCH", arch),
+                ("FACTORIO_UPDATER_FROM", from.unwrap_or_default()),
+                ("FACTORIO_UPDATER_TO", to),
+            ],
+        }
+    }
+
+    /// Runs pre-hooks of the stage, the first failing one stops the update
+    pub async fn pre(&self, stage: Stage) -> anyhow::Result<()> {
+        for hooks in &self.hooks {
+            if let Some(command) = hooks.command(stage, true) {
+                self.run(command, stage, true, None)
+                    .await
+                    .with_context(|| format!("Hook pre_{} failed", stage.name()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs post-hooks of the stage with its outcome, failures are only reported
+    pub async fn post<T>(&self, stage: Stage, result: &anyhow::Result<T>) {
+        for hooks in &self.hooks {
+            if let Some(command) = hooks.command(stage, false)
+                && let Err(err) = self
+                    .run(command, stage, false, Some(result.as_ref().map(|_| ())))
+                    .await
+            {
+                eprintln!(
+                    "{}",
+                    style(format!("Hook post_{} failed: {:#}", stage.name(), err)).yellow()
+                );
+            }
+        }
+    }
+
+    /// `result` is the outcome of the stage for post-hooks
+    async fn run(
+        &self,
+        command: &str,
+        stage: Stage,
+        pre: bool,
+        result: Option<Result<(), &anyhow::Error>>,
+    ) -> anyhow::Result<()> {
+        let hook = format!("{}_{}", if pre { "pre" } else { "post" }, stage.name());
+        println!("{} {}: {}", style("[hook]").bold().cyan(), hook, command);
+
+        //installation folder doesn't exist before the first download
+        let folder = if self.folder.is_dir() {
+            self.folder.as_path()
+        } else {
+            Path::new(".")
+        };
+
+        let mut shell = shell(command, folder);
+        shell
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .envs(self.env.iter().map(|(name, value)| (*name, value.as_str())))
+            .env("FACTORIO_UPDATER_HOOK", &hook);
+
+        if let Some(result) = result {
+            shell.env(
+                "FACTORIO_UPDATER_OUTCOME",
+                if result.is_ok() { "success" } else { "failure" },
+            );
+            if let Err(err) = result {
+                shell.env("FACTORIO_UPDATER_ERROR", format!("{:#}", err));
+            }
+        }
+
+        let status = shell
+            .status()
+            .await
+            .with_context(|| format!("Unable to run hook {}", hook))?;
+        if !status.success() {
+            anyhow::bail!("{} exited with {}", command, status);
+        }
+
+        Ok(())
+    }
+}
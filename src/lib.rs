@@ -1,5 +1,12 @@
 pub mod app;
+pub mod changelog;
 pub(crate) mod config;
-//pub(crate) mod functions;
-//pub mod steps;
-//pub mod structs;
+pub(crate) mod functions;
+pub mod headless;
+pub mod hooks;
+pub mod logging;
+pub mod mods;
+pub mod server;
+pub mod steps;
+pub mod structs;
+pub mod webhooks;
@@ -0,0 +1,150 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Keys, whose values never get into the log file
+const SECRET_KEYS: &[&str] = &[
+    "token",
+    //player-data.json
+    "service-token",
+    "password",
+    "passphrase",
+    "proxy_password",
+    "email_authentication_code",
+];
+
+/// File logger, which redacts credentials (e.g. from request URLs logged by reqwest)
+struct FileLogger {
+    file: Mutex<File>,
+}
+
+/// Replaces everything after `prefix` up to the first character, for which `is_end` is true
+fn redact_after(message: &str, prefix: &str, is_end: impl Fn(char) -> bool) -> String {
+    let mut result = String::with_capacity(message.len());
+    let mut rest = message;
+
+    while let Some(position) = rest.find(prefix) {
+        let value_start = position + prefix.len();
+        result.push_str(&rest[..value_start]);
+        result.push_str("<redacted>");
+
+        rest = &rest[value_start..];
+        let value_end = rest.find(&is_end).unwrap_or(rest.len());
+        rest = &rest[value_end..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Replaces values of secret query parameters and JSON fields
+pub fn redact(message: &str) -> String {
+    let mut message = message.to_string();
+
+    for key in SECRET_KEYS {
+        message = redact_after(&message, &format!("{}=", key), |c| {
+            c.is_whitespace() || ['&', '"', '\'', ')'].contains(&c)
+        });
+        //JSON strings can contain whitespace
+        message = redact_after(&message, &format!("\"{}\":\"", key), |c| c == '"');
+        message = redact_after(&message, &format!("\"{}\": \"", key), |c| c == '"');
+    }
+
+    message
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+
+        let line = format!(
+            "[{}] {} [{}] {}\n",
+            time,
+            record.level(),
+            record.target(),
+            redact(&record.args().to_string())
+        );
+
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+pub fn log_to_file(path: &Path, level: LevelFilter) -> anyhow::Result<()> {
+    let file = File::options().create(true).append(true).open(path)?;
+
+    log::set_boxed_logger(Box::new(FileLogger {
+        file: Mutex::new(file),
+    }))?;
+    log::set_max_level(level);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_query_parameters() {
+        assert_eq!(
+            redact(
+                "GET https://updater.factorio.com/get-available-versions?username=player&token=abc123 (200)"
+            ),
+            "GET https://updater.factorio.com/get-available-versions?username=player&token=<redacted> (200)"
+        );
+        assert_eq!(
+            redact("password=hunter2&username=player"),
+            "password=<redacted>&username=player"
+        );
+    }
+
+    #[test]
+    fn redacts_json_fields() {
+        assert_eq!(
+            redact(r#"{"username":"player","token":"abc123"}"#),
+            r#"{"username":"player","token":"<redacted>"}"#
+        );
+        assert_eq!(
+            redact(r#"{"passphrase": "secret words", "keep": "this"}"#),
+            r#"{"passphrase": "<redacted>", "keep": "this"}"#
+        );
+    }
+
+    #[test]
+    fn redacts_player_data_service_token() {
+        let player_data = r#"{"service-username": "player", "service-token": "abc123"}"#;
+
+        assert_eq!(
+            redact(player_data),
+            r#"{"service-username": "player", "service-token": "<redacted>"}"#
+        );
+    }
+
+    #[test]
+    fn keeps_messages_without_secrets() {
+        let message = "Downloading core-linux_headless64 2.0.10 (tokens: 3)";
+
+        assert_eq!(redact(message), message);
+    }
+}
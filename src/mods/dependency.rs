@@ -0,0 +1,236 @@
+use crate::mods::ModVersion;
+
+/// Prefix of dependency in info.json
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyKind {
+    Required,
+    /// `?`, used only when the mod is installed
+    Optional,
+    /// `(?)`, like optional, but not shown in game
+    HiddenOptional,
+    /// `!`, mods can't be enabled together
+    Incompatible,
+    /// `~`, required, but doesn't affect load order
+    LoadNeutral,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionConstraint {
+    pub operator: Operator,
+    pub version: ModVersion,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub kind: DependencyKind,
+    pub name: String,
+    pub constraint: Option<VersionConstraint>,
+}
+
+/// Longer operators first, so `>=` isn't read as `>`
+const OPERATORS: &[(&str, Operator)] = &[
+    ("<=", Operator::LessEqual),
+    (">=", Operator::GreaterEqual),
+    ("<", Operator::Less),
+    (">", Operator::Greater),
+    ("=", Operator::Equal),
+];
+
+impl Operator {
+    pub fn as_str(&self) -> &'static str {
+        OPERATORS
+            .iter()
+            .find(|(_, operator)| operator == self)
+            .map(|(text, _)| *text)
+            .unwrap()
+    }
+}
+
+impl VersionConstraint {
+    pub fn matches(&self, version: &ModVersion) -> bool {
+        match self.operator {
+            Operator::Less => *version < self.version,
+            Operator::LessEqual => *version <= self.version,
+            Operator::Equal => *version == self.version,
+            Operator::GreaterEqual => *version >= self.version,
+            Operator::Greater => *version > self.version,
+        }
+    }
+}
+
+impl std::fmt::Display for VersionConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.operator.as_str(), self.version)
+    }
+}
+
+impl Dependency {
+    /// Parses dependency string, e.g. `? some-mod >= 1.2.0`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+
+        let (kind, rest) = if let Some(rest) = text.strip_prefix("(?)") {
+            (DependencyKind::HiddenOptional, rest)
+        } else if let Some(rest) = text.strip_prefix('?') {
+            (DependencyKind::Optional, rest)
+        } else if let Some(rest) = text.strip_prefix('!') {
+            (DependencyKind::Incompatible, rest)
+        } else if let Some(rest) = text.strip_prefix('~') {
+            (DependencyKind::LoadNeutral, rest)
+        } else {
+            (DependencyKind::Required, text)
+        };
+
+        let rest = rest.trim();
+
+        //mod names can't contain operator characters
+        let (name, constraint) = match rest.find(['<', '>', '=']) {
+            Some(position) => {
+                let (token, operator) = OPERATORS
+                    .iter()
+                    .find(|(token, _)| rest[position..].starts_with(token))?;
+                let version = ModVersion::parse(&rest[position + token.len()..])?;
+
+                (
+                    rest[..position].trim(),
+                    Some(VersionConstraint {
+                        operator: *operator,
+                        version,
+                    }),
+                )
+            }
+            None => (rest, None),
+        };
+
+        if name.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            kind,
+            name: name.to_string(),
+            constraint,
+        })
+    }
+
+    /// Has to be installed, unlike optional and incompatible dependencies
+    pub fn is_required(&self) -> bool {
+        matches!(
+            self.kind,
+            DependencyKind::Required | DependencyKind::LoadNeutral
+        )
+    }
+
+    pub fn matches(&self, version: &ModVersion) -> bool {
+        self.constraint
+            .as_ref()
+            .is_none_or(|constraint| constraint.matches(version))
+    }
+}
+
+impl std::fmt::Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prefix = match self.kind {
+            DependencyKind::Required => "",
+            DependencyKind::Optional => "? ",
+            DependencyKind::HiddenOptional => "(?) ",
+            DependencyKind::Incompatible => "! ",
+            DependencyKind::LoadNeutral => "~ ",
+        };
+
+        match &self.constraint {
+            Some(constraint) => write!(f, "{}{} {}", prefix, self.name, constraint),
+            None => write!(f, "{}{}", prefix, self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Dependency {
+        Dependency::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parses_prefixes() {
+        assert_eq!(parse("base").kind, DependencyKind::Required);
+        assert_eq!(parse("? some-mod").kind, DependencyKind::Optional);
+        assert_eq!(parse("(?) some-mod").kind, DependencyKind::HiddenOptional);
+        assert_eq!(parse("! some-mod").kind, DependencyKind::Incompatible);
+        assert_eq!(parse("~ some-mod").kind, DependencyKind::LoadNeutral);
+        assert_eq!(parse("?some-mod").name, "some-mod");
+    }
+
+    #[test]
+    fn only_required_and_load_neutral_are_required() {
+        assert!(parse("a").is_required());
+        assert!(parse("~ a").is_required());
+        assert!(!parse("? a").is_required());
+        assert!(!parse("(?) a").is_required());
+        assert!(!parse("! a").is_required());
+    }
+
+    #[test]
+    fn parses_version_operators() {
+        for (text, operator) in [
+            ("a < 1.2.3", Operator::Less),
+            ("a <= 1.2.3", Operator::LessEqual),
+            ("a = 1.2.3", Operator::Equal),
+            ("a >= 1.2.3", Operator::GreaterEqual),
+            ("a > 1.2.3", Operator::Greater),
+            ("a>=1.2.3", Operator::GreaterEqual),
+        ] {
+            let dependency = parse(text);
+
+            assert_eq!(dependency.name, "a");
+            assert_eq!(
+                dependency.constraint,
+                Some(VersionConstraint {
+                    operator,
+                    version: ModVersion(1, 2, 3),
+                }),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn constraints_match_versions() {
+        let dependency = parse("? a >= 1.1.0");
+
+        assert!(!dependency.matches(&ModVersion(1, 0, 9)));
+        assert!(dependency.matches(&ModVersion(1, 1, 0)));
+        assert!(dependency.matches(&ModVersion(2, 0, 0)));
+        assert!(parse("a < 2.0").matches(&ModVersion(1, 9, 9)));
+        assert!(!parse("a < 2.0").matches(&ModVersion(2, 0, 0)));
+        assert!(parse("a").matches(&ModVersion(0, 0, 1)));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert!(Dependency::parse("").is_none());
+        assert!(Dependency::parse("?").is_none());
+        assert!(Dependency::parse(">= 1.0.0").is_none());
+        assert!(Dependency::parse("a >= one").is_none());
+        assert!(Dependency::parse("a => 1.0.0").is_none());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for text in ["a", "? a >= 1.2.3", "(?) a < 2.0.0", "! a", "~ a = 0.1.0"] {
+            assert_eq!(parse(text).to_string(), text);
+        }
+    }
+}
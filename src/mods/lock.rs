@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::mods::{BUILTIN_MODS, InstalledMod, ModList};
+
+/// Exact mod set of an installation, which other installations can be synced to
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ModLock {
+    /// Game version of the exported installation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factorio_version: Option<String>,
+    pub mods: Vec<LockedMod>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockedMod {
+    pub name: String,
+    /// Missing for mods shipped with the game
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub enabled: bool,
+}
+
+impl ModLock {
+    /// Builtin mods from mod-list.json and the newest installed version of other mods
+    pub fn new(
+        factorio_version: Option<String>,
+        mod_list: &ModList,
+        installed: &[InstalledMod],
+    ) -> Self {
+        let mut mods = mod_list
+            .mods
+            .iter()
+            .filter(|entry| BUILTIN_MODS.contains(&entry.name.as_str()))
+            .map(|entry| LockedMod {
+                name: entry.name.clone(),
+                version: None,
+                enabled: entry.enabled,
+            })
+            .collect::<Vec<_>>();
+
+        //installed mods are sorted from the oldest version
+        for installed in installed.iter().rev() {
+            let info = &installed.info;
+            if BUILTIN_MODS.contains(&info.name.as_str())
+                || mods.iter().any(|locked| locked.name == info.name)
+            {
+                continue;
+            }
+
+            mods.push(LockedMod {
+                name: info.name.clone(),
+                version: Some(info.version.clone()),
+                enabled: mod_list.is_enabled(&info.name),
+            });
+        }
+
+        mods.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            factorio_version,
+            mods,
+        }
+    }
+
+    pub async fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read lockfile {}", path.display()))?;
+
+        serde_json::from_str(&data).context("Failed to parse lockfile")
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize lockfile")
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LockedMod> {
+        self.mods.iter().find(|locked| locked.name == name)
+    }
+}
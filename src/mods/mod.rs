@@ -0,0 +1,224 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+pub mod dependency;
+pub mod lock;
+pub mod profiles;
+pub mod resolver;
+pub mod settings;
+
+/// List of enabled mods in the mods folder
+pub const MOD_LIST_FILE: &str = "mod-list.json";
+/// Mods shipped with the game, they are never downloaded from the portal
+pub const BUILTIN_MODS: &[&str] = &["base", "space-age", "quality", "elevated-rails"];
+
+/// Version of mod, unlike semver the parts may have leading zeros (e.g. 1.1.01)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ModVersion(pub u32, pub u32, pub u32);
+
+impl ModVersion {
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut parts = version
+            .trim()
+            .split('.')
+            .map(|part| part.parse::<u32>().ok());
+
+        let version = Self(
+            parts.next()??,
+            parts.next()??,
+            parts.next().unwrap_or(Some(0))?,
+        );
+        match parts.next() {
+            Some(_) => None,
+            None => Some(version),
+        }
+    }
+}
+
+impl std::fmt::Display for ModVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ModList {
+    pub mods: Vec<ModListEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModListEntry {
+    pub name: String,
+    pub enabled: bool,
+    /// Fields we don't use (e.g. pinned version), kept when writing the file back
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ModList {
+    pub async fn load(mods_folder: &Path) -> anyhow::Result<Option<Self>> {
+        let path = mods_folder.join(MOD_LIST_FILE);
+
+        if !fs::try_exists(&path)
+            .await
+            .context("Failed to check if mod list exists")?
+        {
+            return Ok(None);
+        }
+
+        let data = fs::read_to_string(&path)
+            .await
+            .context("Failed to read mod list")?;
+
+        Ok(Some(
+            serde_json::from_str(&data).context("Failed to parse mod list")?,
+        ))
+    }
+
+    pub async fn save(&self, mods_folder: &Path) -> anyhow::Result<()> {
+        let data = serde_json::to_string_pretty(self).context("Failed to serialize mod list")?;
+
+        fs::write(mods_folder.join(MOD_LIST_FILE), data)
+            .await
+            .context("Failed to write mod list")
+    }
+
+    /// Mods missing in the list are enabled, like in game
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.mods
+            .iter()
+            .find(|entry| entry.name == name)
+            .is_none_or(|entry| entry.enabled)
+    }
+}
+
+/// info.json of mod
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModInfo {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub author: String,
+    /// Mods without it are for 0.12
+    #[serde(default = "default_factorio_version")]
+    pub factorio_version: String,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+fn default_factorio_version() -> String {
+    "0.12".to_string()
+}
+
+/// Mod zip in the mods folder
+#[derive(Debug, Clone)]
+pub struct InstalledMod {
+    pub info: ModInfo,
+    pub path: PathBuf,
+}
+
+/// Reads info.json from mod zip, it is in the single top level folder
+pub fn read_zip_info(path: &Path) -> anyhow::Result<ModInfo> {
+    let file = File::open(path).context("Unable to open mod")?;
+    let mut archive = zip::ZipArchive::new(file).context("Unable to read mod archive")?;
+
+    let index = (0..archive.len())
+        .find(|i| {
+            archive
+                .by_index(*i)
+                .ok()
+                .and_then(|file| file.enclosed_name())
+                .is_some_and(|name| name.components().count() == 2 && name.ends_with("info.json"))
+        })
+        .context("Mod doesn't contain info.json")?;
+
+    let mut data = String::new();
+    archive
+        .by_index(index)
+        .context("Unable to access info.json")?
+        .read_to_string(&mut data)
+        .context("Unable to read info.json")?;
+
+    serde_json::from_str(&data).context("Unable to parse info.json")
+}
+
+/// Hex encoded SHA-1 of file, like the checksums of mod portal releases
+pub fn file_sha1(path: &Path) -> anyhow::Result<String> {
+    use sha1::{Digest, Sha1};
+
+    let mut file =
+        File::open(path).with_context(|| format!("Unable to open {}", path.display()))?;
+    let mut hasher = Sha1::new();
+    std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Unable to read {}", path.display()))?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Mod zips in the mods folder, sorted by name and version.
+/// Unreadable zips are skipped with a warning, unpacked mods are not included.
+pub async fn installed_mods(mods_folder: &Path) -> anyhow::Result<Vec<InstalledMod>> {
+    let mut paths = Vec::new();
+    let mut entries = fs::read_dir(mods_folder)
+        .await
+        .with_context(|| format!("Unable to read {}", mods_folder.display()))?;
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .context("Unable to read mods folder")?
+    {
+        let path = entry.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "zip") {
+            paths.push(path);
+        }
+    }
+
+    let mut mods = tokio::task::spawn_blocking(move || {
+        paths
+            .into_iter()
+            .filter_map(|path| match read_zip_info(&path) {
+                Ok(info) => Some(InstalledMod { info, path }),
+                Err(err) => {
+                    log::warn!("Skipping {}: {:#}", path.display(), err);
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .context("Reading of mods was interrupted")?;
+
+    mods.sort_by(|a, b| {
+        a.info.name.cmp(&b.info.name).then_with(|| {
+            ModVersion::parse(&a.info.version).cmp(&ModVersion::parse(&b.info.version))
+        })
+    });
+
+    Ok(mods)
+}
+
+/// Major and minor part of game version, which mods target (e.g. 2.0.11 -> 2.0)
+pub fn game_version(version: &str) -> Option<String> {
+    let version = ModVersion::parse(version)?;
+    Some(format!("{}.{}", version.0, version.1))
+}
+
+/// Whether mod made for `factorio_version` can be loaded by game of `game` major.minor
+pub fn supports(factorio_version: &str, game: &str) -> bool {
+    //1.0 loads 0.18 mods too
+    factorio_version == game || (game == "1.0" && factorio_version == "0.18")
+}
@@ -0,0 +1,447 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    config,
+    mods::{ModList, settings::MOD_SETTINGS_FILE},
+};
+
+/// Folder of installation with its mod profiles
+pub const PROFILES_FOLDER: &str = "mod-profiles";
+/// File in profiles folder with name of the active profile
+const ACTIVE_FILE: &str = "active";
+
+/// Saved state of mods folder, zips themselves are in the shared store
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ModProfile {
+    pub mod_list: ModList,
+    /// File names of zips in the store (e.g. foo_1.0.0.zip)
+    pub files: Vec<String>,
+}
+
+/// Mod profiles of one installation
+pub struct ModProfiles {
+    folder: PathBuf,
+    store: PathBuf,
+}
+
+/// Folder shared by profiles of all installations, each mod version is stored once
+pub fn store_folder() -> anyhow::Result<PathBuf> {
+    let data = config::data_dir()
+        .map_err(|err| anyhow::anyhow!("Unable to find data folder: {:?}", err))?;
+
+    Ok(data.join("mods"))
+}
+
+/// Profile names are used as file names
+fn check_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        anyhow::bail!(
+            "Invalid profile name {:?}, use letters, numbers, '-', '_' and '.'",
+            name
+        );
+    }
+
+    Ok(())
+}
+
+/// Zip files and links in the mods folder
+async fn mod_zips(mods_folder: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut zips = Vec::new();
+    let mut entries = fs::read_dir(mods_folder)
+        .await
+        .with_context(|| format!("Unable to read {}", mods_folder.display()))?;
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .context("Unable to read mods folder")?
+    {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "zip") {
+            zips.push(path);
+        }
+    }
+
+    zips.sort();
+    Ok(zips)
+}
+
+#[cfg(unix)]
+async fn link_mod(source: &Path, target: &Path) -> std::io::Result<()> {
+    fs::symlink(source, target).await
+}
+
+/// Symlinks need extra privileges on Windows, so hard links are used, or copies on other drives
+#[cfg(not(unix))]
+async fn link_mod(source: &Path, target: &Path) -> std::io::Result<()> {
+    match fs::hard_link(source, target).await {
+        Ok(()) => Ok(()),
+        Err(_) => fs::copy(source, target).await.map(|_| ()),
+    }
+}
+
+impl ModProfiles {
+    pub fn new(installation: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            folder: installation.join(PROFILES_FOLDER),
+            store: store_folder()?,
+        })
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.folder.join(format!("{}.json", name))
+    }
+
+    /// mod-settings.dat of profile, copied from and into the mods folder
+    fn settings_path(&self, name: &str) -> PathBuf {
+        self.folder.join(format!("{}.{}", name, MOD_SETTINGS_FILE))
+    }
+
+    /// Copies mod-settings.dat of the mods folder into profile, if there is one
+    async fn store_settings(&self, name: &str, mods_folder: &Path) -> anyhow::Result<()> {
+        let settings = mods_folder.join(MOD_SETTINGS_FILE);
+        if fs::try_exists(&settings)
+            .await
+            .context("Failed to check if mod settings exist")?
+        {
+            fs::copy(&settings, self.settings_path(name))
+                .await
+                .with_context(|| format!("Unable to store mod settings of profile {}", name))?;
+        }
+
+        Ok(())
+    }
+
+    /// Names of saved profiles, sorted
+    pub async fn list(&self) -> anyhow::Result<Vec<String>> {
+        if !fs::try_exists(&self.folder)
+            .await
+            .context("Failed to check if profiles folder exists")?
+        {
+            return Ok(Vec::new());
+        }
+
+        let mut names = Vec::new();
+        let mut entries = fs::read_dir(&self.folder)
+            .await
+            .context("Unable to read profiles folder")?;
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .context("Unable to read profiles folder")?
+        {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json")
+                && let Some(name) = path.file_stem()
+            {
+                names.push(name.to_string_lossy().to_string());
+            }
+        }
+
+        names.sort();
+        Ok(names)
+    }
+
+    pub async fn active(&self) -> anyhow::Result<Option<String>> {
+        let path = self.folder.join(ACTIVE_FILE);
+        if !fs::try_exists(&path)
+            .await
+            .context("Failed to check if active profile exists")?
+        {
+            return Ok(None);
+        }
+
+        let name = fs::read_to_string(&path)
+            .await
+            .context("Failed to read active profile")?;
+
+        Ok(Some(name.trim().to_string()).filter(|name| !name.is_empty()))
+    }
+
+    async fn set_active(&self, name: Option<&str>) -> anyhow::Result<()> {
+        fs::write(self.folder.join(ACTIVE_FILE), name.unwrap_or_default())
+            .await
+            .context("Failed to write active profile")
+    }
+
+    pub async fn load(&self, name: &str) -> anyhow::Result<ModProfile> {
+        check_name(name)?;
+
+        let data = fs::read_to_string(self.path(name))
+            .await
+            .with_context(|| format!("Profile {} doesn't exist", name))?;
+
+        serde_json::from_str(&data).with_context(|| format!("Failed to parse profile {}", name))
+    }
+
+    /// Copies zips, which aren't in the store yet, and returns their file names
+    async fn store_mods(&self, mods_folder: &Path) -> anyhow::Result<Vec<String>> {
+        fs::create_dir_all(&self.store)
+            .await
+            .context("Failed to create mod store")?;
+
+        let mut files = Vec::new();
+
+        for path in mod_zips(mods_folder).await? {
+            let Some(file_name) = path.file_name() else {
+                continue;
+            };
+            let stored = self.store.join(file_name);
+
+            //zip name contains mod version, so same name means same mod
+            if !fs::try_exists(&stored)
+                .await
+                .context("Failed to check if mod is stored")?
+            {
+                fs::copy(&path, &stored)
+                    .await
+                    .with_context(|| format!("Unable to store {}", path.display()))?;
+            }
+
+            files.push(file_name.to_string_lossy().to_string());
+        }
+
+        Ok(files)
+    }
+
+    /// Saves current state of the mods folder as profile, which becomes the active one
+    pub async fn save(&self, name: &str, mods_folder: &Path) -> anyhow::Result<ModProfile> {
+        check_name(name)?;
+
+        fs::create_dir_all(mods_folder)
+            .await
+            .context("Failed to create mods folder")?;
+        fs::create_dir_all(&self.folder)
+            .await
+            .context("Failed to create profiles folder")?;
+
+        let profile = ModProfile {
+            mod_list: ModList::load(mods_folder).await?.unwrap_or_default(),
+            files: self.store_mods(mods_folder).await?,
+        };
+
+        let data = serde_json::to_string_pretty(&profile).context("Failed to serialize profile")?;
+        fs::write(self.path(name), data)
+            .await
+            .with_context(|| format!("Failed to write profile {}", name))?;
+
+        self.store_settings(name, mods_folder).await?;
+        self.set_active(Some(name)).await?;
+
+        Ok(profile)
+    }
+
+    /// Replaces zips in the mods folder with links to the profile's zips and rewrites mod-list.json.
+    /// Zips, which aren't in the store, are stored first, so nothing is lost.
+    /// Mod settings changed since the last switch are kept in the previous profile,
+    /// and replaced with the ones of the activated profile, when it has any.
+    pub async fn activate(&self, name: &str, mods_folder: &Path) -> anyhow::Result<ModProfile> {
+        let profile = self.load(name).await?;
+
+        let missing = profile
+            .files
+            .iter()
+            .filter(|file| !self.store.join(file).is_file())
+            .cloned()
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "Profile {} needs mods missing in {}: {}",
+                name,
+                self.store.display(),
+                missing.join(", ")
+            );
+        }
+
+        fs::create_dir_all(mods_folder)
+            .await
+            .context("Failed to create mods folder")?;
+        self.store_mods(mods_folder).await?;
+
+        if let Some(active) = self.active().await?
+            && fs::try_exists(self.path(&active))
+                .await
+                .context("Failed to check if profile exists")?
+        {
+            self.store_settings(&active, mods_folder).await?;
+        }
+
+        for path in mod_zips(mods_folder).await? {
+            fs::remove_file(&path)
+                .await
+                .with_context(|| format!("Unable to remove {}", path.display()))?;
+        }
+
+        for file in &profile.files {
+            link_mod(&self.store.join(file), &mods_folder.join(file))
+                .await
+                .with_context(|| format!("Unable to link {}", file))?;
+        }
+
+        profile.mod_list.save(mods_folder).await?;
+
+        let settings = self.settings_path(name);
+        if fs::try_exists(&settings)
+            .await
+            .context("Failed to check if mod settings exist")?
+        {
+            fs::copy(&settings, mods_folder.join(MOD_SETTINGS_FILE))
+                .await
+                .context("Unable to restore mod settings")?;
+        }
+
+        self.set_active(Some(name)).await?;
+
+        Ok(profile)
+    }
+
+    /// Removes profile, its zips stay in the store, because other profiles may use them
+    pub async fn remove(&self, name: &str) -> anyhow::Result<()> {
+        check_name(name)?;
+
+        fs::remove_file(self.path(name))
+            .await
+            .with_context(|| format!("Profile {} doesn't exist", name))?;
+
+        let settings = self.settings_path(name);
+        if fs::try_exists(&settings)
+            .await
+            .context("Failed to check if mod settings exist")?
+        {
+            fs::remove_file(&settings)
+                .await
+                .context("Unable to remove mod settings of profile")?;
+        }
+
+        if self.active().await?.as_deref() == Some(name) {
+            self.set_active(None).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::mods::MOD_LIST_FILE;
+
+    fn write_mods(mods_folder: &Path, zips: &[&str], settings: &str) {
+        std::fs::create_dir_all(mods_folder).unwrap();
+        for zip in zips {
+            std::fs::write(mods_folder.join(zip), zip).unwrap();
+        }
+
+        let mods = zips
+            .iter()
+            .map(|zip| format!(r#"{{"name": "{}", "enabled": true}}"#, &zip[..1]))
+            .collect::<Vec<_>>();
+        std::fs::write(
+            mods_folder.join(MOD_LIST_FILE),
+            format!(r#"{{"mods": [{}]}}"#, mods.join(", ")),
+        )
+        .unwrap();
+        std::fs::write(mods_folder.join(MOD_SETTINGS_FILE), settings).unwrap();
+    }
+
+    fn enabled_mods(mods_folder: &Path) -> Vec<String> {
+        let list: ModList = serde_json::from_str(
+            &std::fs::read_to_string(mods_folder.join(MOD_LIST_FILE)).unwrap(),
+        )
+        .unwrap();
+        list.mods.into_iter().map(|entry| entry.name).collect()
+    }
+
+    fn zips(mods_folder: &Path) -> Vec<String> {
+        let mut zips = std::fs::read_dir(mods_folder)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".zip"))
+            .collect::<Vec<_>>();
+        zips.sort();
+        zips
+    }
+
+    fn settings(mods_folder: &Path) -> String {
+        std::fs::read_to_string(mods_folder.join(MOD_SETTINGS_FILE)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn switches_between_saved_profiles() {
+        let temp = TempDir::new("mod-profiles").unwrap();
+        let mods_folder = temp.path().join("factorio").join("mods");
+        let profiles = ModProfiles {
+            folder: temp.path().join("factorio").join(PROFILES_FOLDER),
+            store: temp.path().join("store"),
+        };
+
+        write_mods(&mods_folder, &["a_1.0.0.zip", "b_1.0.0.zip"], "one");
+        profiles.save("one", &mods_folder).await.unwrap();
+
+        std::fs::remove_file(mods_folder.join("b_1.0.0.zip")).unwrap();
+        write_mods(&mods_folder, &["a_1.0.0.zip", "c_2.0.0.zip"], "two");
+        profiles.save("two", &mods_folder).await.unwrap();
+        assert_eq!(profiles.list().await.unwrap(), ["one", "two"]);
+        assert_eq!(profiles.active().await.unwrap().as_deref(), Some("two"));
+
+        //changed in game after the last switch, kept in profile two
+        std::fs::write(mods_folder.join(MOD_SETTINGS_FILE), "two changed").unwrap();
+
+        profiles.activate("one", &mods_folder).await.unwrap();
+        assert_eq!(zips(&mods_folder), ["a_1.0.0.zip", "b_1.0.0.zip"]);
+        assert_eq!(enabled_mods(&mods_folder), ["a", "b"]);
+        assert_eq!(settings(&mods_folder), "one");
+        assert_eq!(profiles.active().await.unwrap().as_deref(), Some("one"));
+        #[cfg(unix)]
+        assert_eq!(
+            std::fs::read_link(mods_folder.join("b_1.0.0.zip")).unwrap(),
+            profiles.store.join("b_1.0.0.zip")
+        );
+
+        profiles.activate("two", &mods_folder).await.unwrap();
+        assert_eq!(zips(&mods_folder), ["a_1.0.0.zip", "c_2.0.0.zip"]);
+        assert_eq!(enabled_mods(&mods_folder), ["a", "c"]);
+        assert_eq!(settings(&mods_folder), "two changed");
+        assert_eq!(
+            std::fs::read_to_string(mods_folder.join("c_2.0.0.zip")).unwrap(),
+            "c_2.0.0.zip"
+        );
+    }
+
+    #[tokio::test]
+    async fn removed_profile_keeps_stored_mods() {
+        let temp = TempDir::new("mod-profiles").unwrap();
+        let mods_folder = temp.path().join("factorio").join("mods");
+        let profiles = ModProfiles {
+            folder: temp.path().join("factorio").join(PROFILES_FOLDER),
+            store: temp.path().join("store"),
+        };
+
+        write_mods(&mods_folder, &["a_1.0.0.zip"], "one");
+        profiles.save("one", &mods_folder).await.unwrap();
+        write_mods(&mods_folder, &["a_1.0.0.zip", "b_1.0.0.zip"], "two");
+        profiles.save("two", &mods_folder).await.unwrap();
+
+        profiles.remove("two").await.unwrap();
+        assert_eq!(profiles.list().await.unwrap(), ["one"]);
+        assert_eq!(profiles.active().await.unwrap(), None);
+        assert!(!profiles.settings_path("two").exists());
+        assert!(profiles.store.join("b_1.0.0.zip").is_file());
+
+        profiles.remove("one").await.unwrap();
+        assert!(profiles.remove("one").await.is_err());
+        assert!(profiles.remove("../one").await.is_err());
+    }
+}
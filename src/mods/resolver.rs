@@ -0,0 +1,677 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+
+use crate::{
+    app::api::{Api, ApiError, portal::Release},
+    mods::{
+        BUILTIN_MODS, InstalledMod, ModList, ModVersion,
+        dependency::{Dependency, DependencyKind},
+        supports,
+    },
+};
+
+/// Plans, which didn't settle after this many rounds, are reported as conflicts
+const MAX_ROUNDS: usize = 50;
+
+#[derive(Debug, Clone)]
+pub enum Source {
+    /// Shipped with the game
+    Builtin,
+    Installed(PathBuf),
+    Portal(Release),
+}
+
+/// Version of mod, which can be chosen
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub version: ModVersion,
+    pub dependencies: Vec<Dependency>,
+    pub source: Source,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Keep,
+    /// Installed, but disabled in mod-list.json
+    Enable,
+    Install,
+    Update {
+        from: ModVersion,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedMod {
+    pub name: String,
+    pub version: ModVersion,
+    pub action: Action,
+    pub source: Source,
+    /// Requested, or mods which depend on it
+    pub required_by: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Plan {
+    pub mods: Vec<PlannedMod>,
+    /// Explanations of requirements, which couldn't be satisfied
+    pub conflicts: Vec<String>,
+    /// Mods considered by the plan, which aren't on the mod portal
+    pub unavailable: Vec<String>,
+}
+
+impl Plan {
+    pub fn changes(&self) -> impl Iterator<Item = &PlannedMod> {
+        self.mods
+            .iter()
+            .filter(|planned| planned.action != Action::Keep)
+    }
+}
+
+/// Requirement of mod together with its origin
+struct Requirement {
+    dependency: Dependency,
+    by: String,
+}
+
+/// Picks versions of requested mods and their dependencies, which can be enabled together
+pub struct Resolver<'a> {
+    api: &'a Api,
+    /// major.minor, releases for other versions are ignored
+    game: String,
+    /// Version of the built-in mods
+    game_version: ModVersion,
+    installed: BTreeMap<String, Vec<InstalledMod>>,
+    enabled: BTreeSet<String>,
+    candidates: HashMap<String, Vec<Candidate>>,
+    unavailable: BTreeSet<String>,
+}
+
+fn parse_dependencies(dependencies: &[String]) -> Vec<Dependency> {
+    dependencies
+        .iter()
+        .filter_map(|text| {
+            let dependency = Dependency::parse(text);
+            if dependency.is_none() {
+                log::warn!("Ignoring malformed dependency: {}", text);
+            }
+            dependency
+        })
+        .collect()
+}
+
+impl<'a> Resolver<'a> {
+    /// `game_version` is the full version of the installation (e.g. 2.0.11)
+    pub fn new(
+        api: &'a Api,
+        game_version: &str,
+        installed: Vec<InstalledMod>,
+        mod_list: &ModList,
+    ) -> anyhow::Result<Self> {
+        let game_version = ModVersion::parse(game_version)
+            .with_context(|| format!("Invalid game version {}", game_version))?;
+
+        let mut grouped: BTreeMap<String, Vec<InstalledMod>> = BTreeMap::new();
+        for installed in installed {
+            grouped
+                .entry(installed.info.name.clone())
+                .or_default()
+                .push(installed);
+        }
+
+        let enabled = grouped
+            .keys()
+            .filter(|name| mod_list.is_enabled(name))
+            .cloned()
+            .collect();
+
+        Ok(Self {
+            api,
+            game: format!("{}.{}", game_version.0, game_version.1),
+            game_version,
+            installed: grouped,
+            enabled,
+            candidates: HashMap::new(),
+            unavailable: BTreeSet::new(),
+        })
+    }
+
+    /// Versions of mod, newest first, installed ones before portal releases of the same version
+    async fn candidates(&mut self, name: &str) -> anyhow::Result<Vec<Candidate>> {
+        if let Some(candidates) = self.candidates.get(name) {
+            return Ok(candidates.clone());
+        }
+
+        if BUILTIN_MODS.contains(&name) {
+            let candidates = vec![Candidate {
+                version: self.game_version,
+                dependencies: Vec::new(),
+                source: Source::Builtin,
+            }];
+            self.candidates.insert(name.to_string(), candidates.clone());
+            return Ok(candidates);
+        }
+
+        let mut candidates = self
+            .installed
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter(|installed| supports(&installed.info.factorio_version, &self.game))
+            .filter_map(|installed| {
+                Some(Candidate {
+                    version: ModVersion::parse(&installed.info.version)?,
+                    dependencies: parse_dependencies(&installed.info.dependencies),
+                    source: Source::Installed(installed.path.clone()),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if !self.api.is_offline() {
+            match self.api.get_mod(name).await {
+                Ok(portal) => {
+                    for release in portal.releases {
+                        let Some(version) = ModVersion::parse(&release.version) else {
+                            continue;
+                        };
+
+                        if !supports(&release.info_json.factorio_version, &self.game)
+                            || candidates.iter().any(|known| known.version == version)
+                        {
+                            continue;
+                        }
+
+                        candidates.push(Candidate {
+                            version,
+                            dependencies: parse_dependencies(&release.info_json.dependencies),
+                            source: Source::Portal(release),
+                        });
+                    }
+                }
+                Err(ApiError::NotFound(_)) => {
+                    self.unavailable.insert(name.to_string());
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("Failed to get {} from mod portal", name));
+                }
+            }
+        }
+
+        //stable sort keeps installed versions first
+        candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.version));
+
+        self.candidates.insert(name.to_string(), candidates.clone());
+        Ok(candidates)
+    }
+
+    /// Plan, in which requested mods are at the newest allowed version, other enabled mods
+    /// keep their installed version unless a dependency needs another one
+    pub async fn resolve(&mut self, requests: &[Dependency]) -> anyhow::Result<Plan> {
+        let requested = requests
+            .iter()
+            .map(|request| request.name.clone())
+            .collect::<BTreeSet<_>>();
+
+        let mut selected: BTreeMap<String, Candidate> = BTreeMap::new();
+        let mut conflicts = Vec::new();
+        let mut requirements = BTreeMap::new();
+        let mut settled = false;
+
+        for _ in 0..MAX_ROUNDS {
+            requirements = self.requirements(requests, &selected);
+            conflicts.clear();
+
+            let mut next = BTreeMap::new();
+
+            for (name, required) in &requirements {
+                let candidates = self.candidates(name).await?;
+
+                //requested mods go to the newest version, others prefer what is installed
+                let mut ordered = candidates.iter().collect::<Vec<_>>();
+                if !requested.contains(name) {
+                    ordered
+                        .sort_by_key(|candidate| !matches!(candidate.source, Source::Installed(_)));
+                }
+
+                let optional = Self::optional_constraints(name, &selected);
+
+                let pick = ordered.into_iter().find(|candidate| {
+                    required
+                        .iter()
+                        .chain(optional.iter())
+                        .all(|requirement| requirement.dependency.matches(&candidate.version))
+                        && Self::incompatibility(name, candidate, &requirements, &selected)
+                            .is_none()
+                });
+
+                match pick {
+                    Some(candidate) => {
+                        next.insert(name.clone(), candidate.clone());
+                    }
+                    None => conflicts.push(self.explain(
+                        name,
+                        &candidates,
+                        required.iter().chain(optional.iter()),
+                        &requirements,
+                        &selected,
+                    )),
+                }
+            }
+
+            let unchanged = next.len() == selected.len()
+                && next
+                    .iter()
+                    .zip(selected.iter())
+                    .all(|((a, a_candidate), (b, b_candidate))| {
+                        a == b && a_candidate.version == b_candidate.version
+                    });
+
+            selected = next;
+
+            if unchanged {
+                settled = true;
+                break;
+            }
+        }
+
+        if !settled {
+            conflicts.push(
+                "Requirements keep changing the chosen versions, try requesting exact versions"
+                    .to_string(),
+            );
+        }
+
+        let mods = selected
+            .into_iter()
+            .filter(|(_, candidate)| !matches!(candidate.source, Source::Builtin))
+            .map(|(name, candidate)| {
+                let action = match &candidate.source {
+                    Source::Installed(_) if self.enabled.contains(&name) => Action::Keep,
+                    Source::Installed(_) => Action::Enable,
+                    _ => match self.installed_version(&name) {
+                        Some(from) => Action::Update { from },
+                        None => Action::Install,
+                    },
+                };
+
+                PlannedMod {
+                    required_by: requirements
+                        .get(&name)
+                        .map(|required| required.iter().map(|req| req.by.clone()).collect())
+                        .unwrap_or_default(),
+                    name,
+                    version: candidate.version,
+                    action,
+                    source: candidate.source,
+                }
+            })
+            .collect();
+
+        Ok(Plan {
+            mods,
+            conflicts,
+            unavailable: self.unavailable.iter().cloned().collect(),
+        })
+    }
+
+    fn installed_version(&self, name: &str) -> Option<ModVersion> {
+        self.installed
+            .get(name)?
+            .iter()
+            .filter_map(|installed| ModVersion::parse(&installed.info.version))
+            .max()
+    }
+
+    /// Mods, which have to be in the plan, with constraints on them
+    fn requirements(
+        &self,
+        requests: &[Dependency],
+        selected: &BTreeMap<String, Candidate>,
+    ) -> BTreeMap<String, Vec<Requirement>> {
+        let mut requirements: BTreeMap<String, Vec<Requirement>> = BTreeMap::new();
+
+        //enabled mods stay enabled
+        for name in &self.enabled {
+            requirements.entry(name.clone()).or_default();
+        }
+
+        for request in requests {
+            requirements
+                .entry(request.name.clone())
+                .or_default()
+                .push(Requirement {
+                    dependency: request.clone(),
+                    by: "requested".to_string(),
+                });
+        }
+
+        for (name, candidate) in selected {
+            for dependency in candidate
+                .dependencies
+                .iter()
+                .filter(|dependency| dependency.is_required())
+            {
+                requirements
+                    .entry(dependency.name.clone())
+                    .or_default()
+                    .push(Requirement {
+                        dependency: dependency.clone(),
+                        by: format!("{} {}", name, candidate.version),
+                    });
+            }
+        }
+
+        requirements
+    }
+
+    /// Optional dependencies constrain the version of mods, which are in the plan anyway
+    fn optional_constraints(
+        name: &str,
+        selected: &BTreeMap<String, Candidate>,
+    ) -> Vec<Requirement> {
+        selected
+            .iter()
+            .flat_map(|(by, candidate)| {
+                candidate
+                    .dependencies
+                    .iter()
+                    .filter(move |dependency| {
+                        dependency.name == name
+                            && matches!(
+                                dependency.kind,
+                                DependencyKind::Optional | DependencyKind::HiddenOptional
+                            )
+                    })
+                    .map(move |dependency| Requirement {
+                        dependency: dependency.clone(),
+                        by: format!("{} {}", by, candidate.version),
+                    })
+            })
+            .collect()
+    }
+
+    /// Mod, which can't be enabled together with candidate. Candidate's own incompatibilities
+    /// are checked against all mods, which have to be in the plan.
+    fn incompatibility(
+        name: &str,
+        candidate: &Candidate,
+        requirements: &BTreeMap<String, Vec<Requirement>>,
+        selected: &BTreeMap<String, Candidate>,
+    ) -> Option<String> {
+        let own = candidate.dependencies.iter().find(|dependency| {
+            dependency.kind == DependencyKind::Incompatible
+                && dependency.name != name
+                && requirements.contains_key(&dependency.name)
+        });
+
+        if let Some(dependency) = own {
+            return Some(format!(
+                "{} {} is incompatible with {}",
+                name, candidate.version, dependency.name
+            ));
+        }
+
+        selected.iter().find_map(|(other, other_candidate)| {
+            other_candidate
+                .dependencies
+                .iter()
+                .any(|dependency| {
+                    dependency.kind == DependencyKind::Incompatible
+                        && dependency.name == name
+                        && other != name
+                })
+                .then(|| {
+                    format!(
+                        "{} {} is incompatible with {}",
+                        other, other_candidate.version, name
+                    )
+                })
+        })
+    }
+
+    fn explain<'r>(
+        &self,
+        name: &str,
+        candidates: &[Candidate],
+        constraints: impl Iterator<Item = &'r Requirement>,
+        requirements: &BTreeMap<String, Vec<Requirement>>,
+        selected: &BTreeMap<String, Candidate>,
+    ) -> String {
+        let constraints = constraints.collect::<Vec<_>>();
+        let wanted_by = constraints
+            .iter()
+            .map(|requirement| requirement.by.as_str())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>()
+            .join(", ");
+        let wanted_by = if wanted_by.is_empty() {
+            "enabled".to_string()
+        } else {
+            wanted_by
+        };
+
+        if candidates.is_empty() {
+            return if self.unavailable.contains(name) {
+                format!("{} isn't on the mod portal (needed by {})", name, wanted_by)
+            } else {
+                format!(
+                    "{} has no release for Factorio {} (needed by {})",
+                    name, self.game, wanted_by
+                )
+            };
+        }
+
+        let matching = candidates
+            .iter()
+            .filter(|candidate| {
+                constraints
+                    .iter()
+                    .all(|requirement| requirement.dependency.matches(&candidate.version))
+            })
+            .collect::<Vec<_>>();
+
+        match matching.first() {
+            None => format!(
+                "No version of {} satisfies {}, available: {}",
+                name,
+                constraints
+                    .iter()
+                    .filter(|requirement| requirement.dependency.constraint.is_some())
+                    .map(|requirement| format!(
+                        "{} {} ({})",
+                        name,
+                        requirement.dependency.constraint.as_ref().unwrap(),
+                        requirement.by
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                candidates
+                    .iter()
+                    .map(|candidate| candidate.version.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Some(candidate) => format!(
+                "{} (needed by {})",
+                Self::incompatibility(name, candidate, requirements, selected)
+                    .unwrap_or_else(|| format!("{} can't be added", name)),
+                wanted_by
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::{config::Account, server::fake::FakeServer};
+
+    /// Release of mod on the fake portal
+    fn release(fixtures: &Path, name: &str, version: &str, dependencies: &[&str]) {
+        let folder = fixtures.join("mods").join(name).join(version);
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(
+            folder.join("info.json"),
+            json!({
+                "name": name,
+                "version": version,
+                "factorio_version": "2.0",
+                "dependencies": dependencies,
+            })
+            .to_string(),
+        )
+        .unwrap();
+    }
+
+    fn installed(name: &str, version: &str, dependencies: &[&str]) -> InstalledMod {
+        InstalledMod {
+            info: crate::mods::ModInfo {
+                name: name.to_string(),
+                version: version.to_string(),
+                title: String::new(),
+                author: String::new(),
+                factorio_version: "2.0".to_string(),
+                dependencies: dependencies.iter().map(|text| text.to_string()).collect(),
+            },
+            path: PathBuf::from(format!("{}_{}.zip", name, version)),
+        }
+    }
+
+    async fn resolve(fixtures: &TempDir, installed: Vec<InstalledMod>, requests: &[&str]) -> Plan {
+        let server = FakeServer::start_local(fixtures.path()).await;
+        let api = server.api(Account::new(String::new(), String::new())).await;
+        let requests = requests
+            .iter()
+            .map(|text| Dependency::parse(text).unwrap())
+            .collect::<Vec<_>>();
+
+        Resolver::new(&api, "2.0.10", installed, &ModList::default())
+            .unwrap()
+            .resolve(&requests)
+            .await
+            .unwrap()
+    }
+
+    fn planned<'p>(plan: &'p Plan, name: &str) -> Option<&'p PlannedMod> {
+        plan.mods.iter().find(|planned| planned.name == name)
+    }
+
+    #[tokio::test]
+    async fn installs_newest_matching_dependencies() {
+        let fixtures = TempDir::new("fixtures").unwrap();
+        release(fixtures.path(), "a", "1.0.0", &["base", "b < 1.2.0", "~ c"]);
+        release(fixtures.path(), "b", "1.0.0", &[]);
+        release(fixtures.path(), "b", "1.1.0", &[]);
+        release(fixtures.path(), "b", "1.2.0", &[]);
+        release(fixtures.path(), "c", "0.1.0", &[]);
+
+        let plan = resolve(&fixtures, Vec::new(), &["a"]).await;
+
+        assert!(plan.conflicts.is_empty(), "{:?}", plan.conflicts);
+        assert_eq!(planned(&plan, "a").unwrap().action, Action::Install);
+        assert_eq!(planned(&plan, "b").unwrap().version, ModVersion(1, 1, 0));
+        assert_eq!(planned(&plan, "b").unwrap().required_by, ["a 1.0.0"]);
+        assert!(planned(&plan, "c").is_some());
+        //built-in mods are never part of the plan
+        assert!(planned(&plan, "base").is_none());
+    }
+
+    #[tokio::test]
+    async fn optional_dependencies_only_constrain_mods_in_plan() {
+        let fixtures = TempDir::new("fixtures").unwrap();
+        release(fixtures.path(), "a", "1.0.0", &["? b >= 2.0.0", "(?) c"]);
+        release(fixtures.path(), "b", "1.0.0", &[]);
+        release(fixtures.path(), "b", "2.0.0", &[]);
+        release(fixtures.path(), "c", "1.0.0", &[]);
+
+        let plan = resolve(&fixtures, Vec::new(), &["a"]).await;
+        assert!(plan.conflicts.is_empty(), "{:?}", plan.conflicts);
+        assert!(planned(&plan, "b").is_none());
+        assert!(planned(&plan, "c").is_none());
+
+        //installed b has to be updated to satisfy the optional constraint
+        let plan = resolve(&fixtures, vec![installed("b", "1.0.0", &[])], &["a"]).await;
+        assert!(plan.conflicts.is_empty(), "{:?}", plan.conflicts);
+        assert_eq!(
+            planned(&plan, "b").unwrap().action,
+            Action::Update {
+                from: ModVersion(1, 0, 0)
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn enabled_mods_keep_installed_version() {
+        let fixtures = TempDir::new("fixtures").unwrap();
+        release(fixtures.path(), "a", "1.0.0", &[]);
+        release(fixtures.path(), "b", "1.0.0", &[]);
+        release(fixtures.path(), "b", "1.1.0", &[]);
+
+        let plan = resolve(&fixtures, vec![installed("b", "1.0.0", &[])], &["a"]).await;
+
+        assert_eq!(planned(&plan, "b").unwrap().action, Action::Keep);
+        assert_eq!(plan.changes().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn incompatible_mods_are_conflicts() {
+        let fixtures = TempDir::new("fixtures").unwrap();
+        release(fixtures.path(), "a", "1.0.0", &["! b"]);
+        release(fixtures.path(), "b", "1.0.0", &[]);
+
+        let plan = resolve(&fixtures, vec![installed("b", "1.0.0", &[])], &["a"]).await;
+
+        assert_eq!(plan.conflicts.len(), 1);
+        assert!(
+            plan.conflicts[0].contains("a 1.0.0 is incompatible with b"),
+            "{}",
+            plan.conflicts[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn unsatisfiable_constraints_are_explained() {
+        let fixtures = TempDir::new("fixtures").unwrap();
+        release(fixtures.path(), "a", "1.0.0", &["b >= 2.0.0", "missing"]);
+        release(fixtures.path(), "b", "1.0.0", &[]);
+
+        let plan = resolve(&fixtures, Vec::new(), &["a"]).await;
+
+        assert_eq!(plan.unavailable, ["missing"]);
+        assert!(
+            plan.conflicts
+                .iter()
+                .any(|conflict| conflict.contains("No version of b satisfies b >= 2.0.0 (a 1.0.0)")),
+            "{:?}",
+            plan.conflicts
+        );
+        assert!(
+            plan.conflicts
+                .iter()
+                .any(|conflict| conflict.contains("missing isn't on the mod portal")),
+            "{:?}",
+            plan.conflicts
+        );
+    }
+
+    #[tokio::test]
+    async fn requested_version_is_respected() {
+        let fixtures = TempDir::new("fixtures").unwrap();
+        release(fixtures.path(), "a", "1.0.0", &[]);
+        release(fixtures.path(), "a", "1.1.0", &[]);
+
+        let plan = resolve(&fixtures, Vec::new(), &["a = 1.0.0"]).await;
+
+        assert_eq!(planned(&plan, "a").unwrap().version, ModVersion(1, 0, 0));
+    }
+}
@@ -0,0 +1,618 @@
+use std::{
+    collections::BTreeMap,
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use anyhow::Context;
+use serde_json::{Map, Value};
+use tokio::fs;
+
+/// Binary file with mod settings in the mods folder
+pub const MOD_SETTINGS_FILE: &str = "mod-settings.dat";
+/// Scopes of settings, each is a dictionary in the root of mod-settings.dat
+pub const SCOPES: &[&str] = &["startup", "runtime-global", "runtime-per-user"];
+/// Nesting of lists and dictionaries, settings of the game don't come close to it
+const MAX_DEPTH: usize = 64;
+/// Smallest list or dictionary item: empty key and tag with any-type flag
+const MIN_ITEM_SIZE: u64 = 3;
+
+type Reader<'a> = Cursor<&'a [u8]>;
+
+/// Factorio's property tree, the types are stored as one byte tags
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyTree {
+    None,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<PropertyTree>),
+    /// Keys in file order, so the file is written back unchanged
+    Dictionary(Vec<(String, PropertyTree)>),
+    /// Added in 2.0
+    Signed(i64),
+    /// Added in 2.0
+    Unsigned(u64),
+}
+
+/// mod-settings.dat: game version, which wrote it, and the settings tree
+#[derive(Debug, Clone)]
+pub struct ModSettings {
+    pub version: [u16; 4],
+    pub tree: PropertyTree,
+}
+
+/// Bytes left in the file, lengths read from it are checked against them before allocating
+fn remaining(reader: &Reader) -> u64 {
+    (reader.get_ref().len() as u64).saturating_sub(reader.position())
+}
+
+fn read_bytes<const N: usize>(reader: &mut Reader) -> anyhow::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader
+        .read_exact(&mut bytes)
+        .context("Unexpected end of mod settings")?;
+    Ok(bytes)
+}
+
+fn read_u8(reader: &mut Reader) -> anyhow::Result<u8> {
+    Ok(read_bytes::<1>(reader)?[0])
+}
+
+fn read_u32(reader: &mut Reader) -> anyhow::Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(reader)?))
+}
+
+/// Item count of list or dictionary, which has to fit in the rest of the file
+fn read_count(reader: &mut Reader) -> anyhow::Result<u32> {
+    let count = read_u32(reader)?;
+    if count as u64 * MIN_ITEM_SIZE > remaining(reader) {
+        anyhow::bail!("Unexpected end of mod settings");
+    }
+
+    Ok(count)
+}
+
+/// Strings start with empty flag, lengths under 255 take one byte
+fn read_string(reader: &mut Reader) -> anyhow::Result<String> {
+    if read_u8(reader)? != 0 {
+        return Ok(String::new());
+    }
+
+    let length = match read_u8(reader)? {
+        255 => read_u32(reader)?,
+        length => length as u32,
+    };
+    if length as u64 > remaining(reader) {
+        anyhow::bail!("Unexpected end of mod settings");
+    }
+
+    let mut data = vec![0; length as usize];
+    reader
+        .read_exact(&mut data)
+        .context("Unexpected end of mod settings")?;
+
+    String::from_utf8(data).context("Invalid string in mod settings")
+}
+
+fn write_string(out: &mut Vec<u8>, text: &str) {
+    if text.is_empty() {
+        out.push(1);
+        return;
+    }
+
+    out.push(0);
+    if text.len() < 255 {
+        out.push(text.len() as u8);
+    } else {
+        out.push(255);
+        out.extend((text.len() as u32).to_le_bytes());
+    }
+    out.extend(text.as_bytes());
+}
+
+impl PropertyTree {
+    pub fn read(reader: &mut Reader) -> anyhow::Result<Self> {
+        Self::read_nested(reader, 0)
+    }
+
+    fn read_nested(reader: &mut Reader, depth: usize) -> anyhow::Result<Self> {
+        if depth > MAX_DEPTH {
+            anyhow::bail!("Mod settings are nested too deep");
+        }
+
+        let tag = read_u8(reader)?;
+        //any-type flag, only used by the game internally
+        read_u8(reader)?;
+
+        Ok(match tag {
+            0 => Self::None,
+            1 => Self::Bool(read_u8(reader)? != 0),
+            2 => Self::Number(f64::from_le_bytes(read_bytes(reader)?)),
+            3 => Self::String(read_string(reader)?),
+            4 => {
+                let count = read_count(reader)?;
+                let mut items = Vec::new();
+                for _ in 0..count {
+                    //list items have keys too, they are always empty
+                    read_string(reader)?;
+                    items.push(Self::read_nested(reader, depth + 1)?);
+                }
+                Self::List(items)
+            }
+            5 => {
+                let count = read_count(reader)?;
+                let mut items = Vec::new();
+                for _ in 0..count {
+                    let key = read_string(reader)?;
+                    items.push((key, Self::read_nested(reader, depth + 1)?));
+                }
+                Self::Dictionary(items)
+            }
+            6 => Self::Signed(i64::from_le_bytes(read_bytes(reader)?)),
+            7 => Self::Unsigned(u64::from_le_bytes(read_bytes(reader)?)),
+            tag => anyhow::bail!("Unknown property tree type {}", tag),
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        let tag = match self {
+            Self::None => 0,
+            Self::Bool(_) => 1,
+            Self::Number(_) => 2,
+            Self::String(_) => 3,
+            Self::List(_) => 4,
+            Self::Dictionary(_) => 5,
+            Self::Signed(_) => 6,
+            Self::Unsigned(_) => 7,
+        };
+        out.extend([tag, 0]);
+
+        match self {
+            Self::None => {}
+            Self::Bool(value) => out.push(*value as u8),
+            Self::Number(value) => out.extend(value.to_le_bytes()),
+            Self::String(text) => write_string(out, text),
+            Self::List(items) => {
+                out.extend((items.len() as u32).to_le_bytes());
+                for item in items {
+                    write_string(out, "");
+                    item.write(out);
+                }
+            }
+            Self::Dictionary(items) => {
+                out.extend((items.len() as u32).to_le_bytes());
+                for (key, item) in items {
+                    write_string(out, key);
+                    item.write(out);
+                }
+            }
+            Self::Signed(value) => out.extend(value.to_le_bytes()),
+            Self::Unsigned(value) => out.extend(value.to_le_bytes()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&PropertyTree> {
+        match self {
+            Self::Dictionary(items) => items
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, item)| item),
+            _ => None,
+        }
+    }
+
+    /// Value of dictionary key, it is added when missing
+    pub fn entry(&mut self, key: &str) -> Option<&mut PropertyTree> {
+        let Self::Dictionary(items) = self else {
+            return None;
+        };
+
+        let index = match items.iter().position(|(name, _)| name == key) {
+            Some(index) => index,
+            None => {
+                items.push((key.to_string(), Self::Dictionary(Vec::new())));
+                items.len() - 1
+            }
+        };
+
+        Some(&mut items[index].1)
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::None => Value::Null,
+            Self::Bool(value) => Value::Bool(*value),
+            Self::Number(value) => serde_json::Number::from_f64(*value)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            Self::String(text) => Value::String(text.clone()),
+            Self::List(items) => Value::Array(items.iter().map(Self::to_json).collect()),
+            Self::Dictionary(items) => Value::Object(
+                items
+                    .iter()
+                    .map(|(key, item)| (key.clone(), item.to_json()))
+                    .collect(),
+            ),
+            Self::Signed(value) => Value::from(*value),
+            Self::Unsigned(value) => Value::from(*value),
+        }
+    }
+
+    /// Converts JSON value, numbers keep the type of `current` value when there is one,
+    /// new numbers are stored as doubles, like the game did before 2.0
+    pub fn from_json(value: &Value, current: Option<&PropertyTree>) -> anyhow::Result<Self> {
+        Ok(match value {
+            Value::Null => Self::None,
+            Value::Bool(value) => Self::Bool(*value),
+            Value::Number(number) => match current {
+                Some(Self::Signed(_)) => Self::Signed(
+                    number
+                        .as_i64()
+                        .with_context(|| format!("{} isn't an integer", number))?,
+                ),
+                Some(Self::Unsigned(_)) => Self::Unsigned(
+                    number
+                        .as_u64()
+                        .with_context(|| format!("{} isn't a positive integer", number))?,
+                ),
+                _ => Self::Number(number.as_f64().context("Invalid number")?),
+            },
+            Value::String(text) => Self::String(text.clone()),
+            Value::Array(items) => Self::List(
+                items
+                    .iter()
+                    .map(|item| Self::from_json(item, None))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            Value::Object(items) => {
+                //keys of current dictionary keep their order (e.g. r, g, b, a of colors)
+                let mut keys = match current {
+                    Some(Self::Dictionary(current)) => current
+                        .iter()
+                        .map(|(key, _)| key)
+                        .filter(|key| items.contains_key(*key))
+                        .collect::<Vec<_>>(),
+                    _ => Vec::new(),
+                };
+                for key in items.keys() {
+                    if !keys.contains(&key) {
+                        keys.push(key);
+                    }
+                }
+
+                Self::Dictionary(
+                    keys.into_iter()
+                        .map(|key| {
+                            Ok((
+                                key.clone(),
+                                Self::from_json(
+                                    &items[key],
+                                    current.and_then(|current| current.get(key)),
+                                )?,
+                            ))
+                        })
+                        .collect::<anyhow::Result<_>>()?,
+                )
+            }
+        })
+    }
+}
+
+impl ModSettings {
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Cursor::new(data);
+
+        let version = [
+            u16::from_le_bytes(read_bytes(&mut reader)?),
+            u16::from_le_bytes(read_bytes(&mut reader)?),
+            u16::from_le_bytes(read_bytes(&mut reader)?),
+            u16::from_le_bytes(read_bytes(&mut reader)?),
+        ];
+        //since 0.17 the version is followed by a zero byte
+        read_u8(&mut reader)?;
+
+        let tree = PropertyTree::read(&mut reader)?;
+        if reader.position() != data.len() as u64 {
+            anyhow::bail!("Unexpected data after mod settings");
+        }
+
+        Ok(Self { version, tree })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for part in self.version {
+            out.extend(part.to_le_bytes());
+        }
+        out.push(0);
+        self.tree.write(&mut out);
+        out
+    }
+
+    pub async fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        Self::parse(&data).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub async fn save(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, self.to_bytes())
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn game_version(&self) -> String {
+        format!(
+            "{}.{}.{}",
+            self.version[0], self.version[1], self.version[2]
+        )
+    }
+
+    /// Values of settings per scope, without the `value` wrappers of the file
+    pub fn values(&self) -> BTreeMap<String, BTreeMap<String, Value>> {
+        SCOPES
+            .iter()
+            .map(|scope| {
+                let settings = match self.tree.get(scope) {
+                    Some(PropertyTree::Dictionary(items)) => items
+                        .iter()
+                        .map(|(name, setting)| {
+                            let value = setting.get("value").unwrap_or(setting);
+                            (name.clone(), value.to_json())
+                        })
+                        .collect(),
+                    _ => BTreeMap::new(),
+                };
+
+                (scope.to_string(), settings)
+            })
+            .collect()
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Object(
+            self.values()
+                .into_iter()
+                .map(|(scope, settings)| (scope, Value::Object(settings.into_iter().collect())))
+                .collect::<Map<_, _>>(),
+        )
+    }
+
+    /// Sets values from JSON in the format of `to_json`, returns names of changed settings
+    pub fn apply(&mut self, values: &Value) -> anyhow::Result<Vec<String>> {
+        let Value::Object(scopes) = values else {
+            anyhow::bail!("Mod settings have to be an object with scopes");
+        };
+
+        let mut changed = Vec::new();
+
+        for (scope, settings) in scopes {
+            if !SCOPES.contains(&scope.as_str()) {
+                anyhow::bail!("Unknown scope {}, use one of {}", scope, SCOPES.join(", "));
+            }
+            let Value::Object(settings) = settings else {
+                anyhow::bail!("Settings of {} have to be an object", scope);
+            };
+
+            let scope_tree = self
+                .tree
+                .entry(scope)
+                .context("Mod settings root isn't a dictionary")?;
+
+            for (name, value) in settings {
+                let setting = scope_tree
+                    .entry(name)
+                    .with_context(|| format!("Settings of {} aren't a dictionary", scope))?;
+                let current = setting.get("value");
+                let new = PropertyTree::from_json(value, current)
+                    .with_context(|| format!("Invalid value of {}", name))?;
+
+                if current != Some(&new) {
+                    *setting
+                        .entry("value")
+                        .with_context(|| format!("Setting {} isn't a dictionary", name))? = new;
+                    changed.push(format!("{}/{}", scope, name));
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn setting(value: PropertyTree) -> PropertyTree {
+        PropertyTree::Dictionary(vec![("value".to_string(), value)])
+    }
+
+    fn settings() -> ModSettings {
+        ModSettings {
+            version: [2, 0, 10, 0],
+            tree: PropertyTree::Dictionary(vec![
+                (
+                    "startup".to_string(),
+                    PropertyTree::Dictionary(vec![
+                        ("signed".to_string(), setting(PropertyTree::Signed(-3))),
+                        ("unsigned".to_string(), setting(PropertyTree::Unsigned(7))),
+                        ("double".to_string(), setting(PropertyTree::Number(1.5))),
+                        ("flag".to_string(), setting(PropertyTree::Bool(true))),
+                        //long strings store their length in four bytes
+                        (
+                            "long".to_string(),
+                            setting(PropertyTree::String("x".repeat(300))),
+                        ),
+                        (
+                            "empty".to_string(),
+                            setting(PropertyTree::String(String::new())),
+                        ),
+                        (
+                            "color".to_string(),
+                            setting(PropertyTree::Dictionary(vec![
+                                ("r".to_string(), PropertyTree::Number(1.0)),
+                                ("g".to_string(), PropertyTree::Number(0.5)),
+                            ])),
+                        ),
+                    ]),
+                ),
+                (
+                    "runtime-global".to_string(),
+                    PropertyTree::Dictionary(vec![(
+                        "list".to_string(),
+                        setting(PropertyTree::List(vec![
+                            PropertyTree::None,
+                            PropertyTree::Number(2.0),
+                        ])),
+                    )]),
+                ),
+                (
+                    "runtime-per-user".to_string(),
+                    PropertyTree::Dictionary(Vec::new()),
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn parses_known_bytes() {
+        //1.1.110.0, root dictionary with one empty startup dictionary
+        let mut data = vec![1, 0, 1, 0, 110, 0, 0, 0, 0, 5, 0, 1, 0, 0, 0];
+        data.extend([0, 7]);
+        data.extend(b"startup");
+        data.extend([5, 0, 0, 0, 0, 0]);
+
+        let settings = ModSettings::parse(&data).unwrap();
+
+        assert_eq!(settings.game_version(), "1.1.110");
+        assert_eq!(
+            settings.tree,
+            PropertyTree::Dictionary(vec![(
+                "startup".to_string(),
+                PropertyTree::Dictionary(Vec::new())
+            )])
+        );
+        assert_eq!(settings.to_bytes(), data);
+    }
+
+    #[test]
+    fn round_trips_bytes() {
+        let data = settings().to_bytes();
+        let parsed = ModSettings::parse(&data).unwrap();
+
+        assert_eq!(parsed.version, [2, 0, 10, 0]);
+        assert_eq!(parsed.tree, settings().tree);
+        assert_eq!(parsed.to_bytes(), data);
+    }
+
+    #[test]
+    fn apply_keeps_number_types() {
+        let mut settings = settings();
+
+        let changed = settings
+            .apply(&json!({
+                "startup": {
+                    "signed": -10,
+                    "unsigned": 20,
+                    "double": 2.5,
+                    "flag": true,
+                    "new": 4,
+                }
+            }))
+            .unwrap();
+
+        //in order of the JSON object, which sorts keys
+        assert_eq!(
+            changed,
+            [
+                "startup/double",
+                "startup/new",
+                "startup/signed",
+                "startup/unsigned"
+            ]
+        );
+
+        let startup = settings.tree.get("startup").unwrap();
+        let value = |name: &str| startup.get(name).unwrap().get("value").unwrap().clone();
+        assert_eq!(value("signed"), PropertyTree::Signed(-10));
+        assert_eq!(value("unsigned"), PropertyTree::Unsigned(20));
+        assert_eq!(value("double"), PropertyTree::Number(2.5));
+        //new numbers are doubles, like before 2.0
+        assert_eq!(value("new"), PropertyTree::Number(4.0));
+
+        let reparsed = ModSettings::parse(&settings.to_bytes()).unwrap();
+        assert_eq!(reparsed.tree, settings.tree);
+    }
+
+    #[test]
+    fn apply_rejects_values_of_wrong_type() {
+        let mut settings = settings();
+
+        assert!(
+            settings
+                .apply(&json!({"startup": {"unsigned": -1}}))
+                .is_err()
+        );
+        assert!(
+            settings
+                .apply(&json!({"startup": {"signed": 1.5}}))
+                .is_err()
+        );
+        assert!(settings.apply(&json!({"unknown-scope": {}})).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let data = settings().to_bytes();
+
+        for length in 0..data.len() {
+            assert!(
+                ModSettings::parse(&data[..length]).is_err(),
+                "{} bytes",
+                length
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_trailing_data() {
+        let mut data = settings().to_bytes();
+        data.push(0);
+
+        assert!(ModSettings::parse(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_lengths_beyond_input() {
+        let header = [2, 0, 0, 0, 10, 0, 0, 0, 0];
+
+        //string of 4 GiB
+        let mut data = header.to_vec();
+        data.extend([3, 0, 0, 255, 0xff, 0xff, 0xff, 0xff]);
+        assert!(ModSettings::parse(&data).is_err());
+
+        //dictionary with 4 billion items
+        let mut data = header.to_vec();
+        data.extend([5, 0, 0xff, 0xff, 0xff, 0xff]);
+        assert!(ModSettings::parse(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let mut data = vec![2, 0, 0, 0, 10, 0, 0, 0, 0];
+        for _ in 0..=MAX_DEPTH {
+            //list with one item with an empty key
+            data.extend([4, 0, 1, 0, 0, 0, 1]);
+        }
+        data.extend([0, 0]);
+
+        let err = ModSettings::parse(&data).unwrap_err();
+        assert!(err.to_string().contains("nested too deep"));
+    }
+}
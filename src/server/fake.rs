@@ -0,0 +1,806 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{Cursor, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use axum::{
+    Form, Json, Router,
+    extract::{Path as UrlPath, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+};
+use console::style;
+use semver::Version;
+use serde::Deserialize;
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use tokio::{fs, task::JoinHandle};
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    app::api::{
+        portal::{PortalMod, Release, ReleaseInfo},
+        structs::{Arch, Item, LatestReleases, Releases, Stable, Updates, VersionDiff},
+    },
+    mods::{ModInfo, ModVersion},
+    server::{ServerError, ServerResult, internal_error},
+    structs::{DevServerArgs, EndpointArgs},
+};
+
+/// Optional settings of fake updater, stored in root of fixtures
+const CONFIG_FILE: &str = "fake.json";
+/// Mods of fake portal (`mods/<name>/<version>/...`, with info.json in each version)
+const MODS_FOLDER: &str = "mods";
+
+#[derive(Deserialize, Default)]
+struct FakeConfig {
+    /// Stable version per package, the newest fixture is stable when missing
+    #[serde(default)]
+    stable: HashMap<Arch, String>,
+    /// Accepted accounts, any credentials are accepted when empty
+    #[serde(default)]
+    users: HashMap<String, FakeUser>,
+}
+
+#[derive(Deserialize)]
+struct FakeUser {
+    password: String,
+    token: String,
+    /// Password login additionally needs this code, like with email authentication
+    #[serde(default)]
+    email_code: Option<String>,
+}
+
+struct FakeState {
+    fixtures: PathBuf,
+    config: FakeConfig,
+}
+
+#[derive(Deserialize)]
+struct AuthQuery {
+    username: Option<String>,
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LinkQuery {
+    username: Option<String>,
+    token: Option<String>,
+    package: Arch,
+    from: String,
+    to: String,
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    username: String,
+    password: String,
+    email_authentication_code: Option<String>,
+}
+
+fn not_found(message: &str) -> ServerError {
+    ServerError(StatusCode::NOT_FOUND, message.to_string())
+}
+
+fn parse_arch(name: &str) -> Option<Arch> {
+    match serde_json::from_value(json!(name)) {
+        Ok(Arch::Other) | Err(_) => None,
+        Ok(arch) => Some(arch),
+    }
+}
+
+fn host(headers: &HeaderMap) -> &str {
+    headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or("localhost")
+}
+
+/// Files of fixture tree by their relative path with `/` separators
+fn collect_files(root: &Path) -> anyhow::Result<BTreeMap<String, PathBuf>> {
+    let mut files = BTreeMap::new();
+    let mut to_walk = vec![root.to_path_buf()];
+
+    while let Some(folder) = to_walk.pop() {
+        for entry in std::fs::read_dir(&folder).context("Unable to read fixture folder")? {
+            let path = entry.context("Unable to read fixture folder")?.path();
+
+            if path.is_dir() {
+                to_walk.push(path);
+                continue;
+            }
+
+            let relative = path
+                .strip_prefix(root)
+                .context("Fixture file outside of its tree")?
+                .components()
+                .filter_map(|component| component.as_os_str().to_str())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            files.insert(relative, path);
+        }
+    }
+
+    Ok(files)
+}
+
+fn zip_files<'a>(
+    files: impl Iterator<Item = (String, &'a Path)>,
+    extra: Option<(&str, String)>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    //fixed time, so the same fixtures always give the same package (and checksum)
+    let options = SimpleFileOptions::default().last_modified_time(zip::DateTime::default());
+
+    if let Some((name, data)) = extra {
+        writer.start_file(name, options)?;
+        writer.write_all(data.as_bytes())?;
+    }
+
+    for (name, path) in files {
+        writer.start_file(name, options)?;
+        writer.write_all(&std::fs::read(path).context("Unable to read fixture file")?)?;
+    }
+
+    Ok(writer.finish()?.into_inner())
+}
+
+/// Headless server is distributed as .tar.xz, with the same top level folder
+fn tar_xz_files<'a>(files: impl Iterator<Item = (String, &'a Path)>) -> anyhow::Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(xz2::write::XzEncoder::new(Vec::new(), 6));
+
+    for (name, path) in files {
+        builder
+            .append_path_with_name(path, name)
+            .context("Unable to add fixture file")?;
+    }
+
+    Ok(builder.into_inner()?.finish()?)
+}
+
+/// Full game package, with all files in one top level folder like the official ones
+fn full_package(arch: &Arch, tree: &Path) -> anyhow::Result<Vec<u8>> {
+    let files = collect_files(tree)?;
+
+    if arch.is_public() {
+        return tar_xz_files(
+            files
+                .iter()
+                .map(|(name, path)| (format!("factorio/{}", name), path.as_path())),
+        );
+    }
+
+    zip_files(
+        files
+            .iter()
+            .map(|(name, path)| (format!("factorio/{}", name), path.as_path())),
+        None,
+    )
+}
+
+/// Patch package in the layout of official ones: one top level folder with info.json of the
+/// update and files, which differ between the two trees. Changed files are stored whole
+/// instead of binary diffs, removed ones are listed in info.json.
+fn patch_package(
+    arch: &Arch,
+    from: &str,
+    from_tree: &Path,
+    to: &str,
+    to_tree: &Path,
+) -> anyhow::Result<Vec<u8>> {
+    let old = collect_files(from_tree)?;
+    let new = collect_files(to_tree)?;
+    let root = format!("{}_{}-{}_update", arch, from, to);
+
+    let mut contents = Vec::new();
+    for (name, path) in &new {
+        let changed = match old.get(name) {
+            None => true,
+            Some(old_path) => std::fs::read(old_path)? != std::fs::read(path)?,
+        };
+
+        if changed {
+            contents.push((format!("{}/{}", root, name), path.as_path()));
+        }
+    }
+
+    let removed = old
+        .keys()
+        .filter(|name| !new.contains_key(*name))
+        .collect::<Vec<_>>();
+
+    let info = json!({
+        "type": "update",
+        "apiVersion": 2,
+        "target": arch,
+        "from": from,
+        "to": to,
+        "removed": removed,
+    });
+
+    zip_files(
+        contents.into_iter(),
+        Some((
+            &format!("{}/info.json", root),
+            serde_json::to_string_pretty(&info)?,
+        )),
+    )
+}
+
+fn package_response(data: Vec<u8>, content_type: &str) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_LENGTH, data.len().to_string()),
+        ],
+        data,
+    )
+        .into_response()
+}
+
+impl FakeState {
+    fn check_credentials(
+        &self,
+        username: &Option<String>,
+        token: &Option<String>,
+    ) -> ServerResult<()> {
+        if self.config.users.is_empty() {
+            return Ok(());
+        }
+
+        match (username, token) {
+            (Some(username), Some(token))
+                if self.config.users.get(username).map(|user| &user.token) == Some(token) =>
+            {
+                Ok(())
+            }
+            _ => Err(ServerError(
+                StatusCode::UNAUTHORIZED,
+                "Invalid username or token".to_string(),
+            )),
+        }
+    }
+
+    /// Versions of every package in fixtures offered by the updater, sorted from the oldest
+    async fn packages(&self) -> ServerResult<Vec<(Arch, Vec<Version>)>> {
+        let mut packages = self.all_packages().await?;
+
+        //versions after the stable one are not offered
+        for (arch, versions) in &mut packages {
+            if let Some(stable) = self
+                .config
+                .stable
+                .get(arch)
+                .and_then(|stable| Version::parse(stable).ok())
+            {
+                versions.retain(|version| *version <= stable);
+            }
+        }
+
+        packages.retain(|(_, versions)| !versions.is_empty());
+
+        Ok(packages)
+    }
+
+    /// Versions of every package in fixtures including experimental ones, sorted from the oldest
+    async fn all_packages(&self) -> ServerResult<Vec<(Arch, Vec<Version>)>> {
+        let mut packages = Vec::new();
+
+        let mut entries = fs::read_dir(&self.fixtures)
+            .await
+            .context("Unable to read fixtures")
+            .map_err(internal_error)?;
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .context("Unable to read fixtures")
+            .map_err(internal_error)?
+        {
+            let Some(arch) = entry.file_name().to_str().and_then(parse_arch) else {
+                continue;
+            };
+
+            let mut versions = Vec::new();
+            let mut version_entries = fs::read_dir(entry.path())
+                .await
+                .context("Unable to read package fixtures")
+                .map_err(internal_error)?;
+
+            while let Some(version) = version_entries
+                .next_entry()
+                .await
+                .context("Unable to read package fixtures")
+                .map_err(internal_error)?
+            {
+                if let Some(version) = version
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| Version::parse(name).ok())
+                {
+                    versions.push(version);
+                }
+            }
+
+            versions.sort();
+
+            if !versions.is_empty() {
+                packages.push((arch, versions));
+            }
+        }
+
+        Ok(packages)
+    }
+
+    /// Fixture tree of package version
+    async fn tree(&self, arch: &Arch, version: &str) -> ServerResult<PathBuf> {
+        let versions = self
+            .all_packages()
+            .await?
+            .into_iter()
+            .find(|(package, _)| package == arch)
+            .map(|(_, versions)| versions)
+            .ok_or_else(|| not_found("Package not found"))?;
+
+        if !versions.iter().any(|known| known.to_string() == version) {
+            return Err(not_found("Version not found"));
+        }
+
+        Ok(self.fixtures.join(arch.to_string()).join(version))
+    }
+}
+
+async fn get_available_versions(
+    State(state): State<Arc<FakeState>>,
+    Query(query): Query<AuthQuery>,
+) -> ServerResult<Json<Updates>> {
+    state.check_credentials(&query.username, &query.token)?;
+
+    Ok(Json(
+        state
+            .packages()
+            .await?
+            .into_iter()
+            .map(|(arch, versions)| {
+                let mut items = versions
+                    .windows(2)
+                    .map(|pair| {
+                        Item::VersionDiff(VersionDiff {
+                            from: pair[0].to_string(),
+                            to: pair[1].to_string(),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                if let Some(stable) = versions.last() {
+                    items.push(Item::Stable(Stable {
+                        stable: stable.to_string(),
+                    }));
+                }
+
+                (arch, items)
+            })
+            .collect(),
+    ))
+}
+
+async fn get_download_link(
+    State(state): State<Arc<FakeState>>,
+    Query(query): Query<LinkQuery>,
+    headers: HeaderMap,
+) -> ServerResult<Json<Vec<String>>> {
+    state.check_credentials(&query.username, &query.token)?;
+
+    state.tree(&query.package, &query.from).await?;
+    state.tree(&query.package, &query.to).await?;
+
+    Ok(Json(vec![format!(
+        "http://{}/packages/patch/{}/{}/{}/factorio_{}_patch_v{}_to_v{}.zip",
+        host(&headers),
+        query.package,
+        query.from,
+        query.to,
+        query.package,
+        query.from,
+        query.to
+    )]))
+}
+
+async fn get_download(
+    State(state): State<Arc<FakeState>>,
+    UrlPath((version, build, distro)): UrlPath<(String, String, String)>,
+    Query(query): Query<AuthQuery>,
+    headers: HeaderMap,
+) -> ServerResult<Redirect> {
+    let arch =
+        Arch::from_download_build(&build, &distro).ok_or_else(|| not_found("Build not found"))?;
+
+    if !arch.is_public() {
+        state.check_credentials(&query.username, &query.token)?;
+    }
+
+    state.tree(&arch, &version).await?;
+
+    if arch.is_public() {
+        return Ok(Redirect::temporary(&format!(
+            "http://{}/packages/full/{}/{}/factorio-headless_linux_{}.tar.xz",
+            host(&headers),
+            arch,
+            version,
+            version
+        )));
+    }
+
+    Ok(Redirect::temporary(&format!(
+        "http://{}/packages/full/{}/{}/factorio_{}_v{}.zip",
+        host(&headers),
+        arch,
+        version,
+        arch,
+        version
+    )))
+}
+
+async fn get_full_package(
+    State(state): State<Arc<FakeState>>,
+    UrlPath((arch, version, _)): UrlPath<(String, String, String)>,
+) -> ServerResult<Response> {
+    let arch = parse_arch(&arch).ok_or_else(|| not_found("Package not found"))?;
+    let tree = state.tree(&arch, &version).await?;
+
+    let content_type = if arch.is_public() {
+        "application/x-xz"
+    } else {
+        "application/zip"
+    };
+
+    let data = tokio::task::spawn_blocking(move || full_package(&arch, &tree))
+        .await
+        .context("Package generation was interrupted")
+        .map_err(internal_error)?
+        .map_err(internal_error)?;
+
+    Ok(package_response(data, content_type))
+}
+
+async fn get_patch_package(
+    State(state): State<Arc<FakeState>>,
+    UrlPath((arch, from, to, _)): UrlPath<(String, String, String, String)>,
+) -> ServerResult<Response> {
+    let arch = parse_arch(&arch).ok_or_else(|| not_found("Package not found"))?;
+    let from_tree = state.tree(&arch, &from).await?;
+    let to_tree = state.tree(&arch, &to).await?;
+
+    let data =
+        tokio::task::spawn_blocking(move || patch_package(&arch, &from, &from_tree, &to, &to_tree))
+            .await
+            .context("Package generation was interrupted")
+            .map_err(internal_error)?
+            .map_err(internal_error)?;
+
+    Ok(package_response(data, "application/zip"))
+}
+
+impl FakeState {
+    /// Versions of mod in fixtures with their info.json, sorted from the oldest
+    async fn mod_versions(&self, name: &str) -> ServerResult<Vec<(ModInfo, PathBuf)>> {
+        let folder = self.fixtures.join(MODS_FOLDER).join(name);
+        let Ok(mut entries) = fs::read_dir(&folder).await else {
+            return Err(not_found("Mod not found"));
+        };
+
+        let mut versions = Vec::new();
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .context("Unable to read mod fixtures")
+            .map_err(internal_error)?
+        {
+            let Ok(data) = fs::read_to_string(entry.path().join("info.json")).await else {
+                continue;
+            };
+
+            let info: ModInfo = serde_json::from_str(&data)
+                .with_context(|| format!("Invalid info.json of mod fixture {}", name))
+                .map_err(internal_error)?;
+            versions.push((info, entry.path()));
+        }
+
+        versions.sort_by_key(|(info, _)| ModVersion::parse(&info.version));
+
+        Ok(versions)
+    }
+}
+
+/// Mod zip with all files of the release in `<name>_<version>` folder
+fn mod_package(name: &str, version: &str, tree: &Path) -> anyhow::Result<Vec<u8>> {
+    let files = collect_files(tree)?;
+
+    zip_files(
+        files
+            .iter()
+            .map(|(file, path)| (format!("{}_{}/{}", name, version, file), path.as_path())),
+        None,
+    )
+}
+
+/// Stand-in of mods.factorio.com/api/mods/{name}/full
+async fn get_mod(
+    State(state): State<Arc<FakeState>>,
+    UrlPath(name): UrlPath<String>,
+) -> ServerResult<Json<PortalMod>> {
+    let versions = state.mod_versions(&name).await?;
+
+    let mut releases = Vec::new();
+    for (info, tree) in versions {
+        let (package_name, version) = (name.clone(), info.version.clone());
+        let data = tokio::task::spawn_blocking(move || mod_package(&package_name, &version, &tree))
+            .await
+            .context("Package generation was interrupted")
+            .map_err(internal_error)?
+            .map_err(internal_error)?;
+
+        releases.push(Release {
+            download_url: format!("/download/{}/{}", name, info.version),
+            file_name: format!("{}_{}.zip", name, info.version),
+            info_json: ReleaseInfo {
+                factorio_version: info.factorio_version,
+                dependencies: info.dependencies,
+            },
+            released_at: String::new(),
+            version: info.version,
+            sha1: Some(
+                Sha1::digest(&data)
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect(),
+            ),
+        });
+    }
+
+    Ok(Json(PortalMod {
+        title: name.clone(),
+        releases,
+        name,
+    }))
+}
+
+async fn get_mod_download(
+    State(state): State<Arc<FakeState>>,
+    UrlPath((name, version)): UrlPath<(String, String)>,
+    Query(query): Query<AuthQuery>,
+) -> ServerResult<Response> {
+    state.check_credentials(&query.username, &query.token)?;
+
+    let tree = state
+        .mod_versions(&name)
+        .await?
+        .into_iter()
+        .find(|(info, _)| info.version == version)
+        .map(|(_, tree)| tree)
+        .ok_or_else(|| not_found("Release not found"))?;
+
+    let data = tokio::task::spawn_blocking(move || mod_package(&name, &version, &tree))
+        .await
+        .context("Package generation was interrupted")
+        .map_err(internal_error)?
+        .map_err(internal_error)?;
+
+    Ok(package_response(data, "application/zip"))
+}
+
+/// Stand-in of factorio.com/api/latest-releases, experimental is the newest fixture
+async fn latest_releases(
+    State(state): State<Arc<FakeState>>,
+) -> ServerResult<Json<LatestReleases>> {
+    let mut releases = LatestReleases::default();
+
+    let set = |releases: &mut Releases, arch: &Arch, version: String| {
+        let (build, _) = arch.download_build();
+        match build {
+            "headless" => releases.headless = Some(version),
+            "expansion" => releases.expansion = Some(version),
+            _ => releases.alpha = Some(version),
+        }
+    };
+
+    for (arch, versions) in state.packages().await? {
+        if let Some(version) = versions.last() {
+            set(&mut releases.stable, &arch, version.to_string());
+        }
+    }
+
+    for (arch, versions) in state.all_packages().await? {
+        if let Some(version) = versions.last() {
+            set(&mut releases.experimental, &arch, version.to_string());
+        }
+    }
+
+    Ok(Json(releases))
+}
+
+/// Stand-in of auth.factorio.com/api-login
+async fn api_login(State(state): State<Arc<FakeState>>, Form(form): Form<LoginForm>) -> Response {
+    let failed = |error: &str, message: &str| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": error,
+                "message": message,
+            })),
+        )
+            .into_response()
+    };
+
+    let token = if state.config.users.is_empty() {
+        format!("fake-token-{}", form.username)
+    } else {
+        let Some(user) = state
+            .config
+            .users
+            .get(&form.username)
+            .filter(|user| user.password == form.password)
+        else {
+            return failed("login-failed", "Invalid username or password");
+        };
+
+        if let Some(code) = &user.email_code
+            && form.email_authentication_code.as_ref() != Some(code)
+        {
+            return failed(
+                "email-authentication-required",
+                "Authentication code was sent to your email",
+            );
+        }
+
+        user.token.clone()
+    };
+
+    Json(json!({
+        "username": form.username,
+        "token": token,
+    }))
+    .into_response()
+}
+
+async fn router(fixtures: PathBuf) -> anyhow::Result<Router> {
+    let config = match fs::read_to_string(fixtures.join(CONFIG_FILE)).await {
+        Ok(data) => serde_json::from_str(&data).context("Failed to parse fake.json")?,
+        Err(_) => FakeConfig::default(),
+    };
+
+    let state = Arc::new(FakeState { fixtures, config });
+
+    Ok(Router::new()
+        .route("/get-available-versions", get(get_available_versions))
+        .route("/get-download-link", get(get_download_link))
+        .route(
+            "/get-download/{version}/{build}/{distro}",
+            get(get_download),
+        )
+        .route(
+            "/packages/full/{arch}/{version}/{file}",
+            get(get_full_package),
+        )
+        .route(
+            "/packages/patch/{arch}/{from}/{to}/{file}",
+            get(get_patch_package),
+        )
+        .route("/api/latest-releases", get(latest_releases))
+        .route("/api/mods/{name}/full", get(get_mod))
+        .route("/download/{name}/{version}", get(get_mod_download))
+        .route("/api-login", post(api_login))
+        .with_state(state))
+}
+
+/// Fake updater running in background (e.g. for tests), stopped when dropped
+pub struct FakeServer {
+    address: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl FakeServer {
+    /// Starts server with fixtures, port 0 picks a free one
+    pub async fn start(fixtures: PathBuf, listen: SocketAddr) -> anyhow::Result<Self> {
+        let router = router(fixtures).await?;
+
+        let listener = tokio::net::TcpListener::bind(listen)
+            .await
+            .context("Unable to bind server address")?;
+        let address = listener
+            .local_addr()
+            .context("Unable to get server address")?;
+
+        let task = tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, router).await {
+                log::error!("Fake updater stopped: {}", err);
+            }
+        });
+
+        Ok(Self { address, task })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Overrides pointing all APIs to this server
+    pub fn endpoints(&self) -> EndpointArgs {
+        let url = format!("http://{}", self.address);
+
+        EndpointArgs {
+            updater_url: Some(url.clone()),
+            download_url: Some(url.clone()),
+            auth_url: Some(url.clone()),
+            mods_url: Some(url),
+            offline: false,
+        }
+    }
+}
+
+#[cfg(test)]
+impl FakeServer {
+    /// Starts server on a free port of localhost
+    pub(crate) async fn start_local(fixtures: &Path) -> Self {
+        Self::start(
+            fixtures.to_path_buf(),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+        )
+        .await
+        .expect("fake server should start")
+    }
+
+    /// Client of the APIs of this server
+    pub(crate) async fn api(&self, account: crate::config::Account) -> crate::app::api::Api {
+        let connection = crate::functions::create_connection(
+            &crate::config::Config::default(),
+            &self.endpoints(),
+        )
+        .await
+        .expect("default HTTP settings are valid");
+
+        crate::app::api::Api::new(account, connection)
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Serves fake updater API with packages generated from fixture trees
+/// (`<fixtures>/<package>/<version>/...`), so updates can be tried without factorio.com.
+pub async fn dev_server(args: DevServerArgs) -> anyhow::Result<()> {
+    let router = router(args.fixtures.clone()).await?;
+
+    let listener = tokio::net::TcpListener::bind(args.listen)
+        .await
+        .context("Unable to bind server address")?;
+
+    println!(
+        "{}",
+        style(format!(
+            "Serving fake updater with fixtures {} on http://{}",
+            args.fixtures.display(),
+            args.listen
+        ))
+        .green()
+        .bold()
+    );
+    println!(
+        "Use it with --updater-url http://{0} --download-url http://{0} --auth-url http://{0} --mods-url http://{0}",
+        args.listen
+    );
+
+    axum::serve(listener, router)
+        .await
+        .context("Server stopped unexpectedly")
+}
@@ -0,0 +1,426 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{Path as UrlPath, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+};
+use console::style;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::fs;
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    app::api::structs::{Arch, Item, Stable, Updates, VersionDiff},
+    steps::{
+        items::get_arch_folder,
+        mirror::{MirrorIndex, find_downloaded},
+    },
+    structs::ServeArgs,
+};
+
+pub mod fake;
+
+struct ServerState {
+    root: PathBuf,
+    /// username -> token, credentials are not checked when missing
+    allowlist: Option<HashMap<String, String>>,
+}
+
+/// Error in the same shape as the one returned by factorio.com
+struct ServerError(StatusCode, String);
+
+impl IntoResponse for ServerError {
+    fn into_response(self) -> Response {
+        (
+            self.0,
+            Json(json!({
+                "message": self.1,
+                "status": self.0.as_u16(),
+            })),
+        )
+            .into_response()
+    }
+}
+
+type ServerResult<T> = Result<T, ServerError>;
+
+fn internal_error(err: anyhow::Error) -> ServerError {
+    log::error!("{:#}", err);
+    ServerError(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+}
+
+#[derive(Deserialize)]
+struct AuthQuery {
+    username: Option<String>,
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LinkQuery {
+    username: Option<String>,
+    token: Option<String>,
+    package: Arch,
+    from: String,
+    to: String,
+}
+
+impl ServerState {
+    fn check_credentials(
+        &self,
+        username: &Option<String>,
+        token: &Option<String>,
+    ) -> ServerResult<()> {
+        let Some(allowlist) = &self.allowlist else {
+            return Ok(());
+        };
+
+        match (username, token) {
+            (Some(username), Some(token)) if allowlist.get(username) == Some(token) => Ok(()),
+            _ => Err(ServerError(
+                StatusCode::UNAUTHORIZED,
+                "Invalid username or token".to_string(),
+            )),
+        }
+    }
+
+    async fn index(&self) -> ServerResult<MirrorIndex> {
+        MirrorIndex::load(&self.root)
+            .await
+            .map_err(internal_error)?
+            .ok_or_else(|| ServerError(StatusCode::NOT_FOUND, "Mirror is empty".to_string()))
+    }
+}
+
+/// Path stays inside of the mirror folder
+fn is_safe_path(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// URL of a mirrored file, built from the Host the client used to reach us.
+/// Credentials are passed on, because files are checked against the allowlist too.
+fn file_url(
+    headers: &HeaderMap,
+    path: &Path,
+    username: &Option<String>,
+    token: &Option<String>,
+) -> String {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or("localhost");
+
+    let path = path
+        .components()
+        .filter_map(|component| component.as_os_str().to_str())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let url = format!("http://{}/files/{}", host, path);
+    let (Some(username), Some(token)) = (username, token) else {
+        return url;
+    };
+
+    match reqwest::Url::parse(&url) {
+        Ok(mut url) => {
+            url.query_pairs_mut()
+                .append_pair("username", username)
+                .append_pair("token", token);
+            url.to_string()
+        }
+        Err(_) => url,
+    }
+}
+
+async fn get_available_versions(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<AuthQuery>,
+) -> ServerResult<Json<Updates>> {
+    state.check_credentials(&query.username, &query.token)?;
+
+    let index = state.index().await?;
+
+    Ok(Json(
+        index
+            .packages
+            .into_iter()
+            .map(|(arch, package)| {
+                let mut items = package
+                    .patches
+                    .into_iter()
+                    .map(|patch| {
+                        Item::VersionDiff(VersionDiff {
+                            from: patch.from,
+                            to: patch.to,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                items.push(Item::Stable(Stable {
+                    stable: package.stable,
+                }));
+
+                (arch, items)
+            })
+            .collect(),
+    ))
+}
+
+async fn get_download_link(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<LinkQuery>,
+    headers: HeaderMap,
+) -> ServerResult<Json<Vec<String>>> {
+    state.check_credentials(&query.username, &query.token)?;
+
+    let index = state.index().await?;
+
+    let patch = index
+        .packages
+        .get(&query.package)
+        .and_then(|package| {
+            package
+                .patches
+                .iter()
+                .find(|patch| patch.from == query.from && patch.to == query.to)
+        })
+        .ok_or_else(|| ServerError(StatusCode::NOT_FOUND, "Patch not found".to_string()))?;
+
+    Ok(Json(vec![file_url(
+        &headers,
+        &patch.path,
+        &query.username,
+        &query.token,
+    )]))
+}
+
+async fn get_download(
+    State(state): State<Arc<ServerState>>,
+    UrlPath((version, build, distro)): UrlPath<(String, String, String)>,
+    Query(query): Query<AuthQuery>,
+    headers: HeaderMap,
+) -> ServerResult<Redirect> {
+    state.check_credentials(&query.username, &query.token)?;
+
+    let not_found = || ServerError(StatusCode::NOT_FOUND, "Build not found".to_string());
+
+    let arch = Arch::from_download_build(&build, &distro).ok_or_else(not_found)?;
+    let folder = Path::new(get_arch_folder(&arch))
+        .join("full")
+        .join(&version);
+
+    if !is_safe_path(&folder) {
+        return Err(not_found());
+    }
+
+    let file = match fs::try_exists(state.root.join(&folder)).await {
+        Ok(true) => find_downloaded(&state.root.join(&folder))
+            .await
+            .map_err(internal_error)?
+            .ok_or_else(not_found)?,
+        _ => return Err(not_found()),
+    };
+
+    let file = folder.join(file.file_name().ok_or_else(not_found)?);
+
+    //redirect, so the client sees the real file name like with factorio.com
+    Ok(Redirect::temporary(&file_url(
+        &headers,
+        &file,
+        &query.username,
+        &query.token,
+    )))
+}
+
+async fn get_file(
+    State(state): State<Arc<ServerState>>,
+    UrlPath(path): UrlPath<String>,
+    Query(query): Query<AuthQuery>,
+) -> ServerResult<Response> {
+    state.check_credentials(&query.username, &query.token)?;
+
+    let path = Path::new(&path);
+    let not_found = || ServerError(StatusCode::NOT_FOUND, "File not found".to_string());
+
+    if !is_safe_path(path) {
+        return Err(not_found());
+    }
+
+    let file = fs::File::open(state.root.join(path))
+        .await
+        .map_err(|_| not_found())?;
+    let metadata = file
+        .metadata()
+        .await
+        .context("Unable to read file metadata")
+        .map_err(internal_error)?;
+
+    if !metadata.is_file() {
+        return Err(not_found());
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_LENGTH, metadata.len().to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+async fn load_allowlist(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    let data = fs::read_to_string(path)
+        .await
+        .context("Failed to read allowlist")?;
+
+    serde_json::from_str(&data).context("Failed to parse allowlist")
+}
+
+fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/get-available-versions", get(get_available_versions))
+        .route("/get-download-link", get(get_download_link))
+        .route(
+            "/get-download/{version}/{build}/{distro}",
+            get(get_download),
+        )
+        .route("/files/{*path}", get(get_file))
+        .with_state(Arc::new(state))
+}
+
+/// Serves a mirror folder (see `mirror` command) with the same endpoints as the official
+/// updater API, so other copies of this tool can use it as their base URL.
+pub async fn serve(args: ServeArgs) -> anyhow::Result<()> {
+    let allowlist = match &args.allowlist {
+        Some(path) => Some(load_allowlist(path).await?),
+        None => None,
+    };
+
+    let router = router(ServerState {
+        root: args.mirror.clone(),
+        allowlist,
+    });
+
+    let listener = tokio::net::TcpListener::bind(args.listen)
+        .await
+        .context("Unable to bind server address")?;
+
+    println!(
+        "{}",
+        style(format!(
+            "Serving mirror {} on http://{}",
+            args.mirror.display(),
+            args.listen
+        ))
+        .green()
+        .bold()
+    );
+
+    axum::serve(listener, router)
+        .await
+        .context("Server stopped unexpectedly")
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    /// Serves mirror in `<temp>/mirror`, next to a file outside of it
+    async fn start(allowlist: Option<HashMap<String, String>>) -> (String, TempDir) {
+        let temp = TempDir::new("serve").unwrap();
+        let root = temp.path().join("mirror");
+        std::fs::create_dir_all(root.join("patches")).unwrap();
+        std::fs::write(root.join("patches").join("patch.zip"), "patch").unwrap();
+        std::fs::write(temp.path().join("outside.txt"), "secret").unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let router = router(ServerState { root, allowlist });
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        (url, temp)
+    }
+
+    fn allowlist() -> Option<HashMap<String, String>> {
+        Some(HashMap::from([("player".to_string(), "token".to_string())]))
+    }
+
+    async fn status(url: &str) -> StatusCode {
+        reqwest::get(url).await.unwrap().status()
+    }
+
+    #[test]
+    fn only_relative_paths_inside_mirror_are_safe() {
+        assert!(is_safe_path(Path::new("patches/patch.zip")));
+        assert!(!is_safe_path(Path::new("../outside.txt")));
+        assert!(!is_safe_path(Path::new("patches/../../outside.txt")));
+        assert!(!is_safe_path(Path::new("/etc/passwd")));
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_or_wrong_credentials() {
+        let (url, _temp) = start(allowlist()).await;
+
+        for query in ["", "?username=player", "?username=player&token=wrong"] {
+            assert_eq!(
+                status(&format!("{}/files/patches/patch.zip{}", url, query)).await,
+                StatusCode::UNAUTHORIZED
+            );
+            assert_eq!(
+                status(&format!("{}/get-available-versions{}", url, query)).await,
+                StatusCode::UNAUTHORIZED
+            );
+        }
+
+        let res = reqwest::get(format!(
+            "{}/files/patches/patch.zip?username=player&token=token",
+            url
+        ))
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await.unwrap(), "patch");
+    }
+
+    #[tokio::test]
+    async fn serves_files_without_allowlist() {
+        let (url, _temp) = start(None).await;
+
+        assert_eq!(
+            status(&format!("{}/files/patches/patch.zip", url)).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_paths_outside_of_mirror() {
+        let (url, temp) = start(None).await;
+        let outside = temp.path().join("outside.txt");
+
+        //slashes are encoded, so the client doesn't resolve dot segments before sending
+        for path in [
+            "..%2Foutside.txt".to_string(),
+            "patches/..%2F..%2Foutside.txt".to_string(),
+            outside.display().to_string().replace('/', "%2F"),
+        ] {
+            assert_eq!(
+                status(&format!("{}/files/{}", url, path)).await,
+                StatusCode::NOT_FOUND,
+                "{}",
+                path
+            );
+        }
+    }
+}
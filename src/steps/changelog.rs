@@ -0,0 +1,196 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use console::style;
+use tokio::fs;
+
+use crate::{
+    app::api::structs::Arch,
+    changelog::{CHANGELOG_PATH, Changelog, VersionEntry},
+    functions::load_config,
+    steps::{items::arch_install_folder, mirror::find_downloaded, update::packages_folder},
+    structs::ChangelogArgs,
+};
+
+/// Path inside of full package, ignoring its top level folder
+fn is_changelog(path: &Path) -> bool {
+    path.components().skip(1).collect::<PathBuf>() == Path::new(CHANGELOG_PATH)
+}
+
+/// Changelog from downloaded full package, if it contains one
+fn read_package_changelog(path: &Path) -> anyhow::Result<Option<String>> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let file = std::fs::File::open(path).context("Unable to open package")?;
+    let mut data = String::new();
+
+    if name.ends_with(".tar.xz") {
+        let mut archive = tar::Archive::new(xz2::read::XzDecoder::new(file));
+
+        for entry in archive.entries().context("Unable to read package")? {
+            let mut entry = entry.context("Unable to read package")?;
+
+            if is_changelog(&entry.path().context("Malformed file path")?) {
+                entry
+                    .read_to_string(&mut data)
+                    .context("Unable to read changelog from package")?;
+                return Ok(Some(data));
+            }
+        }
+
+        return Ok(None);
+    }
+
+    let mut archive = zip::ZipArchive::new(file).context("Unable to read package")?;
+
+    let index = (0..archive.len()).find(|i| {
+        archive
+            .by_index(*i)
+            .ok()
+            .and_then(|file| file.enclosed_name())
+            .is_some_and(|file| is_changelog(&file))
+    });
+
+    match index {
+        Some(index) => {
+            archive
+                .by_index(index)
+                .context("Unable to access changelog in package")?
+                .read_to_string(&mut data)
+                .context("Unable to read changelog from package")?;
+            Ok(Some(data))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Downloaded full package of version `to`, patch packages only contain binary diffs
+async fn downloaded_package(arch: &Arch, to: &str) -> anyhow::Result<Option<PathBuf>> {
+    let full = packages_folder(arch)?.join("full").join(to);
+    if !fs::try_exists(&full).await.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    find_downloaded(&full).await
+}
+
+/// Changelog of version `to` from downloaded package, or the one of installation, when there is
+/// no readable package. Returns it with path, from which it was read.
+pub async fn load_changelog(
+    arch: &Arch,
+    folder: &Path,
+    to: Option<&str>,
+) -> anyhow::Result<(Changelog, PathBuf)> {
+    if let Some(to) = to
+        && let Some(package) = downloaded_package(arch, to).await?
+    {
+        let path = package.clone();
+        let data = tokio::task::spawn_blocking(move || read_package_changelog(&path))
+            .await
+            .context("Reading of package was interrupted")?;
+
+        match data {
+            Ok(Some(data)) => return Ok((Changelog::parse(&data), package)),
+            Ok(None) => {}
+            Err(err) => log::warn!(
+                "Unable to read changelog from {}, using the installed one: {:?}",
+                package.display(),
+                err
+            ),
+        }
+    }
+
+    let path = folder.join(CHANGELOG_PATH);
+    let data = fs::read_to_string(&path)
+        .await
+        .with_context(|| format!("Unable to read {}", path.display()))?;
+
+    Ok((Changelog::parse(&data), path))
+}
+
+fn print_entry(entry: &VersionEntry) {
+    match &entry.date {
+        Some(date) => println!(
+            "{} {}",
+            style(format!("Version {}", entry.version)).bold().blue(),
+            style(format!("({})", date)).dim()
+        ),
+        None => println!(
+            "{}",
+            style(format!("Version {}", entry.version)).bold().blue()
+        ),
+    }
+
+    for section in &entry.sections {
+        println!("  {}", style(format!("{}:", section.name)).yellow());
+
+        for text in &section.entries {
+            println!("    - {}", text);
+        }
+    }
+
+    println!();
+}
+
+pub async fn handle_changelog(args: ChangelogArgs) -> anyhow::Result<()> {
+    let mut arch: Arch = (args.version.clone(), args.platform.clone()).into();
+    let folder = arch_install_folder(&arch, args.custom_folder.as_deref());
+    let installed = load_config(&folder).await?;
+
+    if let Some(installed) = &installed {
+        arch = (installed.version.clone(), installed.platform.clone()).into();
+    }
+
+    let from = match args.from {
+        Some(from) => Some(from),
+        None => installed.map(|installed| installed.current_version),
+    };
+
+    let (changelog, source) = load_changelog(&arch, &folder, args.to.as_deref()).await?;
+    println!(
+        "{}",
+        style(format!("Changelog from {}", source.display())).dim()
+    );
+
+    let from = from
+        .as_deref()
+        .map(semver::Version::parse)
+        .transpose()
+        .context("Unable to parse --from version")?;
+    let to = args
+        .to
+        .as_deref()
+        .map(semver::Version::parse)
+        .transpose()
+        .context("Unable to parse --to version")?;
+
+    let entries = changelog.between(from.as_ref(), to.as_ref());
+
+    if entries.is_empty() {
+        println!("{}", style("No changes in the selected range").yellow());
+    }
+
+    for entry in entries {
+        print_entry(entry);
+    }
+
+    if let (Some(to), Some(latest)) = (&to, changelog.latest())
+        && semver::Version::parse(latest).is_ok_and(|latest| latest < *to)
+    {
+        println!(
+            "{}",
+            style(format!(
+                "Changelog only goes up to {}, download {} first (update command) to see all changes",
+                latest, to
+            ))
+            .yellow()
+        );
+    }
+
+    Ok(())
+}
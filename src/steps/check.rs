@@ -0,0 +1,98 @@
+use anyhow::Context;
+use console::style;
+
+use crate::{
+    app::api::{Api, structs::Arch},
+    config,
+    functions::{create_connection, load_config, save_config},
+    steps::{credentials::config_error, items::arch_install_folder, updates::get_latest_releases},
+    structs::{CheckArgs, Config, EndpointArgs},
+    webhooks::{Event, EventKind, Notifier},
+};
+
+pub async fn handle_check(args: CheckArgs, endpoints: EndpointArgs) -> anyhow::Result<()> {
+    let config = config::Config::load()
+        .await
+        .map_err(config_error)?
+        .unwrap_or_default();
+    let api = Api::anonymous(create_connection(&config, &endpoints).await?);
+
+    let mut arch: Arch = (args.version.clone(), args.platform.clone()).into();
+    let folder = arch_install_folder(&arch, args.custom_folder.as_deref());
+    let installed = load_config(&folder).await?;
+
+    //installation knows better what it is
+    if let Some(installed) = &installed {
+        arch = (installed.version.clone(), installed.platform.clone()).into();
+    }
+
+    let releases = get_latest_releases(&api).await?;
+    let stable = releases.stable.get(&arch);
+    let experimental = releases.experimental.get(&arch);
+
+    println!("Package: {}", style(&arch).bold());
+    println!("Stable: {}", stable.unwrap_or("-"));
+    println!("Experimental: {}", experimental.unwrap_or("-"));
+
+    let Some(installed) = installed else {
+        println!(
+            "{}",
+            style(format!("Nothing is installed in {}", folder.display())).dim()
+        );
+        return Ok(());
+    };
+
+    let latest = if args.experimental {
+        experimental
+    } else {
+        stable
+    }
+    .context("No release found for this package")?;
+
+    let current = semver::Version::parse(&installed.current_version)
+        .context("Unable to parse installed version")?;
+    let latest = semver::Version::parse(latest).context("Unable to parse released version")?;
+
+    if current < latest {
+        println!(
+            "{}",
+            style(format!("Update available: {} -> {}", current, latest))
+                .yellow()
+                .bold()
+        );
+
+        //cron jobs check again and again, the same version is announced once
+        let latest = latest.to_string();
+        let notifier = Notifier::load(endpoints.offline).await;
+        if notifier.is_enabled() && installed.notified_version.as_ref() != Some(&latest) {
+            let mut event = Event::new(
+                EventKind::UpdateAvailable,
+                folder.display().to_string(),
+                arch.to_string(),
+            );
+            event.from = Some(current.to_string());
+            event.to = Some(latest.clone());
+
+            //failed webhooks get the next check
+            if notifier.send(&event).await == 0 {
+                save_config(
+                    &folder,
+                    &Config {
+                        notified_version: Some(latest),
+                        ..installed
+                    },
+                )
+                .await?;
+            }
+        }
+    } else {
+        println!(
+            "{}",
+            style(format!("Installed version {} is up to date", current))
+                .green()
+                .bold()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use anyhow::Context;
+use console::style;
+
+use crate::{
+    config::{Account, Config, ConfigError},
+    structs::{CredentialsAction, CredentialsArgs},
+};
+
+const PASSPHRASE_ENV: &str = "FACTORIO_UPDATER_PASSPHRASE";
+
+/// Passphrase from environment, or from prompt
+pub(crate) fn read_passphrase(confirm: bool) -> anyhow::Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+
+    let passphrase =
+        rpassword::prompt_password("Passphrase: ").context("Unable to read passphrase")?;
+
+    if confirm {
+        let again = rpassword::prompt_password("Repeat passphrase: ")
+            .context("Unable to read passphrase")?;
+
+        if passphrase != again {
+            anyhow::bail!("Passphrases don't match");
+        }
+    }
+
+    Ok(passphrase)
+}
+
+pub(crate) fn config_error(err: ConfigError) -> anyhow::Error {
+    match err {
+        ConfigError::WrongPassphrase => anyhow::anyhow!("Wrong passphrase"),
+        err => anyhow::anyhow!("Config error: {:?}", err),
+    }
+}
+
+/// Account of profile, which is unlocked when its token is encrypted
+pub(crate) fn unlocked_account(config: &Config, profile: Option<&str>) -> anyhow::Result<Account> {
+    let name = profile.unwrap_or(&config.active_profile);
+
+    let mut account = config
+        .account(Some(name))
+        .cloned()
+        .with_context(|| format!("Profile {} doesn't exist, log in first", name))?;
+
+    if account.is_locked() {
+        account
+            .unlock(&read_passphrase(false)?)
+            .map_err(config_error)?;
+    }
+
+    Ok(account)
+}
+
+pub async fn handle_credentials(
+    args: CredentialsArgs,
+    profile: Option<String>,
+) -> anyhow::Result<()> {
+    if let Some(warning) = Config::permission_warning().await {
+        eprintln!("{}", style(warning).yellow());
+    }
+
+    let mut config = Config::load()
+        .await
+        .map_err(config_error)?
+        .context("No credentials are stored, log in first")?;
+
+    let name = profile.unwrap_or_else(|| config.active_profile.clone());
+    let mut account = unlocked_account(&config, Some(&name))?;
+
+    match args.action {
+        CredentialsAction::Encrypt => {
+            if account.encrypted_token.is_some() {
+                anyhow::bail!("Token of profile {} is already encrypted", name);
+            }
+
+            account
+                .encrypt(&read_passphrase(true)?)
+                .map_err(config_error)?;
+        }
+        CredentialsAction::Decrypt => {
+            if account.encrypted_token.is_none() {
+                anyhow::bail!("Token of profile {} is not encrypted", name);
+            }
+
+            account.remove_encryption();
+        }
+    }
+
+    config.profiles.insert(name, account);
+    config.save().await.map_err(config_error)?;
+
+    println!("{}", style("Credentials were saved.").green().bold());
+
+    Ok(())
+}
@@ -0,0 +1,412 @@
+use std::{path::Path, time::Duration};
+
+use anyhow::Context;
+use console::style;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::{
+    app::api::structs::Arch,
+    functions::{load_config, save_config},
+    headless::{
+        Server,
+        rcon::Rcon,
+        snapshots::{self, save_over_rcon},
+    },
+    steps::{TICK_STRINGS, items::arch_install_folder},
+    structs::{
+        HeadlessAction, HeadlessArgs, HeadlessConfigureArgs, HeadlessRconArgs, HeadlessTarget,
+        ServerSettings,
+    },
+};
+
+/// Seconds to wait for the game to be written after /server-save
+const SAVE_TIMEOUT: Duration = Duration::from_secs(120);
+
+fn spinner(message: String) -> ProgressBar {
+    let bar = ProgressBar::new_spinner()
+        .with_style(
+            ProgressStyle::with_template("{spinner} {prefix} {wide_msg}")
+                .unwrap()
+                .tick_strings(TICK_STRINGS),
+        )
+        .with_prefix(message);
+    bar.enable_steady_tick(Duration::from_millis(100));
+    bar
+}
+
+pub(crate) async fn stop_server(folder: &Path, settings: &ServerSettings) -> anyhow::Result<()> {
+    let bar = spinner(format!(
+        "{} Stopping server...",
+        style("[server]").bold().magenta()
+    ));
+    let result = Server::new(folder, settings).stop().await;
+    bar.finish_and_clear();
+    result?;
+
+    println!("{} Server stopped", style("[server]").bold().magenta());
+    Ok(())
+}
+
+pub(crate) async fn start_server(folder: &Path, settings: &ServerSettings) -> anyhow::Result<()> {
+    let bar = spinner(format!(
+        "{} Starting server...",
+        style("[server]").bold().magenta()
+    ));
+    let result = Server::new(folder, settings).start().await;
+    bar.finish_and_clear();
+    result?;
+
+    println!(
+        "{} Server is hosting the game",
+        style("[server]").bold().magenta()
+    );
+    Ok(())
+}
+
+async fn connect(settings: &ServerSettings) -> anyhow::Result<Rcon> {
+    let address = settings
+        .rcon_address
+        .as_deref()
+        .context("RCON address isn't set (headless configure)")?;
+
+    Rcon::connect(address, settings.rcon_password.as_deref().unwrap_or("")).await
+}
+
+/// Counts down the restart in the chat and saves the game when enabled (or backed up).
+/// RCON failures never stop the update, they are reported and the server is restarted anyway.
+pub(crate) async fn warn_players(folder: &Path, settings: &ServerSettings, to: &str) {
+    if settings.rcon_address.is_none() {
+        return;
+    }
+
+    if let Err(err) = countdown(folder, settings, to).await {
+        println!(
+            "{} {}",
+            style("[server]").bold().magenta(),
+            style(format!("Restarting without warning players: {:#}", err)).yellow()
+        );
+    }
+}
+
+async fn countdown(folder: &Path, settings: &ServerSettings, to: &str) -> anyhow::Result<()> {
+    let prefix = style("[server]").bold().magenta();
+    let bar = spinner(format!("{} Connecting to RCON...", prefix));
+    let rcon = connect(settings).await;
+    bar.finish_and_clear();
+    let mut rcon = rcon?;
+
+    let mut countdown = settings.countdown.clone();
+    countdown.sort_unstable_by(|a, b| b.cmp(a));
+    countdown.dedup();
+
+    for (index, seconds) in countdown.iter().enumerate() {
+        rcon.command(&format!(
+            "Server will restart for update to {} in {} second{}",
+            to,
+            seconds,
+            if *seconds == 1 { "" } else { "s" }
+        ))
+        .await
+        .context("Unable to warn players")?;
+
+        let next = countdown.get(index + 1).copied().unwrap_or(0);
+        let bar = spinner(format!(
+            "{} Restarting for update in {} seconds...",
+            prefix, seconds
+        ));
+        tokio::time::sleep(Duration::from_secs(seconds - next)).await;
+        bar.finish_and_clear();
+    }
+
+    if settings.server_save || settings.save_backups > 0 {
+        let bar = spinner(format!("{} Saving the game...", prefix));
+        let result = save_over_rcon(folder, &mut rcon, SAVE_TIMEOUT).await;
+        bar.finish_and_clear();
+        result.context("Unable to save the game")?;
+
+        println!("{} Game saved", prefix);
+    }
+
+    rcon.command(&format!("Server is restarting for update to {}", to))
+        .await
+        .context("Unable to warn players")?;
+
+    Ok(())
+}
+
+/// Copies the newest save into save backups, when they are enabled for the installation.
+/// The server has to be stopped, or saved over RCON, so the save is consistent.
+pub(crate) async fn snapshot_saves(folder: &Path) -> anyhow::Result<()> {
+    let Some(config) = load_config(folder).await? else {
+        return Ok(());
+    };
+    let Some(settings) = config.server.filter(|settings| settings.save_backups > 0) else {
+        return Ok(());
+    };
+
+    take_snapshot(folder, &config.current_version, settings.save_backups).await
+}
+
+async fn take_snapshot(folder: &Path, version: &str, keep: usize) -> anyhow::Result<()> {
+    let prefix = style("[server]").bold().magenta();
+    let bar = spinner(format!("{} Backing up the newest save...", prefix));
+    let snapshot = snapshots::take(folder, version, keep).await;
+    bar.finish_and_clear();
+
+    match snapshot.context("Unable to back up the newest save")? {
+        Some(snapshot) => println!(
+            "{} Backed up {} to {}",
+            prefix,
+            snapshot.save.unwrap_or_default(),
+            snapshot.path.display()
+        ),
+        None => println!("{} {}", prefix, style("No saves to back up").dim()),
+    }
+
+    Ok(())
+}
+
+/// Lifecycle settings of installation, which is running and has to be restarted after update
+pub(crate) async fn running_server(folder: &Path) -> anyhow::Result<Option<ServerSettings>> {
+    let Some(settings) = load_config(folder).await?.and_then(|config| config.server) else {
+        return Ok(None);
+    };
+
+    if !Server::new(folder, &settings).is_running().await? {
+        return Ok(None);
+    }
+
+    Ok(Some(settings))
+}
+
+fn target_folder(target: &HeadlessTarget) -> std::path::PathBuf {
+    let arch: Arch = (target.version.clone(), target.platform.clone()).into();
+    arch_install_folder(&arch, target.custom_folder.as_deref())
+}
+
+async fn server_settings(folder: &Path) -> anyhow::Result<ServerSettings> {
+    load_config(folder)
+        .await?
+        .with_context(|| format!("Nothing is installed in {}", folder.display()))?
+        .server
+        .with_context(|| {
+            format!(
+                "Server of {} isn't configured, use headless configure",
+                folder.display()
+            )
+        })
+}
+
+/// Empty value removes the setting
+fn optional(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+async fn configure(args: HeadlessConfigureArgs) -> anyhow::Result<()> {
+    let folder = target_folder(&args.target);
+    let mut config = load_config(&folder)
+        .await?
+        .with_context(|| format!("Nothing is installed in {}", folder.display()))?;
+
+    let mut settings = config.server.take().unwrap_or_default();
+
+    if let Some(command) = args.start_command {
+        settings.start_command = optional(command);
+    }
+    if let Some(command) = args.stop_command {
+        settings.stop_command = optional(command);
+    }
+    if let Some(pid_file) = args.pid_file {
+        settings.pid_file = Some(pid_file).filter(|path| !path.as_os_str().is_empty());
+    }
+    if let Some(timeout) = args.graceful_timeout {
+        settings.graceful_timeout = timeout;
+    }
+    if let Some(timeout) = args.startup_timeout {
+        settings.startup_timeout = timeout;
+    }
+    if let Some(address) = args.rcon_address {
+        settings.rcon_address = optional(address);
+    }
+    if let Some(password) = args.rcon_password {
+        settings.rcon_password = optional(password);
+    }
+    if let Some(countdown) = args.countdown {
+        settings.countdown = countdown
+            .split(',')
+            .map(str::trim)
+            .filter(|seconds| !seconds.is_empty())
+            .map(|seconds| {
+                seconds
+                    .parse()
+                    .with_context(|| format!("Invalid countdown seconds {}", seconds))
+            })
+            .collect::<anyhow::Result<_>>()?;
+    }
+    if let Some(save) = args.server_save {
+        settings.server_save = save;
+    }
+    if let Some(backups) = args.save_backups {
+        settings.save_backups = backups;
+    }
+
+    config.server = Some(settings);
+    save_config(&folder, &config).await?;
+
+    println!(
+        "{}",
+        style(format!(
+            "Server settings of {} were saved",
+            folder.display()
+        ))
+        .green()
+        .bold()
+    );
+
+    Ok(())
+}
+
+async fn status(target: HeadlessTarget) -> anyhow::Result<()> {
+    let folder = target_folder(&target);
+    let settings = server_settings(&folder).await?;
+    let unset = || style("-").dim().to_string();
+
+    println!(
+        "Start command: {}",
+        settings.start_command.clone().unwrap_or_else(unset)
+    );
+    println!(
+        "Stop command: {}",
+        settings.stop_command.clone().unwrap_or_else(unset)
+    );
+    println!(
+        "PID file: {}",
+        settings
+            .pid_file
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(unset)
+    );
+    println!("Graceful timeout: {}s", settings.graceful_timeout);
+    println!("Startup timeout: {}s", settings.startup_timeout);
+    println!(
+        "RCON: {}",
+        match (&settings.rcon_address, &settings.rcon_password) {
+            (Some(address), Some(_)) => format!("{} (with password)", address),
+            (Some(address), None) => address.clone(),
+            (None, _) => unset(),
+        }
+    );
+    println!(
+        "Countdown: {}",
+        settings
+            .countdown
+            .iter()
+            .map(|seconds| format!("{}s", seconds))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    println!(
+        "Save before update: {}",
+        if settings.server_save { "yes" } else { "no" }
+    );
+
+    let stored = snapshots::list(&folder).await?;
+    if settings.save_backups > 0 {
+        println!(
+            "Save backups: {} kept, {} stored",
+            settings.save_backups,
+            stored.len()
+        );
+    } else {
+        println!("Save backups: {}", unset());
+    }
+    for snapshot in &stored {
+        println!(
+            "  {} (game {}) {}",
+            snapshot.save.as_deref().unwrap_or("-"),
+            snapshot.version,
+            style(snapshot.path.display()).dim()
+        );
+    }
+
+    if Server::new(&folder, &settings).is_running().await? {
+        println!("{}", style("Server is running").green().bold());
+    } else {
+        println!("{}", style("Server is stopped").dim());
+    }
+
+    Ok(())
+}
+
+/// Backs up the newest save now, running server is saved over RCON first
+async fn snapshot_command(target: HeadlessTarget) -> anyhow::Result<()> {
+    let folder = target_folder(&target);
+    let config = load_config(&folder)
+        .await?
+        .with_context(|| format!("Nothing is installed in {}", folder.display()))?;
+    let settings = config.server.unwrap_or_default();
+
+    if Server::new(&folder, &settings).is_running().await? {
+        if settings.rcon_address.is_none() {
+            anyhow::bail!(
+                "Server is running, set RCON address (headless configure) to save it, or stop it"
+            );
+        }
+
+        let bar = spinner(format!(
+            "{} Saving the game...",
+            style("[server]").bold().magenta()
+        ));
+        let result = async {
+            let mut rcon = connect(&settings).await?;
+            save_over_rcon(&folder, &mut rcon, SAVE_TIMEOUT).await
+        }
+        .await;
+        bar.finish_and_clear();
+        result.context("Unable to save the game")?;
+    }
+
+    //without rotation all backups are kept
+    let keep = match settings.save_backups {
+        0 => usize::MAX,
+        keep => keep,
+    };
+    take_snapshot(&folder, &config.current_version, keep).await
+}
+
+async fn rcon_command(args: HeadlessRconArgs) -> anyhow::Result<()> {
+    let folder = target_folder(&args.target);
+    let settings = server_settings(&folder).await?;
+
+    let response = connect(&settings).await?.command(&args.command).await?;
+    if !response.is_empty() {
+        println!("{}", response.trim_end());
+    }
+
+    Ok(())
+}
+
+pub async fn handle_headless(args: HeadlessArgs) -> anyhow::Result<()> {
+    match args.action {
+        HeadlessAction::Status(target) => status(target).await,
+        HeadlessAction::Configure(args) => configure(args).await,
+        HeadlessAction::Start(target) => {
+            let folder = target_folder(&target);
+            start_server(&folder, &server_settings(&folder).await?).await
+        }
+        HeadlessAction::Stop(target) => {
+            let folder = target_folder(&target);
+            let settings = server_settings(&folder).await?;
+
+            if !Server::new(&folder, &settings).is_running().await? {
+                println!("{}", style("Server isn't running.").dim());
+                return Ok(());
+            }
+
+            stop_server(&folder, &settings).await
+        }
+        HeadlessAction::Rcon(args) => rcon_command(args).await,
+        HeadlessAction::Snapshot(target) => snapshot_command(target).await,
+    }
+}
@@ -1,10 +1,13 @@
+use std::path::PathBuf;
+
 use anyhow::Context;
 use semver::Version;
 use tokio::fs;
 
 use crate::{
+    app::api::structs::{Arch, Item, LatestReleases, Updates, VersionDiff},
     functions::load_config,
-    structs::{Arch, Args, Item, Updates, VersionDiff},
+    structs::{Args, Config},
 };
 
 pub fn get_arch_folder(arch: &Arch) -> &'static str {
@@ -20,24 +23,64 @@ pub fn get_arch_folder(arch: &Arch) -> &'static str {
     }
 }
 
+pub fn find_stable(items: &[Item]) -> Option<&str> {
+    items.iter().find_map(|item| match item {
+        Item::Stable(stable) => Some(stable.stable.as_str()),
+        Item::VersionDiff(_) => None,
+    })
+}
+
+/// Walks the patches backwards from `to`, returning at most `count` of them
+/// in the order they need to be applied.
+pub fn patch_chain<'a>(items: &'a [Item], to: &str, count: usize) -> Vec<&'a VersionDiff> {
+    let mut chain = Vec::new();
+    let mut version = to;
+
+    while chain.len() < count {
+        let diff = items.iter().find_map(|item| match item {
+            Item::VersionDiff(diff) if diff.to == version => Some(diff),
+            _ => None,
+        });
+
+        match diff {
+            Some(diff) => {
+                chain.push(diff);
+                version = &diff.from;
+            }
+            None => break,
+        }
+    }
+
+    chain.reverse();
+    chain
+}
+
+/// Folder of installation selected by arguments
+pub fn install_folder(args: &Args) -> PathBuf {
+    arch_install_folder(
+        &(args.version.clone(), args.platform.clone()).into(),
+        args.custom_folder.as_deref(),
+    )
+}
+
+/// Custom folder, or the default one of the package
+pub fn arch_install_folder(arch: &Arch, custom_folder: Option<&str>) -> PathBuf {
+    match custom_folder {
+        Some(folder) => PathBuf::from(folder),
+        None => PathBuf::from(get_arch_folder(arch)),
+    }
+}
+
 pub enum UpdateType<'a> {
     FullGame(String),
     Patch(Vec<&'a VersionDiff>),
     None,
 }
 
-pub async fn resolve_updates<'a>(
-    args: &mut Args,
-    updates: &'a Updates,
-) -> anyhow::Result<UpdateType<'a>> {
-    let arch = (args.version.clone(), args.platform.clone()).into();
-    let folder = if let Some(folder) = &args.custom_folder {
-        folder.as_str()
-    } else {
-        get_arch_folder(&arch)
-    };
-
-    let base_folder = std::path::Path::new(folder);
+/// Creates installation folder and loads its config, which takes precedence over arguments
+async fn prepare_installation(args: &mut Args) -> anyhow::Result<Option<Config>> {
+    let base_folder = install_folder(args);
+    let base_folder = base_folder.as_path();
 
     if !fs::try_exists(base_folder)
         .await
@@ -54,22 +97,52 @@ pub async fn resolve_updates<'a>(
         args.platform = config.platform.clone();
     }
 
+    Ok(config)
+}
+
+/// Full package is the only update without credentials, patches need an account
+pub async fn resolve_public_update(
+    args: &mut Args,
+    releases: &LatestReleases,
+) -> anyhow::Result<UpdateType<'static>> {
+    let config = prepare_installation(args).await?;
+    let arch: Arch = (args.version.clone(), args.platform.clone()).into();
+
+    if !arch.is_public() {
+        anyhow::bail!(
+            "Only the headless server can be updated without credentials, log in first (login command)"
+        );
+    }
+
+    let stable = releases
+        .stable
+        .get(&arch)
+        .context("No stable release found for this architecture")?
+        .to_string();
+
+    if let Some(config) = config
+        && Version::parse(&config.current_version).context("Unable to parse config version")?
+            == Version::parse(&stable).context("Unable to parse stable version")?
+    {
+        return Ok(UpdateType::None);
+    }
+
+    Ok(UpdateType::FullGame(stable))
+}
+
+pub async fn resolve_updates<'a>(
+    args: &mut Args,
+    updates: &'a Updates,
+) -> anyhow::Result<UpdateType<'a>> {
+    let config = prepare_installation(args).await?;
     let arch: Arch = (args.version.clone(), args.platform.clone()).into();
 
     let items = updates
         .get(&arch)
         .context("No updates available for this architecture")?;
 
-    let stable = items
-        .iter()
-        .find(|item| matches!(item, Item::Stable(_)))
-        .and_then(|item| {
-            if let Item::Stable(stable) = item {
-                Some(stable.stable.clone())
-            } else {
-                None
-            }
-        })
+    let stable = find_stable(items)
+        .map(str::to_string)
         .context("No stable version found for full game download")?;
 
     let config = if let Some(config) = config {
@@ -87,13 +160,7 @@ pub async fn resolve_updates<'a>(
     let mut collected_updates = Vec::new();
     let mut to_walk = vec![&config.current_version];
 
-    loop {
-        let version = if let Some(version) = to_walk.pop() {
-            version
-        } else {
-            break;
-        };
-
+    while let Some(version) = to_walk.pop() {
         match items.iter().find(|item| match item {
             Item::VersionDiff(version_diff) => version_diff.from == *version,
             Item::Stable(stable) => stable.stable == *version,
@@ -0,0 +1,240 @@
+use std::{io::Write, path::Path};
+
+use anyhow::Context;
+use console::style;
+
+use crate::{
+    app::api::{Api, ApiError},
+    config::{Account, Config, player_data::PlayerData},
+    functions::create_connection,
+    steps::credentials::{config_error, read_passphrase},
+    structs::{EndpointArgs, LoginArgs},
+};
+
+pub(crate) fn prompt(message: &str) -> anyhow::Result<String> {
+    print!("{}", message);
+    std::io::stdout()
+        .flush()
+        .context("Unable to write prompt")?;
+
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .context("Unable to read input")?;
+
+    Ok(line.trim().to_string())
+}
+
+/// Stores account under profile and makes the profile active
+async fn save(config: Config, profile: Option<String>, api: &Api) -> anyhow::Result<()> {
+    let mut config = config;
+    let profile = profile.unwrap_or_else(|| config.active_profile.clone());
+
+    //new token of encrypted profile is encrypted with the same passphrase
+    let passphrase = if config.is_encrypted(&profile) {
+        println!(
+            "{}",
+            style(format!("Token of profile {} is encrypted", profile)).yellow()
+        );
+        Some(read_passphrase(false)?)
+    } else {
+        None
+    };
+
+    config
+        .set_login(profile.clone(), api.account.clone(), passphrase.as_deref())
+        .map_err(config_error)?;
+    config
+        .save()
+        .await
+        .map_err(|err| anyhow::anyhow!("Failed to save config: {:?}", err))?;
+
+    println!(
+        "{}",
+        style(format!(
+            "Logged in as {} (profile {})",
+            api.account.username, profile
+        ))
+        .green()
+        .bold()
+    );
+
+    Ok(())
+}
+
+/// Imports credentials from player-data.json, after they are validated
+async fn import_login(
+    path: Option<&Path>,
+    yes: bool,
+    config: Config,
+    profile: Option<String>,
+    endpoints: &EndpointArgs,
+) -> anyhow::Result<()> {
+    let (path, data) = PlayerData::find(path)
+        .await
+        .map_err(|err| anyhow::anyhow!("Failed to read player-data.json: {:?}", err))?
+        .context(
+            "No player-data.json with credentials found, log in to factorio.com in game first",
+        )?;
+
+    let account = data
+        .into_account()
+        .context("player-data.json doesn't contain credentials")?;
+
+    if !yes {
+        let answer = prompt(&format!(
+            "Import credentials of {} from {}? [y/N] ",
+            account.username,
+            path.display()
+        ))?;
+
+        if !answer.eq_ignore_ascii_case("y") {
+            return Ok(());
+        }
+    }
+
+    let api = Api::new(account, create_connection(&config, endpoints).await?);
+    api.check_credentials()
+        .await
+        .context("Credentials from player-data.json are not valid")?;
+
+    save(config, profile, &api).await
+}
+
+/// Logs in with password and stores the obtained token in config
+pub async fn handle_login(
+    args: LoginArgs,
+    endpoints: EndpointArgs,
+    profile: Option<String>,
+) -> anyhow::Result<()> {
+    let config = Config::load()
+        .await
+        .map_err(|err| anyhow::anyhow!("Failed to load config: {:?}", err))?
+        .unwrap_or_default();
+
+    if let Some(path) = &args.import {
+        return import_login(path.as_deref(), args.yes, config, profile, &endpoints).await;
+    }
+
+    let username = match args.username {
+        Some(username) => username,
+        None => prompt("Username: ")?,
+    };
+
+    let password = rpassword::prompt_password("Password: ").context("Unable to read password")?;
+
+    let mut api = Api::new(
+        Account::new(username, String::new()),
+        create_connection(&config, &endpoints).await?,
+    );
+
+    password_login(&mut api, &password, |message| {
+        println!("{}", style(message).yellow());
+        prompt("Email authentication code: ")
+    })
+    .await?;
+
+    save(config, profile, &api).await
+}
+
+/// Logs in with password, `ask_code` is asked for the email authentication code when needed
+async fn password_login(
+    api: &mut Api,
+    password: &str,
+    ask_code: impl FnOnce(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<()> {
+    match api.login(password, None).await {
+        Err(ApiError::EmailAuthenticationRequired(message)) => {
+            let code = ask_code(&message)?;
+
+            api.login(password, Some(&code)).await?;
+        }
+        result => result?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::server::fake::FakeServer;
+
+    async fn fake_api(username: &str) -> (FakeServer, Api, TempDir) {
+        let fixtures = TempDir::new("fixtures").unwrap();
+        std::fs::write(
+            fixtures.path().join("fake.json"),
+            r#"{"users": {
+                "player": {"password": "secret", "token": "player-token"},
+                "guarded": {"password": "secret", "token": "guarded-token", "email_code": "123456"}
+            }}"#,
+        )
+        .unwrap();
+
+        let server = FakeServer::start_local(fixtures.path()).await;
+        let api = server
+            .api(Account::new(username.to_string(), String::new()))
+            .await;
+
+        (server, api, fixtures)
+    }
+
+    #[tokio::test]
+    async fn password_login_stores_token() {
+        let (_server, mut api, _fixtures) = fake_api("player").await;
+
+        password_login(&mut api, "secret", |_| panic!("no code is needed"))
+            .await
+            .unwrap();
+
+        assert_eq!(api.account.token, "player-token");
+    }
+
+    #[tokio::test]
+    async fn email_code_is_asked_for() {
+        let (_server, mut api, _fixtures) = fake_api("guarded").await;
+        let mut asked = false;
+
+        password_login(&mut api, "secret", |_| {
+            asked = true;
+            Ok("123456".to_string())
+        })
+        .await
+        .unwrap();
+
+        assert!(asked);
+        assert_eq!(api.account.token, "guarded-token");
+    }
+
+    #[tokio::test]
+    async fn wrong_email_code_is_rejected() {
+        let (_server, mut api, _fixtures) = fake_api("guarded").await;
+
+        let err = password_login(&mut api, "secret", |_| Ok("000000".to_string()))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<ApiError>(),
+            Some(ApiError::EmailAuthenticationRequired(_))
+        ));
+        assert!(api.account.token.is_empty());
+    }
+
+    #[tokio::test]
+    async fn wrong_password_is_rejected() {
+        let (_server, mut api, _fixtures) = fake_api("player").await;
+
+        let err = password_login(&mut api, "wrong", |_| panic!("no code is needed"))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<ApiError>(),
+            Some(ApiError::InvalidCredentials(_))
+        ));
+        assert!(api.account.token.is_empty());
+    }
+}
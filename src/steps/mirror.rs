@@ -0,0 +1,289 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use console::style;
+use indicatif::MultiProgress;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    app::api::{
+        Api,
+        structs::{Arch, Item, Updates},
+    },
+    steps::{
+        items::{find_stable, get_arch_folder, patch_chain},
+        update::{UpdateInfo, download_zip, patch_file_name},
+    },
+    structs::MirrorArgs,
+};
+
+pub const INDEX_FILE: &str = "index.json";
+
+/// Generated index of a mirror folder, all paths are relative to the mirror root.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct MirrorIndex {
+    pub generated: u64,
+    pub packages: HashMap<Arch, MirrorPackage>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MirrorPackage {
+    pub stable: String,
+    pub full: MirrorFile,
+    pub patches: Vec<MirrorPatch>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MirrorFile {
+    pub version: String,
+    pub path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MirrorPatch {
+    pub from: String,
+    pub to: String,
+    pub path: PathBuf,
+}
+
+impl MirrorIndex {
+    pub async fn load(root: &Path) -> anyhow::Result<Option<Self>> {
+        let path = root.join(INDEX_FILE);
+
+        if !fs::try_exists(&path)
+            .await
+            .context("Failed to check if mirror index exists")?
+        {
+            return Ok(None);
+        }
+
+        let data = fs::read_to_string(&path)
+            .await
+            .context("Failed to read mirror index")?;
+
+        Ok(Some(
+            serde_json::from_str(&data).context("Failed to parse mirror index")?,
+        ))
+    }
+
+    pub async fn save(&self, root: &Path) -> anyhow::Result<()> {
+        let data =
+            serde_json::to_string_pretty(self).context("Failed to serialize mirror index")?;
+
+        fs::write(root.join(INDEX_FILE), data)
+            .await
+            .context("Failed to write mirror index")
+    }
+}
+
+/// Returns first completely downloaded file in folder
+pub(crate) async fn find_downloaded(folder: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let mut entries = fs::read_dir(folder)
+        .await
+        .context("Unable to read mirror folder")?;
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .context("Unable to read mirror folder")?
+    {
+        let path = entry.path();
+        if path.is_file() && path.extension().is_none_or(|ext| ext != "part") {
+            return Ok(Some(path));
+        }
+    }
+
+    Ok(None)
+}
+
+async fn mirror_package(
+    args: &MirrorArgs,
+    api: &Api,
+    mp: &MultiProgress,
+    arch: &Arch,
+    items: &[Item],
+) -> anyhow::Result<MirrorPackage> {
+    let stable = find_stable(items).context("No stable version found for package")?;
+
+    let arch_folder = Path::new(get_arch_folder(arch));
+    let full_folder = arch_folder.join("full").join(stable);
+    let patches_folder = arch_folder.join("patches");
+
+    fs::create_dir_all(args.output.join(&full_folder))
+        .await
+        .context("Unable to create mirror folder")?;
+    fs::create_dir_all(args.output.join(&patches_folder))
+        .await
+        .context("Unable to create mirror folder")?;
+
+    let full = match find_downloaded(&args.output.join(&full_folder)).await? {
+        Some(path) => path,
+        None => {
+            download_zip(
+                UpdateInfo::FullGame {
+                    url: &api.get_full_download_link(arch, stable),
+                    arch,
+                    number: stable,
+                },
+                api,
+                Some(mp),
+                Some(&args.output.join(&full_folder)),
+            )
+            .await?
+        }
+    };
+
+    let mut patches = Vec::new();
+
+    for diff in patch_chain(items, stable, args.patches) {
+        let path = patches_folder.join(patch_file_name(arch, &diff.from, &diff.to));
+
+        //download link is only needed for missing patches
+        if !fs::try_exists(args.output.join(&path))
+            .await
+            .context("Unable to check mirrored patch")?
+        {
+            let link = api.get_patch_download_link(arch, diff).await?;
+            download_zip(
+                UpdateInfo::Patch {
+                    url: &link,
+                    arch,
+                    from: &diff.from,
+                    to: &diff.to,
+                },
+                api,
+                Some(mp),
+                Some(&args.output.join(&patches_folder)),
+            )
+            .await?;
+        }
+
+        patches.push(MirrorPatch {
+            from: diff.from.clone(),
+            to: diff.to.clone(),
+            path,
+        });
+    }
+
+    prune_package(&args.output, &full_folder, &patches_folder, &patches).await?;
+
+    Ok(MirrorPackage {
+        stable: stable.to_string(),
+        full: MirrorFile {
+            version: stable.to_string(),
+            path: full_folder.join(full.file_name().context("Malformed file path")?),
+        },
+        patches,
+    })
+}
+
+/// Removes full packages of older versions and patches, which left the patch chain
+async fn prune_package(
+    root: &Path,
+    full_folder: &Path,
+    patches_folder: &Path,
+    patches: &[MirrorPatch],
+) -> anyhow::Result<()> {
+    if let Some(versions_folder) = full_folder.parent() {
+        let mut entries = fs::read_dir(root.join(versions_folder))
+            .await
+            .context("Unable to read mirror folder")?;
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .context("Unable to read mirror folder")?
+        {
+            if entry.path() != root.join(full_folder) && entry.path().is_dir() {
+                fs::remove_dir_all(entry.path())
+                    .await
+                    .with_context(|| format!("Unable to remove {}", entry.path().display()))?;
+            }
+        }
+    }
+
+    let mut entries = fs::read_dir(root.join(patches_folder))
+        .await
+        .context("Unable to read mirror folder")?;
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .context("Unable to read mirror folder")?
+    {
+        let path = patches_folder.join(entry.file_name());
+        if !patches.iter().any(|patch| patch.path == path) {
+            fs::remove_file(entry.path())
+                .await
+                .with_context(|| format!("Unable to remove {}", entry.path().display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Downloads full package + patch chain of every package in `updates` into mirror folder
+/// and regenerates its index. Packages are never extracted, so every platform can be mirrored.
+pub async fn mirror_packages(
+    args: &MirrorArgs,
+    api: &Api,
+    updates: &Updates,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(&args.output)
+        .await
+        .context("Unable to create mirror folder")?;
+
+    let mut index = MirrorIndex::load(&args.output).await?.unwrap_or_default();
+    let mp = MultiProgress::new();
+
+    //failed package doesn't stop the others, index keeps its previous entry
+    let mut failed = Vec::new();
+    for (arch, items) in updates {
+        if *arch == Arch::Other || !(args.arches.is_empty() || args.arches.contains(arch)) {
+            continue;
+        }
+
+        match mirror_package(args, api, &mp, arch, items).await {
+            Ok(package) => {
+                index.packages.insert(arch.clone(), package);
+            }
+            Err(err) => failed.push((arch, err)),
+        }
+    }
+
+    index.generated = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("System time is before UNIX epoch")?
+        .as_secs();
+    index.save(&args.output).await?;
+
+    println!(
+        "{}",
+        style(format!(
+            "Mirrored {} packages into {}",
+            index.packages.len(),
+            args.output.display()
+        ))
+        .green()
+        .bold()
+    );
+
+    for (arch, err) in &failed {
+        eprintln!(
+            "{} {:#}",
+            style(format!("Unable to mirror {}:", arch)).red().bold(),
+            err
+        );
+    }
+
+    if !failed.is_empty() {
+        anyhow::bail!("{} packages were not mirrored", failed.len());
+    }
+
+    Ok(())
+}
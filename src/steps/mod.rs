@@ -1,21 +1,108 @@
+use console::style;
+
 use crate::{
-    steps::{items::resolve_updates, update::do_update, updates::get_updates},
-    structs::Args,
+    app::api::ApiError,
+    functions::{create_api, create_api_or_anonymous, load_config, save_config},
+    steps::{
+        items::{install_folder, resolve_public_update, resolve_updates},
+        mirror::mirror_packages,
+        update::{do_update, notify_failure},
+        updates::{get_latest_releases, get_updates},
+    },
+    structs::{Args, EndpointArgs, MirrorArgs},
 };
 
-mod items;
-mod update;
-mod updates;
+pub mod changelog;
+pub mod check;
+pub mod credentials;
+pub mod headless;
+pub(crate) mod items;
+pub mod login;
+pub mod mirror;
+pub(crate) mod mod_settings;
+pub mod mods;
+pub(crate) mod patch;
+pub mod profiles;
+pub(crate) mod update;
+pub(crate) mod updates;
+pub mod webhooks;
+
+pub(crate) static TICK_STRINGS: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Prints error of CLI command, together with a hint for errors from Factorio API
+pub fn print_error(err: &anyhow::Error) {
+    eprintln!("{} {:#}", style("Error:").red().bold(), err);
+
+    if let Some(api_error) = err.chain().find_map(|err| err.downcast_ref::<ApiError>()) {
+        eprintln!("{}", style(api_error.hint()).yellow());
+    }
+}
 
-pub(crate) static TICK_STRINGS: &[&'static str] =
-    &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+/// Profile of installation, `--profile` ties the installation to another profile
+async fn installation_profile(
+    args: &Args,
+    profile: Option<String>,
+) -> anyhow::Result<Option<String>> {
+    let folder = install_folder(args);
 
-pub async fn handle_update(args: Args) -> anyhow::Result<()> {
+    let mut config = match load_config(&folder).await? {
+        Some(config) => config,
+        None => return Ok(profile),
+    };
+
+    match profile {
+        Some(profile) if config.profile.as_ref() != Some(&profile) => {
+            println!(
+                "{}",
+                style(format!("Installation is now tied to profile {}", profile)).yellow()
+            );
+
+            config.profile = Some(profile);
+            save_config(&folder, &config).await?;
+
+            Ok(config.profile)
+        }
+        Some(profile) => Ok(Some(profile)),
+        None => Ok(config.profile),
+    }
+}
+
+pub async fn handle_update(
+    args: Args,
+    endpoints: EndpointArgs,
+    profile: Option<String>,
+) -> anyhow::Result<()> {
     let mut args = args;
+    let profile = installation_profile(&args, profile).await?;
+    let api = create_api_or_anonymous(&args.credentials, &endpoints, profile.as_deref()).await?;
+
+    //failures before the update starts are reported to webhooks too
+    if api.is_anonymous() {
+        let releases = notify_failure(&args, &api, get_latest_releases(&api).await).await?;
+        let update = resolve_public_update(&mut args, &releases).await;
+        let update = notify_failure(&args, &api, update).await?;
+        do_update(&args, &api, update, None).await?;
+
+        return Ok(());
+    }
+
+    let updates = notify_failure(&args, &api, get_updates(&api).await).await?;
+    let updates = resolve_updates(&mut args, &updates).await;
+    let updates = notify_failure(&args, &api, updates).await?;
+    do_update(&args, &api, updates, profile.as_deref()).await?;
+
+    Ok(())
+}
+
+pub async fn handle_mirror(
+    args: MirrorArgs,
+    endpoints: EndpointArgs,
+    profile: Option<String>,
+) -> anyhow::Result<()> {
+    let api = create_api(&args.credentials, &endpoints, profile.as_deref()).await?;
 
-    let updates = get_updates(&args).await?;
-    let updates = resolve_updates(&mut args, &updates).await?;
-    do_update(&args, updates).await?;
+    let updates = get_updates(&api).await?;
+    mirror_packages(&args, &api, &updates).await?;
 
     Ok(())
 }
@@ -0,0 +1,180 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use console::style;
+use serde_json::Value;
+use tokio::fs;
+
+use crate::{
+    mods::settings::{MOD_SETTINGS_FILE, ModSettings},
+    steps::mods::mods_context,
+    structs::{
+        ModsExportArgs, ModsSettingsAction, ModsSettingsApplyArgs, ModsSettingsDiffArgs, ModsTarget,
+    },
+};
+
+type Values = BTreeMap<String, BTreeMap<String, Value>>;
+
+async fn load_settings(target: &ModsTarget) -> anyhow::Result<(ModSettings, PathBuf)> {
+    let context = mods_context(target).await?;
+    let path = context.mods_folder.join(MOD_SETTINGS_FILE);
+
+    if !fs::try_exists(&path)
+        .await
+        .context("Failed to check if mod settings exist")?
+    {
+        anyhow::bail!(
+            "No mod settings in {}, the game creates them on the first start with mods",
+            context.mods_folder.display()
+        );
+    }
+
+    Ok((ModSettings::load(&path).await?, path))
+}
+
+/// Values of mod-settings.dat, or of JSON exported by the export command
+async fn load_values(path: &Path) -> anyhow::Result<Values> {
+    if path.extension().is_some_and(|ext| ext == "json") {
+        let data = fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        return serde_json::from_str(&data)
+            .with_context(|| format!("Failed to parse {}", path.display()));
+    }
+
+    Ok(ModSettings::load(path).await?.values())
+}
+
+async fn show_settings(target: ModsTarget) -> anyhow::Result<()> {
+    let (settings, path) = load_settings(&target).await?;
+
+    println!(
+        "{}",
+        style(format!(
+            "{} (game {})",
+            path.display(),
+            settings.game_version()
+        ))
+        .dim()
+    );
+
+    for (scope, values) in settings.values() {
+        if values.is_empty() {
+            continue;
+        }
+
+        println!("{}", style(format!("{}:", scope)).bold().blue());
+        for (name, value) in values {
+            println!("  {} = {}", name, value);
+        }
+    }
+
+    Ok(())
+}
+
+async fn diff_settings(args: ModsSettingsDiffArgs) -> anyhow::Result<()> {
+    let (settings, _) = load_settings(&args.target).await?;
+    let ours = settings.values();
+    let theirs = load_values(&args.other).await?;
+
+    let mut differences = 0;
+
+    for scope in ours.keys().chain(theirs.keys()).collect::<BTreeSet<_>>() {
+        let empty = BTreeMap::new();
+        let ours = ours.get(scope).unwrap_or(&empty);
+        let theirs = theirs.get(scope).unwrap_or(&empty);
+
+        for name in ours.keys().chain(theirs.keys()).collect::<BTreeSet<_>>() {
+            let line = match (ours.get(name), theirs.get(name)) {
+                (Some(a), Some(b)) if a == b => continue,
+                (Some(a), Some(b)) => {
+                    format!("{} {}/{}: {} -> {}", style("~").yellow(), scope, name, a, b)
+                }
+                (Some(a), None) => format!("{} {}/{} = {}", style("-").red(), scope, name, a),
+                (None, Some(b)) => format!("{} {}/{} = {}", style("+").green(), scope, name, b),
+                (None, None) => continue,
+            };
+
+            differences += 1;
+            println!("{}", line);
+        }
+    }
+
+    if differences == 0 {
+        println!("{}", style("Mod settings are the same.").green().bold());
+    }
+
+    Ok(())
+}
+
+async fn export_settings(args: ModsExportArgs) -> anyhow::Result<()> {
+    let (settings, _) = load_settings(&args.target).await?;
+    let data = serde_json::to_string_pretty(&settings.to_json())
+        .context("Failed to serialize mod settings")?;
+
+    match args.output {
+        Some(output) => {
+            fs::write(&output, data)
+                .await
+                .with_context(|| format!("Failed to write {}", output.display()))?;
+            println!(
+                "{}",
+                style(format!("Exported mod settings to {}", output.display()))
+                    .green()
+                    .bold()
+            );
+        }
+        None => println!("{}", data),
+    }
+
+    Ok(())
+}
+
+async fn apply_settings(args: ModsSettingsApplyArgs) -> anyhow::Result<()> {
+    let (mut settings, path) = load_settings(&args.target).await?;
+
+    let data = fs::read_to_string(&args.file)
+        .await
+        .with_context(|| format!("Failed to read {}", args.file.display()))?;
+    let values: Value = serde_json::from_str(&data)
+        .with_context(|| format!("Failed to parse {}", args.file.display()))?;
+
+    let changed = settings.apply(&values)?;
+
+    if changed.is_empty() {
+        println!("{}", style("Mod settings are already set.").green().bold());
+        return Ok(());
+    }
+
+    for name in &changed {
+        println!("{} {}", style("set").blue(), name);
+    }
+
+    if args.dry_run {
+        return Ok(());
+    }
+
+    settings.save(&path).await?;
+
+    println!(
+        "{}",
+        style(format!("Changed mod settings: {}", changed.len()))
+            .green()
+            .bold()
+    );
+
+    Ok(())
+}
+
+pub(crate) async fn handle_mod_settings(action: ModsSettingsAction) -> anyhow::Result<()> {
+    match action {
+        ModsSettingsAction::Show(target) => show_settings(target).await,
+        ModsSettingsAction::Diff(args) => diff_settings(args).await,
+        ModsSettingsAction::Export(args) => export_settings(args).await,
+        ModsSettingsAction::Apply(args) => apply_settings(args).await,
+    }
+}
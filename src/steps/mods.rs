@@ -0,0 +1,1088 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use console::style;
+use indicatif::{ProgressBar, ProgressStyle};
+use tokio::fs;
+
+use crate::{
+    app::api::{Api, ApiError, portal::Release, structs::Arch},
+    functions::{create_api, load_config},
+    mods::{
+        BUILTIN_MODS, InstalledMod, ModList, ModListEntry, ModVersion,
+        dependency::{Dependency, DependencyKind},
+        file_sha1, game_version, installed_mods,
+        lock::ModLock,
+        profiles::ModProfiles,
+        resolver::{Action, Plan, Resolver, Source},
+        supports,
+    },
+    steps::{
+        TICK_STRINGS,
+        items::arch_install_folder,
+        login::prompt,
+        mod_settings::handle_mod_settings,
+        update::{UpdateInfo, download_zip},
+    },
+    structs::{
+        EndpointArgs, ModsAction, ModsArgs, ModsExportArgs, ModsInstallArgs, ModsProfileAction,
+        ModsSyncArgs, ModsTarget, ModsUpdateArgs,
+    },
+};
+
+/// Mods folder of installation, together with the game version mods have to support
+pub(crate) struct ModsContext {
+    /// Installation folder
+    pub folder: PathBuf,
+    pub mods_folder: PathBuf,
+    /// Full version (e.g. 2.0.11), missing when nothing is installed and no version was passed
+    pub factorio_version: Option<String>,
+    /// Profile the installation is tied to
+    pub profile: Option<String>,
+}
+
+impl ModsContext {
+    pub fn factorio_version(&self) -> anyhow::Result<&str> {
+        self.factorio_version.as_deref().context(
+            "Unable to detect game version, install the game first or pass --factorio-version",
+        )
+    }
+}
+
+pub(crate) async fn mods_context(target: &ModsTarget) -> anyhow::Result<ModsContext> {
+    let arch: Arch = (target.version.clone(), target.platform.clone()).into();
+    let folder = arch_install_folder(&arch, target.custom_folder.as_deref());
+    let installed = load_config(&folder).await?;
+
+    let factorio_version = match &target.factorio_version {
+        Some(version) => {
+            game_version(version)
+                .with_context(|| format!("Invalid --factorio-version {}", version))?;
+            Some(version.clone())
+        }
+        None => installed
+            .as_ref()
+            .map(|installed| installed.current_version.clone()),
+    };
+
+    Ok(ModsContext {
+        mods_folder: target
+            .mods_folder
+            .clone()
+            .unwrap_or_else(|| folder.join("mods")),
+        folder,
+        factorio_version,
+        profile: installed.and_then(|installed| installed.profile),
+    })
+}
+
+/// Installed versions of each mod, from the oldest
+pub(crate) fn group_by_name(mods: Vec<InstalledMod>) -> BTreeMap<String, Vec<InstalledMod>> {
+    let mut grouped: BTreeMap<String, Vec<InstalledMod>> = BTreeMap::new();
+
+    for installed in mods {
+        grouped
+            .entry(installed.info.name.clone())
+            .or_default()
+            .push(installed);
+    }
+
+    grouped
+}
+
+async fn list_mods(target: ModsTarget) -> anyhow::Result<()> {
+    let context = mods_context(&target).await?;
+    let mod_list = ModList::load(&context.mods_folder)
+        .await?
+        .unwrap_or_default();
+    let mods = installed_mods(&context.mods_folder).await?;
+
+    if mods.is_empty() {
+        println!(
+            "{}",
+            style(format!("No mods in {}", context.mods_folder.display())).dim()
+        );
+    }
+
+    for installed in &mods {
+        let info = &installed.info;
+
+        let mut line = format!("{} {}", style(&info.name).bold(), info.version);
+        if !mod_list.is_enabled(&info.name) {
+            line.push_str(&format!(" {}", style("(disabled)").dim()));
+        }
+
+        if let Some(game) = context.factorio_version.as_deref().and_then(game_version)
+            && !supports(&info.factorio_version, &game)
+        {
+            line.push_str(&format!(
+                " {}",
+                style(format!("(for {}, game is {})", info.factorio_version, game)).red()
+            ));
+        }
+
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+/// Enabled mod, which the new game version won't load
+struct IncompatibleMod {
+    name: String,
+    version: String,
+    factorio_version: String,
+    /// Newest portal release for the new game version
+    portal: Result<Option<String>, ApiError>,
+}
+
+/// Enabled mods, that don't support game of `target` major.minor, with their portal releases for it
+async fn incompatible_mods(
+    api: &Api,
+    mods_folder: &Path,
+    target: &str,
+) -> anyhow::Result<Vec<IncompatibleMod>> {
+    if !fs::try_exists(mods_folder)
+        .await
+        .context("Failed to check if mods folder exists")?
+    {
+        return Ok(Vec::new());
+    }
+
+    let mod_list = ModList::load(mods_folder).await?.unwrap_or_default();
+    let mut incompatible = Vec::new();
+
+    //game loads only the newest version of each mod
+    for (name, versions) in group_by_name(installed_mods(mods_folder).await?) {
+        let Some(installed) = versions.last() else {
+            continue;
+        };
+
+        if BUILTIN_MODS.contains(&name.as_str())
+            || !mod_list.is_enabled(&name)
+            || supports(&installed.info.factorio_version, target)
+        {
+            continue;
+        }
+
+        let portal = api.get_mod(&name).await.map(|portal_mod| {
+            portal_mod
+                .releases
+                .iter()
+                .filter(|release| supports(&release.info_json.factorio_version, target))
+                .filter_map(|release| ModVersion::parse(&release.version))
+                .max()
+                .map(|version| version.to_string())
+        });
+
+        incompatible.push(IncompatibleMod {
+            name,
+            version: installed.info.version.clone(),
+            factorio_version: installed.info.factorio_version.clone(),
+            portal,
+        });
+    }
+
+    Ok(incompatible)
+}
+
+/// Reports enabled mods, that won't load after updating from `from` to `to`, when the major.minor changes.
+/// Returns whether to continue with the update.
+pub(crate) async fn confirm_mod_compatibility(
+    api: &Api,
+    mods_folder: &Path,
+    from: &str,
+    to: &str,
+    yes: bool,
+) -> anyhow::Result<bool> {
+    let (Some(from_game), Some(to_game)) = (game_version(from), game_version(to)) else {
+        return Ok(true);
+    };
+    if from_game == to_game {
+        return Ok(true);
+    }
+
+    let bar = ProgressBar::new_spinner()
+        .with_style(
+            ProgressStyle::with_template("{spinner} {prefix} {wide_msg}")
+                .unwrap()
+                .tick_strings(TICK_STRINGS),
+        )
+        .with_prefix(format!("Checking enabled mods for {}...", to_game));
+    bar.enable_steady_tick(Duration::from_millis(100));
+
+    let incompatible = incompatible_mods(api, mods_folder, &to_game).await;
+
+    bar.finish_and_clear();
+    let incompatible = incompatible?;
+
+    if incompatible.is_empty() {
+        return Ok(true);
+    }
+
+    println!(
+        "{}",
+        style(format!(
+            "Enabled mods not supporting {} (updating from {}):",
+            to_game, from_game
+        ))
+        .yellow()
+        .bold()
+    );
+
+    for incompatible in &incompatible {
+        let portal = match &incompatible.portal {
+            Ok(Some(version)) => style(format!("{} is on the mod portal", version)).green(),
+            Ok(None) => style(format!("no release for {} yet", to_game)).red(),
+            Err(ApiError::NotFound(_)) => style("not on the mod portal".to_string()).red(),
+            Err(err) => style(format!("unable to check the mod portal: {}", err)).red(),
+        };
+
+        println!(
+            "{} {} {} - {}",
+            style(&incompatible.name).bold(),
+            incompatible.version,
+            style(format!("(for {})", incompatible.factorio_version)).dim(),
+            portal
+        );
+    }
+
+    if yes {
+        return Ok(true);
+    }
+
+    let answer = prompt("Continue with the update? [y/N] ")?;
+    Ok(answer.eq_ignore_ascii_case("y"))
+}
+
+fn print_plan(plan: &Plan) {
+    if !plan.unavailable.is_empty() {
+        println!(
+            "{}",
+            style(format!(
+                "Not on the mod portal: {}",
+                plan.unavailable.join(", ")
+            ))
+            .yellow()
+        );
+    }
+
+    for planned in plan.changes() {
+        let needed_by = planned
+            .required_by
+            .iter()
+            .filter(|by| *by != "requested")
+            .cloned()
+            .collect::<Vec<_>>();
+        let needed_by = if needed_by.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " {}",
+                style(format!("(needed by {})", needed_by.join(", "))).dim()
+            )
+        };
+
+        let change = match &planned.action {
+            Action::Install => format!("{} {}", style("install").green(), planned.version),
+            Action::Update { from } => {
+                format!("{} {} -> {}", style("update").blue(), from, planned.version)
+            }
+            Action::Enable => format!("{} {}", style("enable").cyan(), planned.version),
+            Action::Keep => continue,
+        };
+
+        println!("{} {}{}", style(&planned.name).bold(), change, needed_by);
+    }
+
+    for conflict in &plan.conflicts {
+        println!("{} {}", style("Conflict:").red().bold(), conflict);
+    }
+}
+
+/// Checks downloaded mod against checksum of the release, the file is removed when it differs
+async fn verify_download(path: &Path, release: &Release) -> anyhow::Result<()> {
+    let Some(expected) = &release.sha1 else {
+        log::warn!(
+            "Release {} has no checksum, it isn't verified",
+            release.file_name
+        );
+        return Ok(());
+    };
+
+    let file = path.to_path_buf();
+    let actual = tokio::task::spawn_blocking(move || file_sha1(&file))
+        .await
+        .context("Verification was interrupted")??;
+
+    if !actual.eq_ignore_ascii_case(expected) {
+        fs::remove_file(path)
+            .await
+            .with_context(|| format!("Unable to remove {}", path.display()))?;
+        anyhow::bail!(
+            "Downloaded {} is corrupted (SHA-1 {}, expected {})",
+            release.file_name,
+            actual,
+            expected
+        );
+    }
+
+    Ok(())
+}
+
+/// Downloads new releases, removes replaced zips and enables planned mods in mod-list.json
+async fn apply_plan(api: &Api, mods_folder: &Path, plan: &Plan) -> anyhow::Result<()> {
+    let installed = group_by_name(installed_mods(mods_folder).await?);
+
+    for planned in plan.changes() {
+        let Source::Portal(release) = &planned.source else {
+            continue;
+        };
+
+        let version = planned.version.to_string();
+        let path = download_zip(
+            UpdateInfo::Mod {
+                url: &api.get_mod_download_link(release),
+                name: &planned.name,
+                version: &version,
+            },
+            api,
+            None,
+            Some(mods_folder),
+        )
+        .await?;
+
+        //corrupted download must not replace a working version
+        verify_download(&path, release).await?;
+
+        //game loads the newest version, but old zips would just take space
+        for old in installed
+            .get(&planned.name)
+            .into_iter()
+            .flatten()
+            .filter(|old| old.path != path)
+        {
+            fs::remove_file(&old.path)
+                .await
+                .with_context(|| format!("Unable to remove {}", old.path.display()))?;
+        }
+
+        println!(
+            "{} {} {} {}",
+            style("[3/3]").bold().blue(),
+            match planned.action {
+                Action::Install => "Installed",
+                _ => "Updated",
+            },
+            planned.name,
+            planned.version
+        );
+    }
+
+    let mut mod_list = ModList::load(mods_folder)
+        .await?
+        .unwrap_or_else(|| ModList {
+            mods: vec![ModListEntry {
+                name: "base".to_string(),
+                enabled: true,
+                extra: Default::default(),
+            }],
+        });
+
+    for planned in &plan.mods {
+        match mod_list
+            .mods
+            .iter_mut()
+            .find(|entry| entry.name == planned.name)
+        {
+            Some(entry) => entry.enabled = true,
+            None => mod_list.mods.push(ModListEntry {
+                name: planned.name.clone(),
+                enabled: true,
+                extra: Default::default(),
+            }),
+        }
+    }
+
+    mod_list.save(mods_folder).await
+}
+
+/// Resolves requests into plan, prints it and applies it unless `dry_run` is set
+async fn resolve_and_apply(
+    api: &Api,
+    context: &ModsContext,
+    requests: &[Dependency],
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let installed = installed_mods(&context.mods_folder).await?;
+    let mod_list = ModList::load(&context.mods_folder)
+        .await?
+        .unwrap_or_default();
+
+    let bar = ProgressBar::new_spinner()
+        .with_style(
+            ProgressStyle::with_template("{spinner} {prefix} {wide_msg}")
+                .unwrap()
+                .tick_strings(TICK_STRINGS),
+        )
+        .with_prefix(format!(
+            "{} Resolving mods on the mod portal...",
+            style("[1/3]").bold().blue(),
+        ));
+    bar.enable_steady_tick(Duration::from_millis(100));
+
+    let plan = Resolver::new(api, context.factorio_version()?, installed, &mod_list)?
+        .resolve(requests)
+        .await;
+
+    bar.finish_and_clear();
+    let plan = plan?;
+
+    print_plan(&plan);
+
+    if !plan.conflicts.is_empty() {
+        anyhow::bail!("Mods can't be enabled together, resolve the conflicts above first");
+    }
+
+    if plan.changes().next().is_none() {
+        println!("{}", style("All mods are up to date.").green().bold());
+        return Ok(());
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+
+    apply_plan(api, &context.mods_folder, &plan).await?;
+
+    println!(
+        "{}",
+        style(format!("Changed mods: {}", plan.changes().count()))
+            .green()
+            .bold()
+    );
+
+    Ok(())
+}
+
+async fn update_mods(
+    args: ModsUpdateArgs,
+    endpoints: EndpointArgs,
+    profile: Option<String>,
+) -> anyhow::Result<()> {
+    let context = mods_context(&args.target).await?;
+    let api = create_api(
+        &args.credentials,
+        &endpoints,
+        profile.or(context.profile.clone()).as_deref(),
+    )
+    .await?;
+
+    let names = if args.names.is_empty() {
+        let mod_list = ModList::load(&context.mods_folder)
+            .await?
+            .unwrap_or_default();
+
+        group_by_name(installed_mods(&context.mods_folder).await?)
+            .into_keys()
+            .filter(|name| !BUILTIN_MODS.contains(&name.as_str()) && mod_list.is_enabled(name))
+            .collect()
+    } else {
+        args.names
+    };
+
+    let requests = names
+        .into_iter()
+        .map(|name| Dependency {
+            kind: DependencyKind::Required,
+            name,
+            constraint: None,
+        })
+        .collect::<Vec<_>>();
+
+    resolve_and_apply(&api, &context, &requests, args.dry_run).await
+}
+
+async fn install_mods(
+    args: ModsInstallArgs,
+    endpoints: EndpointArgs,
+    profile: Option<String>,
+) -> anyhow::Result<()> {
+    let context = mods_context(&args.target).await?;
+    let api = create_api(
+        &args.credentials,
+        &endpoints,
+        profile.or(context.profile.clone()).as_deref(),
+    )
+    .await?;
+
+    let requests = args
+        .mods
+        .iter()
+        .map(|spec| match Dependency::parse(spec) {
+            Some(request) if request.kind == DependencyKind::Required => Ok(request),
+            _ => Err(anyhow::anyhow!(
+                "Invalid mod {}, use name with optional version constraint (e.g. \"name >= 1.2.0\")",
+                spec
+            )),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    resolve_and_apply(&api, &context, &requests, args.dry_run).await
+}
+
+async fn export_mods(args: ModsExportArgs) -> anyhow::Result<()> {
+    let context = mods_context(&args.target).await?;
+    let mod_list = ModList::load(&context.mods_folder)
+        .await?
+        .unwrap_or_default();
+    let installed = installed_mods(&context.mods_folder).await?;
+
+    let lock = ModLock::new(context.factorio_version.clone(), &mod_list, &installed);
+    let data = lock.to_json()?;
+
+    match args.output {
+        Some(output) => {
+            fs::write(&output, data)
+                .await
+                .with_context(|| format!("Failed to write {}", output.display()))?;
+            println!(
+                "{}",
+                style(format!(
+                    "Exported {} mods to {}",
+                    lock.mods.len(),
+                    output.display()
+                ))
+                .green()
+                .bold()
+            );
+        }
+        None => println!("{}", data),
+    }
+
+    Ok(())
+}
+
+/// Change of a mod, that makes installation match the lockfile
+enum SyncChange {
+    /// Locked version isn't installed, other installed versions are removed
+    Download {
+        version: String,
+        replaces: Vec<InstalledMod>,
+    },
+    /// Mod isn't in the lockfile, or other version than the locked one
+    Remove(InstalledMod),
+    Enable,
+    Disable,
+}
+
+/// Changes per mod, mod-list.json is rewritten separately
+fn sync_changes(
+    lock: &ModLock,
+    mod_list: &ModList,
+    installed: BTreeMap<String, Vec<InstalledMod>>,
+) -> Vec<(String, SyncChange)> {
+    let mut changes = Vec::new();
+    let mut installed = installed;
+
+    for locked in &lock.mods {
+        let versions = installed.remove(&locked.name).unwrap_or_default();
+
+        if let Some(version) = &locked.version {
+            let (kept, other): (Vec<_>, Vec<_>) = versions.into_iter().partition(|installed| {
+                ModVersion::parse(&installed.info.version) == ModVersion::parse(version)
+            });
+
+            if kept.is_empty() {
+                changes.push((
+                    locked.name.clone(),
+                    SyncChange::Download {
+                        version: version.clone(),
+                        replaces: other,
+                    },
+                ));
+            } else {
+                changes.extend(
+                    other
+                        .into_iter()
+                        .map(|old| (locked.name.clone(), SyncChange::Remove(old))),
+                );
+            }
+        }
+
+        //builtin mods missing in mod-list.json are enabled by the game
+        match (mod_list.is_enabled(&locked.name), locked.enabled) {
+            (false, true) => changes.push((locked.name.clone(), SyncChange::Enable)),
+            (true, false) => changes.push((locked.name.clone(), SyncChange::Disable)),
+            _ => {}
+        }
+    }
+
+    for (name, versions) in installed {
+        if BUILTIN_MODS.contains(&name.as_str()) {
+            continue;
+        }
+
+        changes.extend(
+            versions
+                .into_iter()
+                .map(|old| (name.clone(), SyncChange::Remove(old))),
+        );
+    }
+
+    changes
+}
+
+fn print_sync_change(name: &str, change: &SyncChange) {
+    let change = match change {
+        SyncChange::Download { version, replaces } => match replaces.last() {
+            Some(old) if ModVersion::parse(&old.info.version) > ModVersion::parse(version) => {
+                format!(
+                    "{} {} -> {}",
+                    style("downgrade").yellow(),
+                    old.info.version,
+                    version
+                )
+            }
+            Some(old) => format!(
+                "{} {} -> {}",
+                style("update").blue(),
+                old.info.version,
+                version
+            ),
+            None => format!("{} {}", style("install").green(), version),
+        },
+        SyncChange::Remove(old) => format!("{} {}", style("remove").red(), old.info.version),
+        SyncChange::Enable => style("enable").cyan().to_string(),
+        SyncChange::Disable => style("disable").dim().to_string(),
+    };
+
+    println!("{} {}", style(name).bold(), change);
+}
+
+/// Finds portal releases of locked versions, before anything in the mods folder is changed
+async fn find_sync_releases(
+    api: &Api,
+    changes: &[(String, SyncChange)],
+) -> anyhow::Result<BTreeMap<String, Release>> {
+    let bar = ProgressBar::new_spinner()
+        .with_style(
+            ProgressStyle::with_template("{spinner} {prefix} {wide_msg}")
+                .unwrap()
+                .tick_strings(TICK_STRINGS),
+        )
+        .with_prefix(format!(
+            "{} Looking up mods on the mod portal...",
+            style("[1/3]").bold().blue(),
+        ));
+    bar.enable_steady_tick(Duration::from_millis(100));
+
+    let mut releases = BTreeMap::new();
+    let mut missing = Vec::new();
+
+    for (name, change) in changes {
+        let SyncChange::Download { version, .. } = change else {
+            continue;
+        };
+
+        let release = match api.get_mod(name).await {
+            Ok(portal_mod) => portal_mod
+                .releases
+                .into_iter()
+                .find(|release| ModVersion::parse(&release.version) == ModVersion::parse(version)),
+            Err(ApiError::NotFound(_)) => None,
+            Err(err) => {
+                bar.finish_and_clear();
+                return Err(err).with_context(|| format!("Unable to look up {}", name));
+            }
+        };
+
+        match release {
+            Some(release) => {
+                releases.insert(name.clone(), release);
+            }
+            None => missing.push(format!("{} {}", name, version)),
+        }
+    }
+
+    bar.finish_and_clear();
+
+    if !missing.is_empty() {
+        anyhow::bail!("Not on the mod portal: {}", missing.join(", "));
+    }
+
+    Ok(releases)
+}
+
+async fn sync_mods(
+    args: ModsSyncArgs,
+    endpoints: EndpointArgs,
+    profile: Option<String>,
+) -> anyhow::Result<()> {
+    let context = mods_context(&args.target).await?;
+    let lock = ModLock::load(&args.lockfile).await?;
+
+    if let Some(locked) = lock.factorio_version.as_deref().and_then(game_version)
+        && let Some(game) = context.factorio_version.as_deref().and_then(game_version)
+        && locked != game
+    {
+        println!(
+            "{}",
+            style(format!(
+                "Lockfile was exported from game {}, this installation is {}",
+                locked, game
+            ))
+            .yellow()
+        );
+    }
+
+    fs::create_dir_all(&context.mods_folder)
+        .await
+        .context("Failed to create mods folder")?;
+
+    let mod_list = ModList::load(&context.mods_folder)
+        .await?
+        .unwrap_or_default();
+    let changes = sync_changes(
+        &lock,
+        &mod_list,
+        group_by_name(installed_mods(&context.mods_folder).await?),
+    );
+
+    if changes.is_empty() {
+        println!(
+            "{}",
+            style("Mods already match the lockfile.").green().bold()
+        );
+        return Ok(());
+    }
+
+    for (name, change) in &changes {
+        print_sync_change(name, change);
+    }
+
+    if args.dry_run {
+        return Ok(());
+    }
+
+    if changes
+        .iter()
+        .any(|(_, change)| matches!(change, SyncChange::Download { .. }))
+    {
+        let api = create_api(
+            &args.credentials,
+            &endpoints,
+            profile.or(context.profile.clone()).as_deref(),
+        )
+        .await?;
+        let releases = find_sync_releases(&api, &changes).await?;
+
+        for (name, change) in &changes {
+            let SyncChange::Download { version, replaces } = change else {
+                continue;
+            };
+
+            let path = download_zip(
+                UpdateInfo::Mod {
+                    url: &api.get_mod_download_link(&releases[name]),
+                    name,
+                    version,
+                },
+                &api,
+                None,
+                Some(&context.mods_folder),
+            )
+            .await?;
+            verify_download(&path, &releases[name]).await?;
+
+            for old in replaces {
+                fs::remove_file(&old.path)
+                    .await
+                    .with_context(|| format!("Unable to remove {}", old.path.display()))?;
+            }
+
+            println!(
+                "{} Installed {} {}",
+                style("[3/3]").bold().blue(),
+                name,
+                version
+            );
+        }
+    }
+
+    for (_, change) in &changes {
+        if let SyncChange::Remove(old) = change {
+            fs::remove_file(&old.path)
+                .await
+                .with_context(|| format!("Unable to remove {}", old.path.display()))?;
+        }
+    }
+
+    //mods, which were removed, are dropped from the list too
+    let mut mod_list = mod_list;
+    mod_list.mods.retain(|entry| {
+        BUILTIN_MODS.contains(&entry.name.as_str()) || lock.get(&entry.name).is_some()
+    });
+
+    for locked in &lock.mods {
+        match mod_list
+            .mods
+            .iter_mut()
+            .find(|entry| entry.name == locked.name)
+        {
+            Some(entry) => entry.enabled = locked.enabled,
+            None => mod_list.mods.push(ModListEntry {
+                name: locked.name.clone(),
+                enabled: locked.enabled,
+                extra: Default::default(),
+            }),
+        }
+    }
+
+    mod_list.save(&context.mods_folder).await?;
+
+    println!(
+        "{}",
+        style(format!("Mods match {}", args.lockfile.display()))
+            .green()
+            .bold()
+    );
+
+    Ok(())
+}
+
+async fn mod_profiles(action: ModsProfileAction) -> anyhow::Result<()> {
+    let target = match &action {
+        ModsProfileAction::List(target) => target,
+        ModsProfileAction::Save(args)
+        | ModsProfileAction::Activate(args)
+        | ModsProfileAction::Remove(args) => &args.target,
+    };
+    let context = mods_context(target).await?;
+    let profiles = ModProfiles::new(&context.folder)?;
+
+    match action {
+        ModsProfileAction::List(_) => {
+            let names = profiles.list().await?;
+            let active = profiles.active().await?;
+
+            if names.is_empty() {
+                println!(
+                    "{}",
+                    style("No mod profiles, save one with mods profile save").dim()
+                );
+            }
+
+            for name in names {
+                let profile = profiles.load(&name).await?;
+                let mut line = format!(
+                    "{} {}",
+                    style(&name).bold(),
+                    style(format!("({} mods)", profile.files.len())).dim()
+                );
+                if active.as_deref() == Some(name.as_str()) {
+                    line.push_str(&format!(" {}", style("active").green()));
+                }
+
+                println!("{}", line);
+            }
+        }
+        ModsProfileAction::Save(args) => {
+            let profile = profiles.save(&args.name, &context.mods_folder).await?;
+            println!(
+                "{}",
+                style(format!(
+                    "Saved profile {} with {} mods",
+                    args.name,
+                    profile.files.len()
+                ))
+                .green()
+                .bold()
+            );
+        }
+        ModsProfileAction::Activate(args) => {
+            let profile = profiles.activate(&args.name, &context.mods_folder).await?;
+            println!(
+                "{}",
+                style(format!(
+                    "Activated profile {} with {} mods",
+                    args.name,
+                    profile.files.len()
+                ))
+                .green()
+                .bold()
+            );
+        }
+        ModsProfileAction::Remove(args) => {
+            profiles.remove(&args.name).await?;
+            println!(
+                "{}",
+                style(format!("Removed profile {}", args.name))
+                    .green()
+                    .bold()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_mods(
+    args: ModsArgs,
+    endpoints: EndpointArgs,
+    profile: Option<String>,
+) -> anyhow::Result<()> {
+    match args.action {
+        ModsAction::List(target) => list_mods(target).await,
+        ModsAction::Update(args) => update_mods(args, endpoints, profile).await,
+        ModsAction::Install(args) => install_mods(args, endpoints, profile).await,
+        ModsAction::Export(args) => export_mods(args).await,
+        ModsAction::Sync(args) => sync_mods(args, endpoints, profile).await,
+        ModsAction::Profile(args) => mod_profiles(args.action).await,
+        ModsAction::Settings(args) => handle_mod_settings(args.action).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use serde_json::json;
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::{config::Account, server::fake::FakeServer};
+
+    fn info(name: &str, version: &str) -> String {
+        json!({"name": name, "version": version, "factorio_version": "2.0"}).to_string()
+    }
+
+    fn release(fixtures: &Path, name: &str, version: &str) {
+        let folder = fixtures.join("mods").join(name).join(version);
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join("info.json"), info(name, version)).unwrap();
+    }
+
+    /// Mod zip in the mods folder, like downloaded from the portal
+    fn install(mods_folder: &Path, name: &str, version: &str) -> PathBuf {
+        let path = mods_folder.join(format!("{}_{}.zip", name, version));
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        writer
+            .start_file(
+                format!("{}_{}/info.json", name, version),
+                zip::write::SimpleFileOptions::default(),
+            )
+            .unwrap();
+        writer.write_all(info(name, version).as_bytes()).unwrap();
+        writer.finish().unwrap();
+
+        path
+    }
+
+    async fn update_plan(api: &Api, mods_folder: &Path, name: &str) -> Plan {
+        Resolver::new(
+            api,
+            "2.0.10",
+            installed_mods(mods_folder).await.unwrap(),
+            &ModList::default(),
+        )
+        .unwrap()
+        .resolve(&[Dependency::parse(name).unwrap()])
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn portal_releases_have_checksums_of_downloads() {
+        let fixtures = TempDir::new("fixtures").unwrap();
+        release(fixtures.path(), "a", "1.0.0");
+        release(fixtures.path(), "a", "1.1.0");
+        let downloads = TempDir::new("downloads").unwrap();
+
+        let server = FakeServer::start_local(fixtures.path()).await;
+        let api = server.api(Account::new(String::new(), String::new())).await;
+
+        let portal = api.get_mod("a").await.unwrap();
+        assert_eq!(portal.name, "a");
+        assert_eq!(
+            portal
+                .releases
+                .iter()
+                .map(|release| release.version.as_str())
+                .collect::<Vec<_>>(),
+            ["1.0.0", "1.1.0"]
+        );
+        assert!(matches!(
+            api.get_mod("missing").await,
+            Err(ApiError::NotFound(_))
+        ));
+
+        let release = &portal.releases[1];
+        let path = download_zip(
+            UpdateInfo::Mod {
+                url: &api.get_mod_download_link(release),
+                name: "a",
+                version: &release.version,
+            },
+            &api,
+            None,
+            Some(downloads.path()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(crate::mods::read_zip_info(&path).unwrap().version, "1.1.0");
+        assert_eq!(Some(file_sha1(&path).unwrap()), release.sha1);
+    }
+
+    #[tokio::test]
+    async fn apply_plan_replaces_old_version() {
+        let fixtures = TempDir::new("fixtures").unwrap();
+        release(fixtures.path(), "a", "1.0.0");
+        release(fixtures.path(), "a", "1.1.0");
+        let mods_folder = TempDir::new("mods").unwrap();
+        let old = install(mods_folder.path(), "a", "1.0.0");
+
+        let server = FakeServer::start_local(fixtures.path()).await;
+        let api = server.api(Account::new(String::new(), String::new())).await;
+        let plan = update_plan(&api, mods_folder.path(), "a").await;
+
+        apply_plan(&api, mods_folder.path(), &plan).await.unwrap();
+
+        assert!(!old.exists());
+        assert!(mods_folder.path().join("a_1.1.0.zip").exists());
+        let mod_list = ModList::load(mods_folder.path()).await.unwrap().unwrap();
+        assert!(mod_list.is_enabled("a"));
+        assert!(mod_list.mods.iter().any(|entry| entry.name == "base"));
+    }
+
+    #[tokio::test]
+    async fn corrupted_download_keeps_old_version() {
+        let fixtures = TempDir::new("fixtures").unwrap();
+        release(fixtures.path(), "a", "1.0.0");
+        release(fixtures.path(), "a", "1.1.0");
+        let mods_folder = TempDir::new("mods").unwrap();
+        let old = install(mods_folder.path(), "a", "1.0.0");
+
+        let server = FakeServer::start_local(fixtures.path()).await;
+        let api = server.api(Account::new(String::new(), String::new())).await;
+        let mut plan = update_plan(&api, mods_folder.path(), "a").await;
+        for planned in &mut plan.mods {
+            if let Source::Portal(release) = &mut planned.source {
+                release.sha1 = Some("0".repeat(40));
+            }
+        }
+
+        let err = apply_plan(&api, mods_folder.path(), &plan)
+            .await
+            .unwrap_err();
+
+        assert!(format!("{:#}", err).contains("corrupted"));
+        assert!(old.exists());
+        assert!(!mods_folder.path().join("a_1.1.0.zip").exists());
+    }
+}
@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use tokio::process::Command;
+
+/// Game binaries, one of which is in every installation
+const GAME_BINARIES: &[&str] = &[
+    "bin/x64/factorio",
+    "bin/x64/factorio.exe",
+    "factorio.app/Contents/MacOS/factorio",
+];
+
+/// Game binary of installation
+pub fn game_binary(folder: &Path) -> Option<PathBuf> {
+    GAME_BINARIES
+        .iter()
+        .map(|binary| folder.join(binary))
+        .find(|binary| binary.is_file())
+}
+
+/// Applies patch package to installation with the game itself (`--apply-update`), as official
+/// packages contain binary diffs, which only the game knows how to apply
+pub async fn apply_patch(path: &Path, target: &Path) -> anyhow::Result<()> {
+    let binary = game_binary(target)
+        .with_context(|| format!("No game binary found in {}", target.display()))?;
+
+    let output = Command::new(&binary)
+        .arg("--apply-update")
+        .arg(path)
+        .current_dir(target)
+        .output()
+        .await
+        .with_context(|| format!("Unable to run {}", binary.display()))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Game was unable to apply {} ({}): {}",
+            path.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .chain(String::from_utf8_lossy(&output.stderr).lines())
+                .last()
+                .unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use anyhow::Context;
+use console::style;
+
+use crate::{
+    config::Config,
+    steps::credentials::config_error,
+    structs::{ProfilesAction, ProfilesArgs},
+};
+
+pub async fn handle_profiles(args: ProfilesArgs) -> anyhow::Result<()> {
+    let mut config = Config::load()
+        .await
+        .map_err(config_error)?
+        .context("No profiles are stored, log in first")?;
+
+    match args.action.unwrap_or(ProfilesAction::List) {
+        ProfilesAction::List => {
+            for (name, account) in &config.profiles {
+                let active = if *name == config.active_profile {
+                    style("*").green().bold()
+                } else {
+                    style(" ")
+                };
+                let locked = if account.encrypted_token.is_some() {
+                    " (encrypted)"
+                } else {
+                    ""
+                };
+
+                println!(
+                    "{} {} - {}{}",
+                    active,
+                    style(name).bold(),
+                    account.username,
+                    locked
+                );
+            }
+
+            return Ok(());
+        }
+        ProfilesAction::Use { name } => {
+            if !config.profiles.contains_key(&name) {
+                anyhow::bail!("Profile {} doesn't exist", name);
+            }
+
+            config.active_profile = name;
+        }
+        ProfilesAction::Remove { name } => {
+            let was_active = name == config.active_profile;
+            if config.remove_profile(&name).is_none() {
+                anyhow::bail!("Profile {} doesn't exist", name);
+            }
+
+            if was_active {
+                println!(
+                    "Active profile is now {}",
+                    style(&config.active_profile).bold()
+                );
+            }
+        }
+    }
+
+    config.save().await.map_err(config_error)?;
+
+    println!("{}", style("Profiles were saved.").green().bold());
+
+    Ok(())
+}
@@ -1,92 +1,91 @@
 use std::{
-    env::temp_dir,
-    os::unix::process,
-    path::{Path, PathBuf},
-    time::{Duration, Instant},
+    path::{Component, Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::Context;
 use console::style;
 use futures_util::{StreamExt, future::join_all};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use serde_json::json;
 use tempdir::TempDir;
 use tokio::{fs, io::AsyncWriteExt};
 use uuid::Uuid;
-use zip::read::root_dir_common_filter;
 
 use crate::{
-    functions::get_base_query_params,
-    steps::{TICK_STRINGS, items::UpdateType},
-    structs::{Arch, Args, Item, Version, VersionDiff},
+    app::api::{
+        Api,
+        structs::{Arch, VersionDiff},
+    },
+    config,
+    functions::{load_config, save_config},
+    hooks::{HookRunner, Hooks, Stage},
+    steps::{
+        TICK_STRINGS,
+        headless::{running_server, snapshot_saves, start_server, stop_server, warn_players},
+        items::{UpdateType, get_arch_folder, install_folder},
+        mirror::find_downloaded,
+        mods::confirm_mod_compatibility,
+        patch::apply_patch,
+    },
+    structs::{Args, Config},
+    webhooks::{Event, EventKind, Notifier},
 };
 
-fn get_download_links(arch: &Arch, version: &str) -> String {
-    let base_url = "https://factorio.com/get-download";
-    match arch {
-        Arch::CoreLinux64 => format!("{base_url}/{version}/alpha/linux64"),
-        Arch::CoreLinuxHeadless64 => format!("{base_url}/{version}/headless/linux64"),
-        Arch::CoreExpansionLinux64 => format!("{base_url}/{version}/expansion/linux64"),
-        Arch::CoreMac => format!("{base_url}/{version}/alpha/osx"),
-        Arch::CoreExpansionMac => format!("{base_url}/{version}/expansion/osx"),
-        Arch::CoreWin64 => format!("{base_url}/{version}/alpha/win64-manual"),
-        Arch::CoreExpansionWin64 => format!("{base_url}/{version}/expansion/win64-manual"),
-        Arch::Other => panic!(),
-    }
-}
-
-async fn get_patch_download_link(args: &Args, item: &VersionDiff) -> anyhow::Result<String> {
-    let arch: Arch = (args.version.clone(), args.platform.clone()).into();
-
-    let client = reqwest::Client::new();
-
-    let resp = client
-        .get("https://updater.factorio.com/get-download-link")
-        .query(&get_base_query_params(args))
-        .query(&json!({
-            "package": arch,
-            "from": item.from,
-            "to": item.to,
-        }))
-        .send()
-        .await
-        .context("Unable to send get download link request")?;
-
-    let mut data = resp
-        .json::<Vec<String>>()
-        .await
-        .context("Unable to parse download link response")?;
-
-    if data.len() != 1 {
-        return Err(anyhow::anyhow!("Unexpected download link response"));
-    }
-
-    Ok(data.pop().unwrap())
-}
-
-enum UpdateInfo<'a, 'b> {
+pub(crate) enum UpdateInfo<'a, 'b> {
     FullGame {
         url: &'b str,
-        version: &'a Version,
+        arch: &'a Arch,
         number: &'a str,
     },
     Patch {
         url: &'b str,
-        version: &'a Version,
+        arch: &'a Arch,
         from: &'a str,
         to: &'a str,
     },
+    Mod {
+        url: &'b str,
+        name: &'a str,
+        version: &'a str,
+    },
 }
 
-async fn download_zip<'a, 'b>(
+impl UpdateInfo<'_, '_> {
+    /// Name of the downloaded file, full game downloads prefer the name
+    /// from the download server, because the archive type differs per platform.
+    pub(crate) fn file_name(&self) -> String {
+        match self {
+            UpdateInfo::FullGame { arch, number, .. } => {
+                format!("factorio_{}_v{}.zip", arch, number)
+            }
+            UpdateInfo::Patch { arch, from, to, .. } => patch_file_name(arch, from, to),
+            UpdateInfo::Mod { name, version, .. } => format!("{}_{}.zip", name, version),
+        }
+    }
+}
+
+pub(crate) fn patch_file_name(arch: &Arch, from: &str, to: &str) -> String {
+    format!("factorio_{}_patch_v{}_to_v{}.zip", arch, from, to)
+}
+
+/// Downloaded packages of arch, kept for later updates and offline mode (same layout as mirror)
+pub(crate) fn packages_folder(arch: &Arch) -> anyhow::Result<PathBuf> {
+    let cache = config::cache_dir()
+        .map_err(|err| anyhow::anyhow!("Unable to find cache folder: {:?}", err))?;
+
+    Ok(cache.join("packages").join(get_arch_folder(arch)))
+}
+
+pub(crate) async fn download_zip<'a, 'b>(
     update: UpdateInfo<'a, 'b>,
-    args: &Args,
+    api: &Api,
     mp: Option<&MultiProgress>,
-    file_path: Option<&TempDir>,
+    file_path: Option<&Path>,
 ) -> anyhow::Result<PathBuf> {
     let url = match update {
         UpdateInfo::FullGame { url, .. } => url,
         UpdateInfo::Patch { url, .. } => url,
+        UpdateInfo::Mod { url, .. } => url,
     };
 
     let progress_style = ProgressStyle::with_template(
@@ -96,22 +95,17 @@ async fn download_zip<'a, 'b>(
     .tick_strings(TICK_STRINGS)
     .progress_chars("=O ");
 
-    let (download_string, file_name) = match update {
-        UpdateInfo::FullGame {
-            version, number, ..
-        } => (
-            format!("Downloading Factorio {} v{}...", version, number),
-            format!("factorio_{}_v{}.zip", version, number),
-        ),
-        UpdateInfo::Patch {
-            version, from, to, ..
-        } => (
-            format!(
-                "Downloading Factorio {} patch v{} to v{}...",
-                version, from, to
-            ),
-            format!("factorio_{}_patch_v{}_to_v{}.zip", version, from, to),
+    let download_string = match update {
+        UpdateInfo::FullGame { arch, number, .. } => {
+            format!("Downloading Factorio {} v{}...", arch, number)
+        }
+        UpdateInfo::Patch { arch, from, to, .. } => format!(
+            "Downloading Factorio {} patch v{} to v{}...",
+            arch, from, to
         ),
+        UpdateInfo::Mod { name, version, .. } => {
+            format!("Downloading mod {} v{}...", name, version)
+        }
     };
 
     let mut pb = ProgressBar::new(100)
@@ -129,39 +123,48 @@ async fn download_zip<'a, 'b>(
         pb = mp.add(pb);
     }
 
-    let client = reqwest::Client::new();
-    let resp = client
-        .get(url)
-        .query(&get_base_query_params(args))
-        .send()
+    let resp = api
+        .download(url)
         .await
         .context("Unable to send download request")?;
 
     let total_length = resp
         .headers()
         .get(reqwest::header::CONTENT_LENGTH)
-        .and_then(|opt| {
-            Some(
-                opt.to_str()
-                    .unwrap_or("0")
-                    .parse::<usize>()
-                    .unwrap_or(0usize),
-            )
+        .map(|opt| {
+            opt.to_str()
+                .unwrap_or("0")
+                .parse::<usize>()
+                .unwrap_or(0usize)
         })
         .unwrap_or(0usize);
 
     pb.set_length(total_length as u64);
 
+    let file_name = match update {
+        UpdateInfo::FullGame { .. } => resp
+            .url()
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|name| name.contains('.'))
+            .map(str::to_string)
+            .unwrap_or_else(|| update.file_name()),
+        UpdateInfo::Patch { .. } | UpdateInfo::Mod { .. } => update.file_name(),
+    };
+
     let mut stream = resp.bytes_stream();
-    let file_path = if let Some(fp) = file_path {
-        fp
-    } else {
-        &TempDir::new(&Uuid::new_v4().to_string()).context("Unable to create temp dir")?
+    let file_path = match file_path {
+        Some(fp) => fp.join(file_name),
+        None => TempDir::new(&Uuid::new_v4().to_string())
+            .context("Unable to create temp dir")?
+            .into_path()
+            .join(file_name),
     };
 
-    let file_path = file_path.path().join(file_name);
+    //download into .part file, so interrupted downloads are never mistaken for complete ones
+    let part_path = file_path.with_extension("part");
 
-    let mut file = fs::File::create_new(&file_path)
+    let mut file = fs::File::create(&part_path)
         .await
         .context("Unable to create zip file")?;
 
@@ -169,12 +172,19 @@ async fn download_zip<'a, 'b>(
         let chunk = chunk.context("Unable to download data")?;
 
         pb.inc(chunk.len() as u64);
-        file.write(&chunk).await.context("Error writing zip file")?;
+        file.write_all(&chunk)
+            .await
+            .context("Error writing zip file")?;
     }
 
     file.flush().await.context("Error flushing zip file")?;
+    drop(file);
+
+    fs::rename(&part_path, &file_path)
+        .await
+        .context("Unable to move downloaded file")?;
 
-    if let Some(_) = mp {
+    if mp.is_some() {
         pb.finish_with_message("Download completed.");
     } else {
         pb.finish_and_clear();
@@ -183,24 +193,203 @@ async fn download_zip<'a, 'b>(
     Ok(file_path)
 }
 
-pub async fn do_update<'a>(args: &'a Args, update_type: UpdateType<'a>) -> anyhow::Result<()> {
-    match update_type {
-        UpdateType::FullGame(version) => process_full_version(args, &version).await?,
-        UpdateType::Patch(items) => process_diff(args, items).await?,
-        UpdateType::None => {
-            println!("{}", style("No updates available.").green().bold());
+/// Reports error, which happened before the update started (e.g. while resolving it),
+/// as a failed update to webhooks
+pub(crate) async fn notify_failure<T>(
+    args: &Args,
+    api: &Api,
+    result: anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let Err(err) = &result else {
+        return result;
+    };
+
+    let folder = install_folder(args);
+    let arch: Arch = (args.version.clone(), args.platform.clone()).into();
+    let mut event = Event::new(
+        EventKind::UpdateFailed,
+        folder.display().to_string(),
+        arch.to_string(),
+    );
+    event.from = load_config(&folder)
+        .await
+        .ok()
+        .flatten()
+        .map(|installed| installed.current_version);
+    event.error = Some(format!("{:#}", err));
+    Notifier::load(api.is_offline()).await.send(&event).await;
+
+    result
+}
+
+pub async fn do_update<'a>(
+    args: &'a Args,
+    api: &Api,
+    update_type: UpdateType<'a>,
+    profile: Option<&str>,
+) -> anyhow::Result<()> {
+    let target = match &update_type {
+        UpdateType::FullGame(version) => Some(version.clone()),
+        UpdateType::Patch(items) => items.last().map(|item| item.to.clone()),
+        UpdateType::None => None,
+    };
+
+    let folder = install_folder(args);
+    let installed = notify_failure(args, api, load_config(&folder).await).await?;
+    if let Some(target) = &target
+        && let Some(installed) = &installed
+    {
+        let confirmed = confirm_mod_compatibility(
+            api,
+            &folder.join("mods"),
+            &installed.current_version,
+            target,
+            args.yes,
+        )
+        .await;
+
+        if !notify_failure(args, api, confirmed).await? {
+            println!("{}", style("Update cancelled.").yellow().bold());
+            return Ok(());
         }
     }
-    Ok(())
+
+    let Some(target) = target else {
+        println!("{}", style("No updates available.").green().bold());
+        return Ok(());
+    };
+
+    let notifier = Notifier::load(api.is_offline()).await;
+    let arch: Arch = (args.version.clone(), args.platform.clone()).into();
+    let mut event = Event::new(
+        EventKind::UpdateStarted,
+        folder.display().to_string(),
+        arch.to_string(),
+    );
+    event.from = installed
+        .as_ref()
+        .map(|installed| installed.current_version.clone());
+    event.to = Some(target.clone());
+    notifier.send(&event).await;
+
+    let hooks = HookRunner::load(
+        &folder,
+        installed.as_ref().map(|installed| &installed.hooks),
+        arch.to_string(),
+        event.from.clone(),
+        target.clone(),
+    )
+    .await;
+
+    //downloads are part of the update, their failures are reported below
+    let started = std::time::Instant::now();
+    let result =
+        update_installation(args, api, update_type, profile, &folder, &hooks, &target).await;
+
+    event.kind = match &result {
+        Ok(()) => EventKind::UpdateSucceeded,
+        Err(_) => EventKind::UpdateFailed,
+    };
+    event.duration = Some(started.elapsed());
+    event.error = result.as_ref().err().map(|err| format!("{:#}", err));
+    notifier.send(&event).await;
+
+    result
 }
 
-fn extract_archive(
-    path: &PathBuf,
-    target: &Path,
-    mp: Option<&MultiProgress>,
+/// Downloads the update and applies it, running server is stopped only for applying
+async fn update_installation<'a>(
+    args: &'a Args,
+    api: &Api,
+    update_type: UpdateType<'a>,
+    profile: Option<&str>,
+    folder: &Path,
+    hooks: &HookRunner,
+    target: &str,
 ) -> anyhow::Result<()> {
+    hooks.pre(Stage::Download).await?;
+    let downloaded = match &update_type {
+        UpdateType::FullGame(version) => download_full_version(args, api, version)
+            .await
+            .map(|file| vec![file]),
+        UpdateType::Patch(items) => download_patches(args, api, items).await,
+        UpdateType::None => Ok(Vec::new()),
+    };
+    hooks.post(Stage::Download, &downloaded).await;
+    let files = downloaded?;
+
+    //binary of running server can't be replaced
+    let server = running_server(folder).await?;
+    if let Some(settings) = &server {
+        hooks.pre(Stage::Restart).await?;
+        warn_players(folder, settings, target).await;
+        stop_server(folder, settings).await?;
+    }
+
+    let result = async {
+        snapshot_saves(folder).await?;
+        hooks.pre(Stage::Apply).await?;
+        let applied = match update_type {
+            UpdateType::FullGame(version) => {
+                install_full_version(args, &version, profile, &files[0]).await
+            }
+            UpdateType::Patch(items) => apply_patches(args, &items, files).await,
+            UpdateType::None => Ok(()),
+        };
+        hooks.post(Stage::Apply, &applied).await;
+        applied
+    }
+    .await;
+
+    //server is started even after failed update, with the old version
+    if let Some(settings) = &server {
+        let started = start_server(folder, settings).await;
+        hooks.post(Stage::Restart, &started).await;
+
+        if let Err(err) = started {
+            if result.is_ok() {
+                return Err(err);
+            }
+            eprintln!(
+                "{} {:#}",
+                style("Unable to start the server again:").red().bold(),
+                err
+            );
+        }
+    }
+
+    result
+}
+
+/// Folder, in which all files of the archive are, full game packages contain one
+fn archive_root(archive: &mut zip::ZipArchive<std::fs::File>) -> Option<PathBuf> {
+    let mut root: Option<PathBuf> = None;
+
+    for i in 0..archive.len() {
+        let name = archive.by_index(i).ok()?.enclosed_name()?;
+        let mut components = name.components();
+        let first = PathBuf::from(components.next()?.as_os_str());
+
+        //file directly in the root
+        if components.next().is_none() && !archive.by_index(i).ok()?.is_dir() {
+            return None;
+        }
+
+        match &root {
+            Some(root) if *root != first => return None,
+            Some(_) => {}
+            None => root = Some(first),
+        }
+    }
+
+    root
+}
+
+/// Extracts archive into target, single top level folder is stripped
+fn extract_archive(path: &Path, target: &Path, mp: Option<&MultiProgress>) -> anyhow::Result<()> {
     let file = std::fs::File::open(path).context("Unable to open zip file")?;
     let mut archive = zip::ZipArchive::new(file).context("Unable to read zip archive")?;
+    let root = archive_root(&mut archive);
 
     let progress_style = ProgressStyle::with_template(
         "{spinner} {prefix} {wide_bar:.green} ({percent}%) - ETA: {eta_precise} ",
@@ -214,8 +403,8 @@ fn extract_archive(
         .with_prefix(format!(
             "{} Extracting files...",
             match mp {
-                Some(_) => style("[2/~]").bold().blue(),
-                None => style("[2/3]").bold().blue(),
+                Some(_) => style("[3/~]").bold().blue(),
+                None => style("[3/3]").bold().blue(),
             },
         ));
 
@@ -228,15 +417,24 @@ fn extract_archive(
             .by_index(i)
             .context("Unable to access file in zip")?;
 
-        let out_path = target.join(file.enclosed_name().context("Malformed file path")?);
+        let name = file.enclosed_name().context("Malformed file path")?;
+        let name = match &root {
+            Some(root) => name.strip_prefix(root).unwrap_or(&name).to_path_buf(),
+            None => name,
+        };
+        let out_path = target.join(name);
+
+        if file.is_dir() {
+            std::fs::create_dir_all(&out_path).context("Unable to create folder structure")?;
+            pb.inc(1);
+            continue;
+        }
 
         //create folder structure
         if let Some(parent) = out_path.parent() {
             std::fs::create_dir_all(parent).context("Unable to create folder structure")?;
         }
 
-        println!("Writing to {}", out_path.display());
-
         let mut out_file =
             std::fs::File::create(out_path).context("Unable to create extracted file")?;
         std::io::copy(&mut file, &mut out_file).context("Unable to extract file")?;
@@ -245,33 +443,166 @@ fn extract_archive(
         pb.inc(1);
     }
 
-    pb.finish_with_message("Extraction completed.");
+    pb.finish_and_clear();
 
     Ok(())
 }
 
-async fn process_full_version(args: &Args, version: &str) -> anyhow::Result<()> {
+/// Extracts .tar.xz package (Linux builds) into target, its top level folder is stripped
+fn extract_tar_xz(path: &Path, target: &Path) -> anyhow::Result<()> {
+    let file = std::fs::File::open(path).context("Unable to open package")?;
+    let mut archive = tar::Archive::new(xz2::read::XzDecoder::new(file));
+
+    let spinner = ProgressBar::new_spinner()
+        .with_style(
+            ProgressStyle::with_template("{spinner} {prefix} {wide_msg}")
+                .unwrap()
+                .tick_strings(TICK_STRINGS),
+        )
+        .with_prefix(format!(
+            "{} Extracting files...",
+            style("[3/3]").bold().blue()
+        ));
+    spinner.enable_steady_tick(Duration::from_millis(100));
+
+    for entry in archive.entries().context("Unable to read package")? {
+        let mut entry = entry.context("Unable to read package")?;
+        let name = entry.path().context("Malformed file path")?.into_owned();
+        let relative = name.components().skip(1).collect::<PathBuf>();
+
+        //top level folder itself
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            anyhow::bail!("Malformed file path in package: {}", name.display());
+        }
+
+        let out_path = target.join(&relative);
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent).context("Unable to create folder structure")?;
+        }
+
+        //unpack keeps permissions, so the server binary stays executable
+        entry
+            .unpack(&out_path)
+            .with_context(|| format!("Unable to extract {}", relative.display()))?;
+    }
+
+    spinner.finish_and_clear();
+
+    Ok(())
+}
+
+/// Full game package from the package cache, it is downloaded when missing
+async fn download_full_version(args: &Args, api: &Api, version: &str) -> anyhow::Result<PathBuf> {
     let arch: Arch = (args.version.clone(), args.platform.clone()).into();
-    let link = get_download_links(&arch, &version);
-    let file = download_zip(
-        UpdateInfo::FullGame {
-            url: &link,
-            version: &args.version,
-            number: &version,
+    let package_folder = packages_folder(&arch)?.join("full").join(version);
+    fs::create_dir_all(&package_folder)
+        .await
+        .context("Unable to create package cache")?;
+
+    let file = match find_downloaded(&package_folder).await? {
+        Some(file) => file,
+        None => {
+            download_zip(
+                UpdateInfo::FullGame {
+                    url: &api.get_full_download_link(&arch, version),
+                    arch: &arch,
+                    number: version,
+                },
+                api,
+                None,
+                Some(&package_folder),
+            )
+            .await?
+        }
+    };
+
+    Ok(file)
+}
+
+async fn install_full_version(
+    args: &Args,
+    version: &str,
+    profile: Option<&str>,
+    file: &Path,
+) -> anyhow::Result<()> {
+    let file_name = file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let folder = install_folder(args);
+    let target = folder.clone();
+    let file = file.to_path_buf();
+
+    if file_name.ends_with(".zip") {
+        tokio::task::spawn_blocking(move || extract_archive(&file, &target, None))
+            .await
+            .context("Extraction was interrupted")??;
+    } else if file_name.ends_with(".tar.xz") {
+        tokio::task::spawn_blocking(move || extract_tar_xz(&file, &target))
+            .await
+            .context("Extraction was interrupted")??;
+    } else {
+        anyhow::bail!(
+            "Downloaded {}, only zip and tar.xz packages can be installed",
+            file.display()
+        );
+    }
+
+    //other settings of the installation are kept
+    let config = match load_config(&folder).await? {
+        Some(config) => Config {
+            version: args.version.clone(),
+            platform: args.platform.clone(),
+            current_version: version.to_string(),
+            profile: profile.map(str::to_string),
+            ..config
         },
-        args,
-        None,
-        None,
-    )
-    .await?;
+        None => Config {
+            version: args.version.clone(),
+            platform: args.platform.clone(),
+            current_version: version.to_string(),
+            profile: profile.map(str::to_string),
+            server: None,
+            hooks: Hooks::default(),
+            notified_version: None,
+        },
+    };
+    save_config(&folder, &config).await?;
+
+    println!(
+        "{}",
+        style(format!(
+            "Factorio {} was installed into {}",
+            version,
+            folder.display()
+        ))
+        .green()
+        .bold()
+    );
 
     Ok(())
 }
 
-async fn process_diff(args: &Args, items: Vec<&VersionDiff>) -> anyhow::Result<()> {
+/// Patches of the chain from the package cache, missing ones are downloaded
+async fn download_patches(
+    args: &Args,
+    api: &Api,
+    items: &[&VersionDiff],
+) -> anyhow::Result<Vec<PathBuf>> {
+    let arch: Arch = (args.version.clone(), args.platform.clone()).into();
+
     let progress_style = ProgressStyle::with_template("{spinner} {prefix} {wide_msg}")
         .unwrap()
-        .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]);
+        .tick_strings(TICK_STRINGS);
 
     let spinner = ProgressBar::new_spinner()
         .with_style(progress_style.clone())
@@ -282,52 +613,113 @@ async fn process_diff(args: &Args, items: Vec<&VersionDiff>) -> anyhow::Result<(
 
     spinner.enable_steady_tick(Duration::from_millis(100));
 
+    let patches_folder = packages_folder(&arch)?.join("patches");
+    fs::create_dir_all(&patches_folder)
+        .await
+        .context("Unable to create package cache")?;
+
+    let files = items
+        .iter()
+        .map(|patch| patches_folder.join(patch_file_name(&arch, &patch.from, &patch.to)))
+        .collect::<Vec<_>>();
+
+    //only patches, which weren't downloaded before
+    let mut missing = Vec::new();
+    for (patch, file) in items.iter().zip(files.iter()) {
+        if !fs::try_exists(file)
+            .await
+            .context("Unable to check downloaded patch")?
+        {
+            missing.push(*patch);
+        }
+    }
+
     let paths = join_all(
-        items
+        missing
             .iter()
-            .map(async |item| get_patch_download_link(&args, *item).await),
+            .map(async |item| api.get_patch_download_link(&arch, item).await),
     )
     .await
     .into_iter()
-    .map(|res| res.context("Unable to get download url for patch").unwrap())
-    .collect::<Vec<_>>();
+    .collect::<Result<Vec<_>, _>>()
+    .context("Unable to get download url for patch")?;
 
     spinner.finish();
 
     let mp = MultiProgress::new();
-    let file_path =
-        TempDir::new(Uuid::new_v4().to_string().as_str()).context("Unable to create temp dir")?;
 
-    let files = join_all(
+    join_all(
         paths
             .into_iter()
-            .zip(items.iter())
+            .zip(missing.iter())
             .map(async |(link, patch)| {
-                let link = link;
-
                 download_zip(
                     UpdateInfo::Patch {
                         url: &link,
-                        version: &args.version,
+                        arch: &arch,
                         from: &patch.from,
                         to: &patch.to,
                     },
-                    &args,
+                    api,
                     Some(&mp),
-                    Some(&file_path),
+                    Some(&patches_folder),
                 )
                 .await
                 .context("Unable to download patch")
-                .unwrap()
             }),
     )
-    .await;
+    .await
+    .into_iter()
+    .collect::<anyhow::Result<Vec<_>>>()?;
 
     mp.clear().unwrap();
 
-    join_all(files.into_iter().map(|f| {
-        tokio::task::spawn_blocking(move || extract_archive(&f, f.parent().unwrap(), None))
-    }))
-    .await;
+    Ok(files)
+}
+
+async fn apply_patches(
+    args: &Args,
+    items: &[&VersionDiff],
+    files: Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    let folder = install_folder(args);
+    let mut config = load_config(&folder)
+        .await?
+        .context("Installation has no config.json, so patches can't be applied")?;
+
+    //patches have to be applied in order of the chain
+    for (file, patch) in files.into_iter().zip(items.iter()) {
+        if patch.from != config.current_version {
+            anyhow::bail!(
+                "Patch {} -> {} can't be applied to version {}",
+                patch.from,
+                patch.to,
+                config.current_version
+            );
+        }
+
+        apply_patch(&file, &folder).await?;
+
+        config.current_version = patch.to.clone();
+        save_config(&folder, &config).await?;
+
+        println!(
+            "{} Patched {} -> {}",
+            style("[3/3]").bold().blue(),
+            patch.from,
+            patch.to
+        );
+    }
+
+    println!(
+        "{}",
+        style(format!(
+            "Factorio was updated to {}",
+            config.current_version
+        ))
+        .green()
+        .bold()
+    );
+
     Ok(())
 }
@@ -5,12 +5,15 @@ use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
 
 use crate::{
-    functions::get_base_query_params,
+    app::api::{
+        Api,
+        cache::{Freshness, Listing},
+        structs::{LatestReleases, Updates},
+    },
     steps::TICK_STRINGS,
-    structs::{Args, Updates},
 };
 
-pub async fn get_updates(args: &Args) -> anyhow::Result<Updates> {
+pub async fn get_updates(api: &Api) -> anyhow::Result<Updates> {
     let progress_style = ProgressStyle::with_template("{spinner} {prefix} {wide_msg}")
         .unwrap()
         .tick_strings(TICK_STRINGS);
@@ -23,20 +26,58 @@ pub async fn get_updates(args: &Args) -> anyhow::Result<Updates> {
         ));
     bar.enable_steady_tick(Duration::from_millis(100));
 
-    let client = reqwest::Client::new();
-    let resp = client
-        .get("https://updater.factorio.com/get-available-versions")
-        .query(&get_base_query_params(args))
-        .send()
+    let listing = api
+        .get_versions_cached()
         .await
         .context("Failed to get list of versions from Factorio.com")?;
 
-    let updates: Updates = resp
-        .json()
+    bar.finish();
+    print_freshness(&listing, "version list");
+
+    Ok(listing.value)
+}
+
+/// Tells, when cached `what` is used instead of asking the API
+fn print_freshness<T>(listing: &Listing<T>, what: &str) {
+    match listing.freshness {
+        Freshness::Fresh => {}
+        Freshness::Cached => println!(
+            "{}",
+            style(format!("Using {} cached {}", what, listing.age())).dim()
+        ),
+        Freshness::Stale => println!(
+            "{}",
+            style(format!(
+                "Using stale {} from {}, newer versions may exist",
+                what,
+                listing.age()
+            ))
+            .yellow()
+        ),
+    }
+}
+
+/// Public releases, which don't need credentials
+pub async fn get_latest_releases(api: &Api) -> anyhow::Result<LatestReleases> {
+    let progress_style = ProgressStyle::with_template("{spinner} {prefix} {wide_msg}")
+        .unwrap()
+        .tick_strings(TICK_STRINGS);
+
+    let bar = ProgressBar::new_spinner()
+        .with_style(progress_style)
+        .with_prefix(format!(
+            "{} Fetching latest releases...",
+            style("[1/3]").bold().blue(),
+        ));
+    bar.enable_steady_tick(Duration::from_millis(100));
+
+    let listing = api
+        .get_latest_releases_cached()
         .await
-        .context("Failed to parse response from Factorio.com")?;
+        .context("Failed to get latest releases from Factorio.com")?;
 
     bar.finish();
+    print_freshness(&listing, "latest releases");
 
-    Ok(updates)
+    Ok(listing.value)
 }
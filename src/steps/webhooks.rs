@@ -0,0 +1,80 @@
+use console::style;
+
+use crate::{
+    config::Config,
+    steps::credentials::config_error,
+    structs::{EndpointArgs, WebhooksAction, WebhooksArgs},
+    webhooks::{Event, EventKind, Notifier, WebhookFormat},
+};
+
+pub async fn handle_webhooks(args: WebhooksArgs, endpoints: EndpointArgs) -> anyhow::Result<()> {
+    let config = Config::load()
+        .await
+        .map_err(config_error)?
+        .unwrap_or_default();
+    let notifier = Notifier::from_config(config, endpoints.offline).await;
+
+    if notifier.webhooks().is_empty() {
+        println!(
+            "{}",
+            style("No webhooks, add them to the webhooks list of the config file").dim()
+        );
+        return Ok(());
+    }
+
+    match args.action.unwrap_or(WebhooksAction::List) {
+        WebhooksAction::List => {
+            for webhook in notifier.webhooks() {
+                let events = if webhook.events.is_empty() {
+                    "all events".to_string()
+                } else {
+                    serde_json::to_value(&webhook.events)?
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|event| event.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+
+                println!(
+                    "{} {} ({}, {} retries)",
+                    style(webhook.name()).bold(),
+                    match webhook.format {
+                        WebhookFormat::Json => "json",
+                        WebhookFormat::Discord => "discord",
+                    },
+                    events,
+                    webhook.retries
+                );
+            }
+        }
+        WebhooksAction::Test => {
+            if endpoints.offline {
+                anyhow::bail!("Webhooks can't be tested in offline mode");
+            }
+            if !notifier.is_enabled() {
+                anyhow::bail!("Webhooks can't be sent, check the HTTP settings of the config file");
+            }
+
+            let failed = notifier
+                .send(&Event::new(
+                    EventKind::Test,
+                    "test".to_string(),
+                    "test".to_string(),
+                ))
+                .await;
+            if failed > 0 {
+                anyhow::bail!(
+                    "Test event wasn't delivered to {} of {} webhooks",
+                    failed,
+                    notifier.webhooks().len()
+                );
+            }
+
+            println!("{}", style("Test event was sent").green().bold());
+        }
+    }
+
+    Ok(())
+}
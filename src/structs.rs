@@ -1,3 +1,75 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::api::structs::{Arch, Platform, Version},
+    hooks::Hooks,
+};
+
+#[derive(Parser)]
+#[command(
+    about = "Factorio Updater",
+    long_about = "Keeps Factorio installations up to date using the Factorio Updater API. Without any command the interactive TUI is started."
+)]
+pub struct Cli {
+    #[command(flatten)]
+    pub endpoints: EndpointArgs,
+    /// Account profile to use instead of the active one (or the one the installation is tied to)
+    #[arg(long, env = "FACTORIO_UPDATER_PROFILE", global = true)]
+    pub profile: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    Update(Args),
+    Mirror(MirrorArgs),
+    Check(CheckArgs),
+    Changelog(ChangelogArgs),
+    Serve(ServeArgs),
+    Login(LoginArgs),
+    Credentials(CredentialsArgs),
+    Profiles(ProfilesArgs),
+    Mods(ModsArgs),
+    Headless(HeadlessArgs),
+    Webhooks(WebhooksArgs),
+    DevServer(DevServerArgs),
+}
+
+/// Overrides of the API access stored in config
+#[derive(clap::Args, Clone, Default)]
+pub struct EndpointArgs {
+    /// Base URL of the updater API (get-available-versions, get-download-link)
+    #[arg(long, env = "FACTORIO_UPDATER_URL", global = true)]
+    pub updater_url: Option<String>,
+    /// Base URL of full game downloads (get-download)
+    #[arg(long, env = "FACTORIO_DOWNLOAD_URL", global = true)]
+    pub download_url: Option<String>,
+    /// Base URL of the auth API used for password login (api-login)
+    #[arg(long, env = "FACTORIO_AUTH_URL", global = true)]
+    pub auth_url: Option<String>,
+    /// Base URL of the mod portal (api/mods, download)
+    #[arg(long, env = "FACTORIO_MODS_URL", global = true)]
+    pub mods_url: Option<String>,
+    /// Don't make any requests, use only cached version listing, latest releases and downloaded packages
+    #[arg(long, global = true)]
+    pub offline: bool,
+}
+
+/// Credentials passed directly, the stored profile is used when they are missing
+#[derive(clap::Args, Clone)]
+pub struct Credentials {
+    /// Your factorio.com username (for authentication)
+    #[arg(long, requires = "token")]
+    pub username: Option<String>,
+    /// Your factorio.com token (for authentication)
+    #[arg(long, env)]
+    pub token: Option<String>,
+}
+
 #[derive(Parser)]
 #[command(
     about = "Factorio Updater CLI",
@@ -10,19 +82,501 @@ pub struct Args {
     /// Which platform to update
     #[arg(long, default_value = "win64")]
     pub platform: Platform,
-    /// Your factorio.com username (for authentication)
+    #[command(flatten)]
+    pub credentials: Credentials,
     #[arg(long)]
-    pub username: String,
-    /// Your factorio.com token (for authentication)
-    #[arg(long, env)]
-    pub token: String,
+    pub custom_folder: Option<String>,
+    /// Update even if enabled mods don't support the new game version, without asking
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(clap::Args)]
+#[command(
+    about = "Check the latest public release without credentials",
+    long_about = "Shows the latest stable and experimental versions from the public latest-releases endpoint of factorio.com and compares them with the installed version. No account is needed."
+)]
+pub struct CheckArgs {
+    /// Which version of Factorio to check
+    #[arg(long, default_value = "vanilla")]
+    pub version: Version,
+    /// Which platform to check
+    #[arg(long, default_value = "headless")]
+    pub platform: Platform,
+    /// Installation to compare with (default folder of the platform when missing)
+    #[arg(long)]
+    pub custom_folder: Option<String>,
+    /// Compare with the experimental release instead of the stable one
+    #[arg(long)]
+    pub experimental: bool,
+}
+
+#[derive(clap::Args)]
+#[command(
+    about = "Show changes between two versions",
+    long_about = "Prints entries of data/changelog.txt between the installed version (or --from) and --to. The changelog of the target version is taken from its downloaded package when available, otherwise from the installation."
+)]
+pub struct ChangelogArgs {
+    /// Which version of Factorio is installed
+    #[arg(long, default_value = "vanilla")]
+    pub version: Version,
+    /// Which platform is installed
+    #[arg(long, default_value = "win64")]
+    pub platform: Platform,
+    #[arg(long)]
+    pub custom_folder: Option<String>,
+    /// Show changes after this version (installed version by default)
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Show changes up to this version (newest in changelog by default)
+    #[arg(long)]
+    pub to: Option<String>,
+}
+
+#[derive(clap::Args)]
+#[command(
+    about = "Mirror every available platform build into a distribution folder",
+    long_about = "Downloads the current full package of every platform the account is entitled to, together with the patches from the previous versions, into a distribution folder with a generated index.json. Packages are stored as-is, so builds for other platforms (e.g. mac) can be mirrored too. Full packages of older versions and patches, which left the patch chain, are removed."
+)]
+pub struct MirrorArgs {
+    #[command(flatten)]
+    pub credentials: Credentials,
+    /// Folder where the mirror is stored
+    #[arg(long, default_value = "mirror")]
+    pub output: PathBuf,
+    /// How many previous versions should be able to patch to the current one
+    #[arg(long, default_value_t = 5)]
+    pub patches: usize,
+    /// Only mirror these packages (all entitled packages when empty)
+    #[arg(long = "arch")]
+    pub arches: Vec<Arch>,
+}
+
+#[derive(clap::Args)]
+#[command(
+    about = "Log in with factorio.com username and password",
+    long_about = "Obtains token from factorio.com with username and password, only the token is stored in config under the profile selected with --profile (the active one by default). Password is always read from prompt. With --import the credentials are taken from Factorio's player-data.json instead."
+)]
+pub struct LoginArgs {
+    /// Your factorio.com username or email
+    #[arg(long)]
+    pub username: Option<String>,
+    /// Import credentials from player-data.json, standard locations are searched when no path is given
+    #[arg(long, num_args = 0..=1, value_name = "PATH", conflicts_with = "username")]
+    pub import: Option<Option<PathBuf>>,
+    /// Import without asking for confirmation
+    #[arg(short, long, requires = "import")]
+    pub yes: bool,
+}
+
+#[derive(clap::Args)]
+#[command(
+    about = "Manage protection of the stored token",
+    long_about = "Encrypts the token of the selected profile with a passphrase, or stores it as plain text again. The passphrase is read from FACTORIO_UPDATER_PASSPHRASE or from prompt."
+)]
+pub struct CredentialsArgs {
+    #[command(subcommand)]
+    pub action: CredentialsAction,
+}
+
+#[derive(Subcommand)]
+pub enum CredentialsAction {
+    /// Encrypt the stored token with a passphrase
+    Encrypt,
+    /// Store the token as plain text again
+    Decrypt,
+}
+
+#[derive(clap::Args)]
+#[command(
+    about = "Manage stored factorio.com account profiles",
+    long_about = "Lists stored profiles, or changes the active one. New profiles are created with the login command and --profile."
+)]
+pub struct ProfilesArgs {
+    #[command(subcommand)]
+    pub action: Option<ProfilesAction>,
+}
+
+#[derive(Subcommand)]
+pub enum ProfilesAction {
+    /// List stored profiles (default)
+    List,
+    /// Make profile the active one
+    Use { name: String },
+    /// Remove profile with its credentials
+    Remove { name: String },
+}
+
+#[derive(clap::Args)]
+#[command(
+    about = "Show and test webhooks",
+    long_about = "Webhooks are set in the webhooks list of the config file, each with url, format (json or discord), events (update_available, update_started, update_succeeded, update_failed, all when missing) and retries. The check command sends update_available once per new version, the update command sends the others."
+)]
+pub struct WebhooksArgs {
+    #[command(subcommand)]
+    pub action: Option<WebhooksAction>,
+}
+
+#[derive(Subcommand)]
+pub enum WebhooksAction {
+    /// List configured webhooks (default)
+    List,
+    /// Send a test event to all webhooks
+    Test,
+}
+
+#[derive(clap::Args)]
+#[command(
+    about = "Manage mods of an installation",
+    long_about = "Reads mods/mod-list.json and info.json of the mod zips, and updates or installs mods from the mod portal, using the newest releases compatible with the installed game version. Dependencies are resolved into an install plan, conflicts are explained instead of installing anything. Mod sets can be exported into a lockfile and other installations synced to it, or saved as named profiles to switch between. Mod settings from mod-settings.dat can be shown, compared, exported and applied. Downloads use the same credentials as game updates."
+)]
+pub struct ModsArgs {
+    #[command(subcommand)]
+    pub action: ModsAction,
+}
+
+#[derive(Subcommand)]
+pub enum ModsAction {
+    /// List installed mods
+    List(ModsTarget),
+    /// Update enabled mods to the newest compatible releases
+    Update(ModsUpdateArgs),
+    /// Install mods together with their dependencies
+    Install(ModsInstallArgs),
+    /// Export names, versions and enabled flags of mods into a lockfile
+    Export(ModsExportArgs),
+    /// Make mods match a lockfile, downloading, downgrading, disabling and removing mods as needed
+    Sync(ModsSyncArgs),
+    /// Manage named mod profiles of the installation
+    Profile(ModsProfileArgs),
+    /// Show, compare, export and apply mod settings (mod-settings.dat)
+    Settings(ModsSettingsArgs),
+}
+
+/// Installation, whose mods are managed
+#[derive(clap::Args, Clone)]
+pub struct ModsTarget {
+    /// Which version of Factorio is installed
+    #[arg(long, default_value = "vanilla")]
+    pub version: Version,
+    /// Which platform is installed
+    #[arg(long, default_value = "win64")]
+    pub platform: Platform,
+    #[arg(long)]
+    pub custom_folder: Option<String>,
+    /// Mods folder, mods in the installation by default
+    #[arg(long)]
+    pub mods_folder: Option<PathBuf>,
+    /// Game version (e.g. 2.0), which mods have to support, the installed one by default
+    #[arg(long)]
+    pub factorio_version: Option<String>,
+}
+
+#[derive(clap::Args)]
+pub struct ModsUpdateArgs {
+    #[command(flatten)]
+    pub target: ModsTarget,
+    #[command(flatten)]
+    pub credentials: Credentials,
+    /// Only update these mods (all of them when empty)
+    pub names: Vec<String>,
+    /// Only show available updates
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(clap::Args)]
+pub struct ModsInstallArgs {
+    #[command(flatten)]
+    pub target: ModsTarget,
+    #[command(flatten)]
+    pub credentials: Credentials,
+    /// Mods to install, optionally with version constraint (e.g. "name >= 1.2.0")
+    #[arg(required = true)]
+    pub mods: Vec<String>,
+    /// Only show the install plan
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(clap::Args)]
+pub struct ModsExportArgs {
+    #[command(flatten)]
+    pub target: ModsTarget,
+    /// File to write to, printed when missing
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct ModsSyncArgs {
+    #[command(flatten)]
+    pub target: ModsTarget,
+    #[command(flatten)]
+    pub credentials: Credentials,
+    /// Lockfile created with mods export
+    pub lockfile: PathBuf,
+    /// Only show the changes
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(clap::Args)]
+pub struct ModsProfileArgs {
+    #[command(subcommand)]
+    pub action: ModsProfileAction,
+}
+
+#[derive(Subcommand)]
+pub enum ModsProfileAction {
+    /// List saved mod profiles
+    List(ModsTarget),
+    /// Save current mods as profile, replacing profile of the same name
+    Save(ModsProfileTarget),
+    /// Replace current mods with the ones of profile
+    Activate(ModsProfileTarget),
+    /// Remove profile, its mods stay in the shared store
+    Remove(ModsProfileTarget),
+}
+
+#[derive(clap::Args)]
+pub struct ModsProfileTarget {
+    #[command(flatten)]
+    pub target: ModsTarget,
+    /// Name of the profile
+    pub name: String,
+}
+
+#[derive(clap::Args)]
+pub struct ModsSettingsArgs {
+    #[command(subcommand)]
+    pub action: ModsSettingsAction,
+}
+
+#[derive(Subcommand)]
+pub enum ModsSettingsAction {
+    /// Show mod settings
+    Show(ModsTarget),
+    /// Compare mod settings with another mod-settings.dat or exported JSON
+    Diff(ModsSettingsDiffArgs),
+    /// Export mod settings into JSON
+    Export(ModsExportArgs),
+    /// Set mod settings from JSON in the format of export, other settings are kept
+    Apply(ModsSettingsApplyArgs),
+}
+
+#[derive(clap::Args)]
+pub struct ModsSettingsDiffArgs {
+    #[command(flatten)]
+    pub target: ModsTarget,
+    /// mod-settings.dat or JSON file to compare with
+    pub other: PathBuf,
+}
+
+#[derive(clap::Args)]
+pub struct ModsSettingsApplyArgs {
+    #[command(flatten)]
+    pub target: ModsTarget,
+    /// JSON file with settings per scope (e.g. {"startup": {"setting-name": 5}})
+    pub file: PathBuf,
+    /// Only show the changed settings
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(clap::Args)]
+#[command(
+    about = "Stop and start a headless server",
+    long_about = "Manages the server process of a headless installation with the start command, stop command or PID file stored in its config.json. When the server is running during an update, players are warned over RCON, the server is stopped, its newest save is backed up into save-backups (when enabled), the game is updated and started again, and the start is checked in factorio-current.log."
+)]
+pub struct HeadlessArgs {
+    #[command(subcommand)]
+    pub action: HeadlessAction,
+}
+
+#[derive(Subcommand)]
+pub enum HeadlessAction {
+    /// Show lifecycle settings and whether the server is running
+    Status(HeadlessTarget),
+    /// Change lifecycle settings, empty value removes the setting
+    Configure(HeadlessConfigureArgs),
+    /// Start the server and wait until it hosts the game
+    Start(HeadlessTarget),
+    /// Stop the server and wait until it exits
+    Stop(HeadlessTarget),
+    /// Run a command on the server over RCON
+    Rcon(HeadlessRconArgs),
+    /// Back up the newest save now, running server is saved over RCON first
+    Snapshot(HeadlessTarget),
+}
+
+/// Installation with the headless server
+#[derive(clap::Args, Clone)]
+pub struct HeadlessTarget {
+    /// Which version of Factorio is installed
+    #[arg(long, default_value = "vanilla")]
+    pub version: Version,
+    /// Which platform is installed
+    #[arg(long, default_value = "headless")]
+    pub platform: Platform,
     #[arg(long)]
     pub custom_folder: Option<String>,
 }
 
+#[derive(clap::Args)]
+pub struct HeadlessConfigureArgs {
+    #[command(flatten)]
+    pub target: HeadlessTarget,
+    /// Command starting the server (e.g. "bin/x64/factorio --start-server saves/world.zip")
+    #[arg(long)]
+    pub start_command: Option<String>,
+    /// Command stopping the server (e.g. "systemctl stop factorio"), PID file is used when missing
+    #[arg(long)]
+    pub stop_command: Option<String>,
+    /// File with PID of the server, relative to the installation folder. It has to be written
+    /// by the start command (e.g. "echo $$ > server.pid; exec bin/x64/factorio ...")
+    #[arg(long)]
+    pub pid_file: Option<PathBuf>,
+    /// Seconds to wait for the server to stop, before it is killed
+    #[arg(long)]
+    pub graceful_timeout: Option<u64>,
+    /// Seconds to wait for the server to host the game after start
+    #[arg(long)]
+    pub startup_timeout: Option<u64>,
+    /// RCON address of the server (e.g. 127.0.0.1:27015)
+    #[arg(long)]
+    pub rcon_address: Option<String>,
+    /// RCON password of the server
+    #[arg(long)]
+    pub rcon_password: Option<String>,
+    /// Comma separated seconds before the restart, when players are warned (e.g. 300,60,10)
+    #[arg(long)]
+    pub countdown: Option<String>,
+    /// Save the game with /server-save before the server is stopped
+    #[arg(long)]
+    pub server_save: Option<bool>,
+    /// Number of kept backups of the newest save, taken before updates (0 disables them)
+    #[arg(long)]
+    pub save_backups: Option<usize>,
+}
+
+#[derive(clap::Args)]
+pub struct HeadlessRconArgs {
+    #[command(flatten)]
+    pub target: HeadlessTarget,
+    /// Command to run (e.g. /players), text without slash is sent to the chat
+    pub command: String,
+}
+
+#[derive(clap::Args)]
+#[command(
+    about = "Serve a mirror folder to other updaters on the network",
+    long_about = "Exposes get-available-versions, get-download-link and get-download with the same shapes as the official endpoints, backed by a folder created with the mirror command. Credentials are only checked when an allowlist is given, download links carry them so mirrored files are checked too. Listens on localhost unless another address is given."
+)]
+pub struct ServeArgs {
+    /// Mirror folder to serve
+    #[arg(long, default_value = "mirror")]
+    pub mirror: PathBuf,
+    /// Address to listen on, use 0.0.0.0:8080 to serve other machines
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub listen: SocketAddr,
+    /// JSON file with allowed {"username": "token"} pairs
+    #[arg(long)]
+    pub allowlist: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+#[command(
+    about = "Run a fake updater API for development and testing",
+    long_about = "Serves get-available-versions, get-download-link, get-download and api-login without factorio.com. Versions are folders of the fixtures (<fixtures>/<package>/<version>/...), full and patch packages are generated from them. Patches are applied by the game of the installation (--apply-update), so fixture trees need a bin/x64/factorio able to apply them. Stable versions and accepted accounts can be set in <fixtures>/fake.json."
+)]
+pub struct DevServerArgs {
+    /// Folder with fixture trees
+    #[arg(long, default_value = "fixtures")]
+    pub fixtures: PathBuf,
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub listen: SocketAddr,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
     pub version: Version,
     pub platform: Platform,
     pub current_version: String,
+    /// Account profile, which is used for updating this installation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// How to stop and start the headless server around updates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<ServerSettings>,
+    /// Commands run around the stages of updates, after the global ones
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// Newest version, which webhooks were told is available, so it's announced only once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notified_version: Option<String>,
+}
+
+/// Lifecycle of headless server, commands are run by the shell in the installation folder
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServerSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_command: Option<String>,
+    /// Used instead of signalling the process from PID file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_command: Option<String>,
+    /// Written by the start command, the updater only reads and removes it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid_file: Option<PathBuf>,
+    /// Seconds to wait for the server to stop, before it is killed
+    #[serde(default = "default_graceful_timeout")]
+    pub graceful_timeout: u64,
+    /// Seconds to wait for the server to host the game after start
+    #[serde(default = "default_startup_timeout")]
+    pub startup_timeout: u64,
+    /// Address of RCON (--rcon-port of the server), players are warned over it before updates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rcon_address: Option<String>,
+    /// Password of RCON (--rcon-password of the server)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rcon_password: Option<String>,
+    /// Seconds before the restart, when a warning is sent to the chat
+    #[serde(default = "default_countdown")]
+    pub countdown: Vec<u64>,
+    /// Run /server-save after the countdown
+    #[serde(default)]
+    pub server_save: bool,
+    /// Number of kept backups of the newest save, taken before updates, 0 disables them
+    #[serde(default)]
+    pub save_backups: usize,
+}
+
+fn default_graceful_timeout() -> u64 {
+    60
+}
+
+fn default_startup_timeout() -> u64 {
+    120
+}
+
+fn default_countdown() -> Vec<u64> {
+    vec![60, 30, 10]
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            start_command: None,
+            stop_command: None,
+            pid_file: None,
+            graceful_timeout: default_graceful_timeout(),
+            startup_timeout: default_startup_timeout(),
+            rcon_address: None,
+            rcon_password: None,
+            countdown: default_countdown(),
+            server_save: false,
+            save_backups: 0,
+        }
+    }
 }
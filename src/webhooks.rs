@@ -0,0 +1,486 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::config::Config;
+
+/// Payload sent to the webhook URL
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// Event as a flat JSON object
+    #[default]
+    Json,
+    /// Message with an embed, for Discord (and Discord compatible) webhooks
+    Discord,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    UpdateAvailable,
+    UpdateStarted,
+    UpdateSucceeded,
+    UpdateFailed,
+    /// Sent by the webhooks test command only
+    Test,
+}
+
+/// Outbound webhook from config file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Events sent to the webhook, all of them when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<EventKind>,
+    /// Attempts after a failed one, the delay doubles from one second
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+impl Webhook {
+    /// URL without its path, which often contains the secret of the webhook
+    pub fn name(&self) -> &str {
+        let start = self.url.find("://").map(|index| index + 3).unwrap_or(0);
+        match self.url[start..].find('/') {
+            Some(index) => &self.url[..start + index],
+            None => &self.url,
+        }
+    }
+}
+
+/// Update of an installation, which is reported to the webhooks
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub kind: EventKind,
+    /// Installation folder
+    pub installation: String,
+    pub arch: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub duration: Option<Duration>,
+    pub error: Option<String>,
+}
+
+impl EventKind {
+    fn title(&self) -> &'static str {
+        match self {
+            Self::UpdateAvailable => "Factorio update available",
+            Self::UpdateStarted => "Factorio update started",
+            Self::UpdateSucceeded => "Factorio update succeeded",
+            Self::UpdateFailed => "Factorio update failed",
+            Self::Test => "Factorio updater webhook test",
+        }
+    }
+
+    fn color(&self) -> u32 {
+        match self {
+            Self::UpdateAvailable => 0xf1c40f,
+            Self::UpdateStarted => 0x3498db,
+            Self::UpdateSucceeded => 0x2ecc71,
+            Self::UpdateFailed => 0xe74c3c,
+            Self::Test => 0x95a5a6,
+        }
+    }
+}
+
+impl Event {
+    pub fn new(kind: EventKind, installation: String, arch: String) -> Self {
+        Self {
+            kind,
+            installation,
+            arch,
+            from: None,
+            to: None,
+            duration: None,
+            error: None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "event": self.kind,
+            "installation": self.installation,
+            "arch": self.arch,
+            "from": self.from,
+            "to": self.to,
+            "duration_seconds": self.duration.map(|duration| duration.as_secs_f64()),
+            "error": self.error,
+            "timestamp": SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or(0),
+        })
+    }
+
+    fn to_discord(&self) -> Value {
+        let mut fields = vec![
+            json!({"name": "Installation", "value": self.installation, "inline": true}),
+            json!({"name": "Arch", "value": self.arch, "inline": true}),
+        ];
+        if let Some(from) = &self.from {
+            fields.push(json!({"name": "From", "value": from, "inline": true}));
+        }
+        if let Some(to) = &self.to {
+            fields.push(json!({"name": "To", "value": to, "inline": true}));
+        }
+        if let Some(duration) = self.duration {
+            fields.push(json!({
+                "name": "Duration",
+                "value": format!("{:.1}s", duration.as_secs_f64()),
+                "inline": true
+            }));
+        }
+        if let Some(error) = &self.error {
+            //embed field values are limited to 1024 characters
+            let error = error.chars().take(1000).collect::<String>();
+            fields.push(json!({"name": "Error", "value": format!("```{}```", error)}));
+        }
+
+        json!({
+            "embeds": [{
+                "title": self.kind.title(),
+                "color": self.kind.color(),
+                "fields": fields,
+            }]
+        })
+    }
+}
+
+/// Webhooks from config file with the configured HTTP client
+pub struct Notifier {
+    webhooks: Vec<Webhook>,
+    /// Missing in offline mode, or when the HTTP settings are invalid
+    client: Option<reqwest::Client>,
+}
+
+impl Notifier {
+    /// Nothing is sent in offline mode, or when the config can't be used
+    pub async fn load(offline: bool) -> Self {
+        match Config::load().await {
+            Ok(config) => Self::from_config(config.unwrap_or_default(), offline).await,
+            Err(err) => {
+                log::warn!("Webhooks disabled, unable to load config: {:?}", err);
+                Self {
+                    webhooks: Vec::new(),
+                    client: None,
+                }
+            }
+        }
+    }
+
+    /// Webhooks are kept even when they can't be sent, so they can be listed
+    pub async fn from_config(config: Config, offline: bool) -> Self {
+        let client = if offline || config.webhooks.is_empty() {
+            None
+        } else {
+            match config.http.client().await {
+                Ok(client) => Some(client),
+                Err(err) => {
+                    log::warn!("Webhooks disabled, invalid HTTP settings: {:?}", err);
+                    None
+                }
+            }
+        };
+
+        Self {
+            webhooks: config.webhooks,
+            client,
+        }
+    }
+
+    pub fn webhooks(&self) -> &[Webhook] {
+        &self.webhooks
+    }
+
+    /// There are webhooks and they can be reached
+    pub fn is_enabled(&self) -> bool {
+        self.client.is_some() && !self.webhooks.is_empty()
+    }
+
+    /// Sends event to all webhooks subscribed to it, failures are only reported,
+    /// so they never stop an update. Returns the number of webhooks, which failed.
+    pub async fn send(&self, event: &Event) -> usize {
+        let mut failed = 0;
+
+        for webhook in &self.webhooks {
+            if !webhook.events.is_empty()
+                && event.kind != EventKind::Test
+                && !webhook.events.contains(&event.kind)
+            {
+                continue;
+            }
+
+            if let Err(err) = self.send_to(webhook, event).await {
+                failed += 1;
+                log::warn!("Webhook {} failed: {:#}", webhook.name(), err);
+                eprintln!(
+                    "{}",
+                    console::style(format!("Webhook {} failed: {:#}", webhook.name(), err))
+                        .yellow()
+                );
+            }
+        }
+
+        failed
+    }
+
+    async fn send_to(&self, webhook: &Webhook, event: &Event) -> anyhow::Result<()> {
+        let Some(client) = &self.client else {
+            return Ok(());
+        };
+
+        let body = match webhook.format {
+            WebhookFormat::Json => event.to_json(),
+            WebhookFormat::Discord => event.to_discord(),
+        };
+
+        let mut attempt = 0;
+        loop {
+            let result = match client.post(&webhook.url).json(&body).send().await {
+                Ok(response) => response
+                    .error_for_status()
+                    .map(|_| ())
+                    .map_err(|err| anyhow::Error::from(err.without_url())),
+                Err(err) => Err(err.without_url().into()),
+            };
+
+            match result {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt >= webhook.retries => return Err(err),
+                Err(err) => {
+                    log::debug!(
+                        "Webhook {} attempt {} failed: {:#}",
+                        webhook.name(),
+                        attempt + 1,
+                        err
+                    );
+                    tokio::time::sleep(Duration::from_secs(1 << attempt.min(6))).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        Json, Router,
+        extract::State,
+        http::{StatusCode, Uri},
+    };
+
+    use super::*;
+
+    /// Bodies received by the test server with their paths
+    type Received = Arc<Mutex<Vec<(String, Value)>>>;
+
+    /// Server recording every request, requests to /fail get 500
+    async fn webhook_server() -> (String, Received) {
+        let received = Received::default();
+        let router = Router::new()
+            .fallback(
+                |State(received): State<Received>, uri: Uri, Json(body): Json<Value>| async move {
+                    let path = uri.path().to_string();
+                    let status = if path == "/fail" {
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    } else {
+                        StatusCode::NO_CONTENT
+                    };
+                    received.lock().unwrap().push((path, body));
+                    status
+                },
+            )
+            .with_state(received.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        (format!("http://{}", address), received)
+    }
+
+    fn webhook(url: String, events: Vec<EventKind>, retries: u32) -> Webhook {
+        Webhook {
+            url,
+            format: WebhookFormat::Json,
+            events,
+            retries,
+        }
+    }
+
+    fn notifier(webhooks: Vec<Webhook>) -> Notifier {
+        Notifier {
+            webhooks,
+            client: Some(reqwest::Client::new()),
+        }
+    }
+
+    fn failed_update() -> Event {
+        Event {
+            from: Some("1.1.0".to_string()),
+            to: Some("1.1.1".to_string()),
+            duration: Some(Duration::from_millis(2500)),
+            error: Some("Unable to apply patch".to_string()),
+            ..Event::new(
+                EventKind::UpdateFailed,
+                "/opt/factorio".to_string(),
+                "linux64".to_string(),
+            )
+        }
+    }
+
+    #[test]
+    fn json_payload_contains_event() {
+        let payload = failed_update().to_json();
+
+        assert_eq!(payload["event"], "update_failed");
+        assert_eq!(payload["installation"], "/opt/factorio");
+        assert_eq!(payload["arch"], "linux64");
+        assert_eq!(payload["from"], "1.1.0");
+        assert_eq!(payload["to"], "1.1.1");
+        assert_eq!(payload["duration_seconds"], 2.5);
+        assert_eq!(payload["error"], "Unable to apply patch");
+        assert!(payload["timestamp"].as_u64().unwrap() > 0);
+
+        let payload = Event::new(EventKind::Test, String::new(), String::new()).to_json();
+        assert!(payload["from"].is_null());
+        assert!(payload["error"].is_null());
+    }
+
+    #[test]
+    fn discord_payload_has_embed_fields() {
+        let payload = failed_update().to_discord();
+        let embed = &payload["embeds"][0];
+
+        assert_eq!(embed["title"], "Factorio update failed");
+        assert_eq!(embed["color"], 0xe74c3c);
+
+        let fields = embed["fields"].as_array().unwrap();
+        let names = fields
+            .iter()
+            .map(|field| field["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["Installation", "Arch", "From", "To", "Duration", "Error"]
+        );
+        assert_eq!(fields[4]["value"], "2.5s");
+        assert_eq!(fields[5]["value"], "```Unable to apply patch```");
+
+        //optional fields are left out
+        let payload = Event::new(EventKind::Test, String::new(), String::new()).to_discord();
+        assert_eq!(payload["embeds"][0]["fields"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn discord_error_field_fits_limit() {
+        let event = Event {
+            error: Some("é".repeat(5000)),
+            ..failed_update()
+        };
+
+        let payload = event.to_discord();
+        let fields = payload["embeds"][0]["fields"].as_array().unwrap();
+        let error = fields.last().unwrap()["value"].as_str().unwrap();
+
+        assert!(error.chars().count() <= 1024);
+        assert!(error.starts_with("```é") && error.ends_with("é```"));
+    }
+
+    #[test]
+    fn name_hides_url_path() {
+        let hook = webhook(
+            "https://discord.com/api/webhooks/1/secret".to_string(),
+            Vec::new(),
+            0,
+        );
+        assert_eq!(hook.name(), "https://discord.com");
+
+        let hook = webhook("example.com/hook".to_string(), Vec::new(), 0);
+        assert_eq!(hook.name(), "example.com");
+    }
+
+    #[tokio::test]
+    async fn events_are_filtered_per_webhook() {
+        let (url, received) = webhook_server().await;
+        let notifier = notifier(vec![
+            webhook(format!("{}/all", url), Vec::new(), 0),
+            webhook(
+                format!("{}/failures", url),
+                vec![EventKind::UpdateFailed],
+                0,
+            ),
+        ]);
+        let started = Event::new(
+            EventKind::UpdateStarted,
+            "/opt/factorio".to_string(),
+            "linux64".to_string(),
+        );
+
+        assert_eq!(notifier.send(&started).await, 0);
+        assert_eq!(notifier.send(&failed_update()).await, 0);
+        //test event goes to every webhook
+        let test = Event::new(EventKind::Test, String::new(), String::new());
+        assert_eq!(notifier.send(&test).await, 0);
+
+        let received = received.lock().unwrap();
+        let events = received
+            .iter()
+            .map(|(path, body)| (path.as_str(), body["event"].as_str().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                ("/all", "update_started"),
+                ("/all", "update_failed"),
+                ("/failures", "update_failed"),
+                ("/all", "test"),
+                ("/failures", "test"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_webhook_is_retried_and_counted() {
+        let (url, received) = webhook_server().await;
+        let notifier = notifier(vec![
+            webhook(format!("{}/fail", url), Vec::new(), 1),
+            webhook(format!("{}/ok", url), Vec::new(), 1),
+        ]);
+
+        assert_eq!(notifier.send(&failed_update()).await, 1);
+
+        let paths = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        //one attempt and one retry of the failing webhook, others are still sent
+        assert_eq!(paths, ["/fail", "/fail", "/ok"]);
+    }
+
+    #[tokio::test]
+    async fn nothing_is_sent_offline() {
+        let (url, received) = webhook_server().await;
+        let notifier = Notifier {
+            webhooks: vec![webhook(url, Vec::new(), 0)],
+            client: None,
+        };
+
+        assert!(!notifier.is_enabled());
+        assert_eq!(notifier.send(&failed_update()).await, 0);
+        assert!(received.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+//shared by all test binaries, each of them uses only a part of it
+#![allow(dead_code)]
+
+use std::{net::SocketAddr, path::Path, sync::Once};
+
+use clap::Parser;
+use factorio_updater::{
+    server::fake::FakeServer,
+    structs::{Cli, Command},
+};
+use tempdir::TempDir;
+
+/// Game of fixture trees, applies whole-file update packages of the fake server (including
+/// removals listed in their info.json) and lists them in applied-updates.txt
+const FAKE_GAME: &str = r#"#!/bin/sh
+[ "$1" = "--apply-update" ] || exit 1
+tmp=$(mktemp -d)
+unzip -q "$2" -d "$tmp" || exit 1
+awk '/"removed"/ { list = !/]/; next } list && /]/ { list = 0 } list { gsub(/^ *"|",? *$/, ""); print }' \
+    "$tmp"/*/info.json | while IFS= read -r file; do rm -f "./$file"; done
+rm "$tmp"/*/info.json
+cp -R "$tmp"/*/. . && rm -r "$tmp"
+basename "$2" >> applied-updates.txt
+"#;
+
+static ISOLATE: Once = Once::new();
+
+/// Points config, cache and data folders into a folder of the test binary under cargo's
+/// target folder, so tests never touch the real ones. It's emptied on every run.
+pub fn isolate() {
+    ISOLATE.call_once(|| {
+        let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join(env!("CARGO_CRATE_NAME"));
+        if root.exists() {
+            std::fs::remove_dir_all(&root).expect("old test folder removed");
+        }
+
+        //SAFETY: set once, before any test reads them
+        unsafe {
+            std::env::set_var("XDG_CONFIG_HOME", root.join("config"));
+            std::env::set_var("XDG_CACHE_HOME", root.join("cache"));
+            std::env::set_var("XDG_DATA_HOME", root.join("data"));
+        }
+    });
+}
+
+pub fn write(path: &Path, data: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, data).unwrap();
+}
+
+/// Fixture tree of package version with the fake game and given files
+pub fn game_fixture(fixtures: &Path, package: &str, version: &str, files: &[(&str, &str)]) {
+    let tree = fixtures.join(package).join(version);
+
+    let game = tree.join("bin/x64/factorio");
+    write(&game, FAKE_GAME);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&game, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    for (name, data) in files {
+        write(&tree.join(name), data);
+    }
+}
+
+pub async fn start(fixtures: &Path) -> FakeServer {
+    isolate();
+
+    FakeServer::start(
+        fixtures.to_path_buf(),
+        SocketAddr::from(([127, 0, 0, 1], 0)),
+    )
+    .await
+    .expect("fake server")
+}
+
+/// Parses command line like the binary does
+pub fn command(args: &[&str]) -> Command {
+    Cli::try_parse_from(std::iter::once("factorio-updater").chain(args.iter().copied()))
+        .expect("valid arguments")
+        .command
+        .expect("command")
+}
+
+/// Temporary folder, removed when dropped
+pub fn temp(name: &str) -> TempDir {
+    TempDir::new(name).expect("temp dir")
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use std::path::Path;
+
+use common::{command, game_fixture, start, temp, write};
+use factorio_updater::{steps::handle_update, structs::Command};
+
+const HEADLESS: &str = "core-linux_headless64";
+
+fn users(fixtures: &Path, stable: &str) {
+    write(
+        &fixtures.join("fake.json"),
+        &format!(
+            r#"{{"stable": {{"{}": "{}"}}, "users": {{"user": {{"password": "pass", "token": "token"}}}}}}"#,
+            HEADLESS, stable
+        ),
+    );
+}
+
+async fn update(fixtures: &Path, folder: &Path, token: &str) -> anyhow::Result<()> {
+    let server = start(fixtures).await;
+    let Command::Update(args) = command(&[
+        "update",
+        "--platform",
+        "headless",
+        "--username",
+        "user",
+        "--token",
+        token,
+        "--custom-folder",
+        folder.to_str().unwrap(),
+        "--yes",
+    ]) else {
+        unreachable!();
+    };
+
+    handle_update(args, server.endpoints(), None).await
+}
+
+fn installed_version(folder: &Path) -> String {
+    let config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(folder.join("config.json")).unwrap())
+            .unwrap();
+    config["current_version"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn installs_full_package_then_applies_patch_chain() {
+    let fixtures = temp("fixtures");
+    game_fixture(
+        fixtures.path(),
+        HEADLESS,
+        "1.0.0",
+        &[("data/base/info.json", "1.0.0"), ("data/old.txt", "old")],
+    );
+    game_fixture(
+        fixtures.path(),
+        HEADLESS,
+        "1.0.1",
+        &[("data/base/info.json", "1.0.1"), ("data/old.txt", "old")],
+    );
+    game_fixture(
+        fixtures.path(),
+        HEADLESS,
+        "1.0.2",
+        &[("data/base/info.json", "1.0.2"), ("data/new.txt", "new")],
+    );
+    let installation = temp("installation");
+    let folder = installation.path().join("factorio");
+
+    //nothing to patch from yet, so resolving picks the full package
+    users(fixtures.path(), "1.0.0");
+    update(fixtures.path(), &folder, "token").await.unwrap();
+
+    assert_eq!(installed_version(&folder), "1.0.0");
+    assert_eq!(
+        std::fs::read_to_string(folder.join("data/base/info.json")).unwrap(),
+        "1.0.0"
+    );
+    //config of installation can hold RCON password
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let metadata = std::fs::metadata(folder.join("config.json")).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+    }
+
+    //the game applies both patches of the chain in order
+    users(fixtures.path(), "1.0.2");
+    update(fixtures.path(), &folder, "token").await.unwrap();
+
+    assert_eq!(installed_version(&folder), "1.0.2");
+    assert_eq!(
+        std::fs::read_to_string(folder.join("data/base/info.json")).unwrap(),
+        "1.0.2"
+    );
+    assert_eq!(
+        std::fs::read_to_string(folder.join("data/new.txt")).unwrap(),
+        "new"
+    );
+    //removed by the second patch of the chain
+    assert!(!folder.join("data/old.txt").exists());
+    assert!(!folder.join("info.json").exists());
+    assert_eq!(
+        std::fs::read_to_string(folder.join("applied-updates.txt")).unwrap(),
+        "factorio_core-linux_headless64_patch_v1.0.0_to_v1.0.1.zip\n\
+         factorio_core-linux_headless64_patch_v1.0.1_to_v1.0.2.zip\n"
+    );
+}
+
+#[tokio::test]
+async fn patch_failure_keeps_installed_version() {
+    let fixtures = temp("fixtures");
+    game_fixture(
+        fixtures.path(),
+        HEADLESS,
+        "2.0.0",
+        &[("data/base/info.json", "2.0.0")],
+    );
+    game_fixture(
+        fixtures.path(),
+        HEADLESS,
+        "2.0.1",
+        &[("data/base/info.json", "2.0.1")],
+    );
+    let installation = temp("installation");
+    let folder = installation.path().join("factorio");
+
+    users(fixtures.path(), "2.0.0");
+    update(fixtures.path(), &folder, "token").await.unwrap();
+
+    //installation without a game, which could apply the patch
+    std::fs::remove_dir_all(folder.join("bin")).unwrap();
+    users(fixtures.path(), "2.0.1");
+    let err = update(fixtures.path(), &folder, "token").await.unwrap_err();
+
+    assert!(format!("{:#}", err).contains("No game binary"));
+    assert_eq!(installed_version(&folder), "2.0.0");
+}
+
+#[tokio::test]
+async fn rejects_invalid_token() {
+    let fixtures = temp("fixtures");
+    game_fixture(fixtures.path(), HEADLESS, "3.0.0", &[]);
+    users(fixtures.path(), "3.0.0");
+    let installation = temp("installation");
+    let folder = installation.path().join("factorio");
+
+    assert!(update(fixtures.path(), &folder, "wrong").await.is_err());
+    assert!(!folder.join("config.json").exists());
+}